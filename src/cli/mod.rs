@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    ops::RangeInclusive,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    compiler, objdump,
+    output::OutputFormat,
+    vm::{
+        loader::{load_ihex, load_raw},
+        machine::Machine,
+        sfile::SFile,
+    },
+};
+
+const USAGE: &str = "usage:
+  svm asm <file> <out> [--format raw|ihex|hexdump|sfile] [--base ADDRESS]
+  svm objdump <file>
+  svm export <file> [out.json]
+  svm import <file.json> <out>
+  svm run <file> [--cores N] [--quantum N] [--memory BYTES] [--raise LINE] [--clock TICKS]";
+
+//Bounds of svm run, every core is a host thread and guest memory is allocated up front
+const CORES: RangeInclusive<u64> = 1..=256;
+const MEMORY: RangeInclusive<u64> = 0..=1 << 30;
+
+pub(crate) fn run(args: Vec<String>) -> Result<(), String> {
+    match args.get(1).map(String::as_str) {
+        Some("asm") => cmd_asm(&args[2..]),
+        Some("objdump") => cmd_objdump(&args[2..]),
+        Some("export") => cmd_export(&args[2..]),
+        Some("import") => cmd_import(&args[2..]),
+        Some("run") => cmd_run(&args[2..]),
+        _ => Err(USAGE.to_string()),
+    }
+}
+//Raw code unless a format is given, the entry is the first instruction
+fn cmd_asm(args: &[String]) -> Result<(), String> {
+    let (path, out) = match args {
+        [path, out, ..] => (path, out),
+        _ => return Err(USAGE.to_string()),
+    };
+    let (mut format, mut base) = (OutputFormat::Raw, 0);
+    for pair in args[2..].chunks(2) {
+        let val = match pair {
+            [_, val] => val,
+            _ => return Err(USAGE.to_string()),
+        };
+        match pair[0].as_str() {
+            "--format" => format = OutputFormat::from(val.as_str()),
+            "--base" => base = val.parse::<u64>().map_err(|_| USAGE)?,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let text = String::from_utf8(read(path)?).map_err(|err| format!("{}: {}", path, err))?;
+    let bytes = compiler::compile_to(text.lines().collect(), format, base, base)
+        .map_err(|err| format!("{}: {:?}", path, err))?;
+    write(out, &bytes)
+}
+fn cmd_objdump(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or(USAGE)?;
+    let bytes = read(path)?;
+    print!("{}:     file format sfile\n\n", path);
+    print!("{}", objdump::inspect(&bytes));
+    Ok(())
+}
+fn cmd_export(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or(USAGE)?;
+    let file = SFile::from_bytes(&read(path)?).map_err(|err| format!("{}: {:?}", path, err))?;
+    let text = file.to_json().map_err(|err| format!("{}: {}", path, err))?;
+    match args.get(1) {
+        Some(out) => write(out, text.as_bytes()),
+        None => {
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+fn cmd_import(args: &[String]) -> Result<(), String> {
+    let (path, out) = match args {
+        [path, out, ..] => (path, out),
+        _ => return Err(USAGE.to_string()),
+    };
+    let text = String::from_utf8(read(path)?).map_err(|err| format!("{}: {}", path, err))?;
+    let file = SFile::from_json(&text).map_err(|err| format!("{}: {}", path, err))?;
+    let bytes = file
+        .to_bytes()
+        .map_err(|err| format!("{}: {:?}", out, err))?;
+    write(out, &bytes)
+}
+//Intel HEX or raw code loaded at 0, threaded unless a quantum asks for an interleaved run.
+//--raise leaves an external interrupt line pending on core 0 at the start, --clock advances
+//the virtual time of every core by that many ticks a millisecond
+fn cmd_run(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or(USAGE)?;
+    let (mut cores, mut quantum, mut memory) = (1, None, 1 << 20);
+    let (mut lines, mut clock) = (Vec::new(), 0);
+    for pair in args[1..].chunks(2) {
+        let val = match pair {
+            [_, val] => val.parse::<u64>().map_err(|_| USAGE)?,
+            _ => return Err(USAGE.to_string()),
+        };
+        match pair[0].as_str() {
+            "--cores" => cores = bounded(val, CORES)?,
+            "--quantum" => quantum = Some(val),
+            "--memory" => memory = bounded(val, MEMORY)?,
+            "--raise" if val < 64 => lines.push(val as u32),
+            "--clock" => clock = val,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let bytes = read(path)?;
+    let image = match bytes.first() {
+        Some(b':') => load_ihex(&String::from_utf8_lossy(&bytes), 0, 0),
+        _ => load_raw(&bytes, 0, 0),
+    }
+    .map_err(|err| format!("{}: {:?}", path, err))?;
+    let mut machine = Machine::new(cores, memory);
+    machine.load(image);
+    let irqs: Vec<_> = machine.cores.iter().map(|core| core.irq.handle()).collect();
+    for line in lines {
+        irqs[0].raise(line);
+    }
+    let done = AtomicBool::new(false);
+    let result = thread::scope(|s| {
+        if clock > 0 {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(1));
+                    irqs.iter().for_each(|irq| irq.advance(clock));
+                }
+            });
+        }
+        let result = match quantum {
+            Some(quantum) => machine.run_interleaved(quantum),
+            None => machine.run_threaded(),
+        };
+        done.store(true, Ordering::Relaxed);
+        result
+    });
+    result.map_err(|(core, err)| format!("{}: core {}: {:?}", path, core, err))?;
+    let core = &machine.cores[0];
+    println!("r0 {:#x}\nr1 {:#x}\nr2 {:#x}", core.r0, core.r1, core.r2);
+    Ok(())
+}
+fn bounded(val: u64, range: RangeInclusive<u64>) -> Result<usize, String> {
+    usize::try_from(val)
+        .ok()
+        .filter(|_| range.contains(&val))
+        .ok_or_else(|| USAGE.to_string())
+}
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("{}: {}", path, err))
+}
+fn write(path: &str, bytes: &[u8]) -> Result<(), String> {
+    fs::write(path, bytes).map_err(|err| format!("{}: {}", path, err))
+}
@@ -1,21 +1,233 @@
 /*
 Syntax:
 opcode reg0 reg1 data
+opcode reg0 reg1 reg2 data (three-register form of a destructive op)
+label:
+
+Instructions use the compact encoding when the operands fit and the extended one otherwise.
+assemble takes a label or a signed offset in source instructions as the target of jump
+opcodes, adr and jl. Targets that no longer fit 16 bits move to the extended form, nop zr zr
+to a jl.
 */
 
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::HashMap;
+
+use crate::{
+    instruction::Instruction,
+    opcode::Opcode,
+    output::{self, OutputError, OutputFormat},
+};
 
-use crate::instruction::Instruction;
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AsmError {
+    UnknownLabel(usize),   //Target is neither a label nor a number (line)
+    DuplicateLabel(usize), //Label defined twice (line)
+    OutOfRange(usize),     //Target outside the program (line)
+    Output(OutputError),   //Assembled code doesn't fit the output format
+}
 
-pub(crate) fn compile_line(line: &str) -> u32 {
-    Instruction::from(line).into()
+//Targets are indices into the source instructions, the end of the program included
+enum Item {
+    Fixed(Instruction),
+    Target(Instruction, usize),
+}
+
+//Text and length in words of every instruction by word offset
+pub(crate) fn decompile(words: &[u32]) -> Vec<(usize, (String, usize))> {
+    Instruction::decode_all(words)
+        .into_iter()
+        .map(|(k, v)| (k, (String::from(v), v.len())))
+        .collect()
+}
+pub(crate) fn compile_to(
+    lines: Vec<&str>,
+    format: OutputFormat,
+    base: u64,
+    entry: u64,
+) -> Result<Vec<u8>, AsmError> {
+    output::emit(&assemble(lines)?, format, base, entry).map_err(AsmError::Output)
+}
+pub(crate) fn assemble(lines: Vec<&str>) -> Result<Vec<u32>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut source = Vec::new();
+    for (line, text) in lines.iter().enumerate() {
+        let text = text.trim();
+        if let Some(label) = text.strip_suffix(':') {
+            if labels.insert(label, source.len()).is_some() {
+                return Err(AsmError::DuplicateLabel(line));
+            }
+        } else if !text.is_empty() {
+            source.push((line, text));
+        }
+    }
+    let mut items = Vec::new();
+    for (k, (line, text)) in source.iter().enumerate() {
+        items.push(parse_item(*line, text, k, source.len(), &labels)?);
+    }
+    Ok(emit(&items, &relax(&items)))
 }
-pub(crate) fn compile_lines(lines: Vec<&str>) -> Vec<u32> {
-    lines.iter().map(|v| compile_line(v)).collect()
+fn parse_item(
+    line: usize,
+    text: &str,
+    index: usize,
+    len: usize,
+    labels: &HashMap<&str, usize>,
+) -> Result<Item, AsmError> {
+    let ins = Instruction::from(text);
+    let opcode = Opcode::from(ins.opcode);
+    if !opcode.has_offset() {
+        return Ok(Item::Fixed(ins));
+    }
+    let position = match opcode {
+        Opcode::Jl => 1,
+        _ => 3 + ins.reg2().is_some() as usize,
+    };
+    let operand = match text.split(' ').nth(position) {
+        Some(v) => v,
+        None => return Ok(Item::Fixed(ins)),
+    };
+    let target = match labels.get(operand) {
+        Some(target) => *target as i64,
+        None => {
+            let offset = operand
+                .parse::<i64>()
+                .map_err(|_| AsmError::UnknownLabel(line))?;
+            index as i64 + 1 + offset
+        }
+    };
+    let target = usize::try_from(target)
+        .ok()
+        .filter(|v| *v <= len)
+        .ok_or(AsmError::OutOfRange(line))?;
+    Ok(Item::Target(ins, target))
 }
-pub(crate) fn compile_lines_par(lines: Vec<&str>) -> Vec<u32> {
-    lines.par_iter().map(|v| compile_line(*v)).collect()
+//Sizes in words, they only ever grow so this settles. A form shorter than its size is
+//preceded by nops, which keeps the end of every item, and so its offset, in place
+fn relax(items: &[Item]) -> Vec<usize> {
+    let mut sizes: Vec<usize> = items.iter().map(|v| place(v, 0).len()).collect();
+    loop {
+        let addresses = layout(&sizes);
+        let mut changed = false;
+        for (k, item) in items.iter().enumerate() {
+            let size = place(item, offset(item, k, &addresses)).len();
+            if size > sizes[k] {
+                sizes[k] = size;
+                changed = true;
+            }
+        }
+        if !changed {
+            return sizes;
+        }
+    }
 }
-pub(crate) fn decompile_line(line: u32) -> String {
-    String::from(Instruction::from(line))
+fn emit(items: &[Item], sizes: &[usize]) -> Vec<u32> {
+    let addresses = layout(sizes);
+    let mut out = Vec::new();
+    for (k, item) in items.iter().enumerate() {
+        let words = place(item, offset(item, k, &addresses)).words();
+        let nop = u32::from(Instruction::new(Opcode::Nop.into(), 0, 0));
+        out.extend(std::iter::repeat_n(nop, sizes[k] - words.len()));
+        out.extend(words);
+    }
+    out
+}
+//The encoding of an item for a given offset to its target
+fn place(item: &Item, offset: i64) -> Instruction {
+    let ins = match item {
+        Item::Fixed(ins) => return *ins,
+        Item::Target(ins, _) => ins,
+    };
+    let (reg0, reg1) = ins.strip_registers();
+    //nop zr zr only jumps, so jl can stand in for it
+    if ins.opcode == u8::from(Opcode::Nop)
+        && reg0 == 0
+        && reg1 == 0
+        && i16::try_from(offset).is_err()
+    {
+        return Instruction::encode(Opcode::Jl.into(), 0, 0, None, offset as u64);
+    }
+    Instruction::encode(ins.opcode, reg0, reg1, ins.reg2(), offset as u64)
+}
+//Address of every item plus the end of the program
+fn layout(sizes: &[usize]) -> Vec<u64> {
+    let mut addresses = vec![0];
+    for size in sizes {
+        addresses.push(addresses[addresses.len() - 1] + *size as u64);
+    }
+    addresses
+}
+//Relative to the end of the item, 0 for fixed items
+fn offset(item: &Item, k: usize, addresses: &[u64]) -> i64 {
+    match item {
+        Item::Fixed(_) => 0,
+        Item::Target(_, target) => addresses[*target] as i64 - addresses[k + 1] as i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_resolve_to_offsets() {
+        let code = assemble(vec![
+            "top:",
+            "nop zr zr end",
+            "dadd r0 zr 1",
+            "",
+            "nop zr zr top",
+            "jl top",
+            "end:",
+        ])
+        .unwrap();
+        let data: Vec<u16> = code.iter().map(|v| Instruction::from(*v).data).collect();
+        assert_eq!(data, [3, 1, (-3i16) as u16, (-4i16) as u16]);
+        assert_eq!(Instruction::from(code[3]).long_offset(), -4);
+        assert_eq!(
+            assemble(vec!["nop zr zr 1", "nop zr zr -2"]),
+            Ok(vec![0x0001_0000, 0xFFFE_0000])
+        );
+        assert_eq!(
+            assemble(vec!["a:", "nop", "a:"]),
+            Err(AsmError::DuplicateLabel(2))
+        );
+        assert_eq!(
+            assemble(vec!["nop zr zr nowhere"]),
+            Err(AsmError::UnknownLabel(0))
+        );
+        assert_eq!(assemble(vec!["nop", "jl 1"]), Err(AsmError::OutOfRange(1)));
+        assert_eq!(assemble(vec!["nop zr zr -2"]), Err(AsmError::OutOfRange(0)));
+    }
+    #[test]
+    fn jumps_past_16_bits_are_relaxed() {
+        let mut lines = vec![
+            "dadd r0 zr 1",
+            "nop zr zr fwd",
+            "back:",
+            "dadd r1 zr 9",
+            "nop zr zr end",
+        ];
+        lines.extend(std::iter::repeat_n("dadd r2 zr 7", 40000));
+        lines.extend(["fwd:", "inc r0 zr back", "end:"]);
+        let code = assemble(lines).unwrap();
+        //Both plain jumps become jl, the inc takes the extended form with a negative immediate
+        let jl = u8::from(Opcode::Jl);
+        assert_eq!(Instruction::from(code[1]).opcode, jl);
+        assert_eq!(Instruction::from(code[1]).long_offset(), 40002);
+        assert_eq!(Instruction::from(code[3]).opcode, jl);
+        let inc = Instruction::decode(&code[40004..]).unwrap();
+        assert_eq!(inc.opcode, u8::from(Opcode::Inc));
+        assert_eq!(inc.immediate() as i64, -(40004 + 3 - 2));
+        assert_eq!(code.len(), 40004 + 3);
+        //A numeric offset past i16 jumps forward as well
+        let mut lines = vec!["nop zr zr 40000"];
+        lines.extend(std::iter::repeat_n("dadd r2 zr 7", 40001));
+        let code = assemble(lines).unwrap();
+        assert_eq!(Instruction::from(code[0]).opcode, jl);
+        assert_eq!(Instruction::from(code[0]).long_offset(), 40000);
+        assert_eq!(
+            assemble(vec!["nop zr zr 65535"]),
+            Err(AsmError::OutOfRange(0))
+        );
+    }
 }
@@ -1,64 +1,382 @@
-use crate::{
-    opcode,
-    register::{self, Register},
-};
+use serde::{Deserialize, Serialize};
 
+use crate::{lane, opcode::Opcode, register};
+
+/*
+Compact form, one word:
+    opcode u8 | regs u8 (reg0 low nibble, reg1 high nibble) | data u16
+Extended form, a prefix word, an operand word and 0-2 immediate words:
+    ext (255) u8 | opcode u8 | flags u16 (bits 0-1 immediate words, bit 2 reg2 present)
+    reg0 u8 | reg1 u8 | reg2 u8 | 0
+The immediate replaces data, one immediate word is sign extended for pc-relative offsets
+and zero extended otherwise.
+Register indices past 15 (r3-r31, f3-f31, s3-s7, x3-x7, see register::BANKS) only fit the
+extended form, masks and lane indices inside data stay compact registers.
+*/
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(into = "DecodedInstruction", try_from = "DecodedInstruction")]
 pub(crate) struct Instruction {
     pub opcode: u8,
     pub regs: u8,
     pub data: u16,
+    pub ext: Option<Extension>,
+}
+
+//Operands of the extended form, regs and data are unused then
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Extension {
+    pub reg0: u8,
+    pub reg1: u8,
+    pub reg2: Option<u8>, //Three-register form of a destructive op
+    pub imm: u64,
 }
 
+const EXT_REG2: u16 = 1 << 2;
+
 impl Instruction {
     pub(crate) fn new(opcode: u8, regs: u8, data: u16) -> Self {
-        Self { opcode, regs, data }
+        Self {
+            opcode,
+            regs,
+            data,
+            ext: None,
+        }
+    }
+    pub(crate) fn extended(opcode: u8, reg0: u8, reg1: u8, reg2: Option<u8>, imm: u64) -> Self {
+        Self {
+            ext: Some(Extension {
+                reg0,
+                reg1,
+                reg2,
+                imm,
+            }),
+            ..Self::new(opcode, 0, 0)
+        }
+    }
+    //The compact form whenever the operands fit it
+    pub(crate) fn encode(opcode: u8, reg0: u8, reg1: u8, reg2: Option<u8>, imm: u64) -> Self {
+        let op = Opcode::from(opcode);
+        if reg2.is_some() || reg0 > 0xF || reg1 > 0xF {
+            return Self::extended(opcode, reg0, reg1, reg2, imm);
+        }
+        //jl always reads its offset from the register and data fields, even a short one
+        if op == Opcode::Jl && (-(1 << 23)..1 << 23).contains(&(imm as i64)) {
+            return Self::long_jump(imm as i32);
+        }
+        let data = if op.has_offset() {
+            i16::try_from(imm as i64).ok().map(|v| v as u16)
+        } else {
+            u16::try_from(imm).ok()
+        };
+        match data {
+            Some(data) => Self::new(opcode, (reg1 << 4) | reg0, data),
+            None => Self::extended(opcode, reg0, reg1, reg2, imm),
+        }
     }
     pub(crate) fn strip_registers(&self) -> (u8, u8) {
-        (self.regs & 0b00001111, self.regs >> 4)
+        match self.ext {
+            Some(ext) => (ext.reg0, ext.reg1),
+            None => (self.regs & 0b00001111, self.regs >> 4),
+        }
+    }
+    pub(crate) fn reg2(&self) -> Option<u8> {
+        self.ext.and_then(|ext| ext.reg2)
+    }
+    //data widened to 64 bits, offsets sign extended
+    pub(crate) fn immediate(&self) -> u64 {
+        let opcode = Opcode::from(self.opcode);
+        match self.ext {
+            Some(ext) => ext.imm,
+            None if opcode == Opcode::Jl => self.long_offset() as i64 as u64,
+            None if opcode.has_offset() => self.data as i16 as i64 as u64,
+            None => self.data as u64,
+        }
+    }
+    //jl packs a signed 24-bit offset into the register and data fields
+    pub(crate) fn long_jump(offset: i32) -> Self {
+        let val = offset as u32;
+        Self::new(Opcode::Jl.into(), (val >> 16) as u8, val as u16)
+    }
+    pub(crate) fn long_offset(&self) -> i32 {
+        (((self.regs as u32) << 24 | (self.data as u32) << 8) as i32) >> 8
+    }
+    //Length in words
+    pub(crate) fn len(&self) -> usize {
+        match self.ext {
+            Some(ext) => 2 + imm_words(self.opcode, ext.imm),
+            None => 1,
+        }
+    }
+    pub(crate) fn words(&self) -> Vec<u32> {
+        let ext = match self.ext {
+            Some(ext) => ext,
+            None => return vec![u32::from(*self)],
+        };
+        let count = imm_words(self.opcode, ext.imm);
+        let flags = count as u16 | if ext.reg2.is_some() { EXT_REG2 } else { 0 };
+        let mut out = vec![
+            u32::from(Instruction::new(Opcode::Ext.into(), self.opcode, flags)),
+            (ext.reg2.unwrap_or(0) as u32) << 16 | (ext.reg1 as u32) << 8 | ext.reg0 as u32,
+        ];
+        out.extend([ext.imm as u32, (ext.imm >> 32) as u32].iter().take(count));
+        out
+    }
+    //None at the end of the stream or for a truncated extended form
+    pub(crate) fn decode(words: &[u32]) -> Option<Self> {
+        let first = Instruction::from(*words.first()?);
+        if first.opcode != u8::from(Opcode::Ext) {
+            return Some(first);
+        }
+        let (opcode, flags) = (first.regs, first.data);
+        let operand = *words.get(1)?;
+        let imm = match words.get(2..2 + (flags & 3) as usize)? {
+            [] => 0,
+            [v] if Opcode::from(opcode).has_offset() => *v as i32 as i64 as u64,
+            [v] => *v as u64,
+            [low, high] => (*high as u64) << 32 | *low as u64,
+            _ => return None,
+        };
+        let reg2 = (flags & EXT_REG2 != 0).then_some((operand >> 16) as u8);
+        Some(Self::extended(
+            opcode,
+            operand as u8,
+            (operand >> 8) as u8,
+            reg2,
+            imm,
+        ))
+    }
+    //Words read back from the JSON form, None if the mnemonic or registers don't survive it
+    pub(crate) fn json_words(&self) -> Option<Vec<u32>> {
+        Instruction::try_from(DecodedInstruction::from(*self))
+            .ok()
+            .map(|v| v.words())
+    }
+    //Instructions with their word offsets, a truncated extended form is kept as single words
+    pub(crate) fn decode_all(words: &[u32]) -> Vec<(usize, Self)> {
+        let mut out = Vec::new();
+        let mut k = 0;
+        while k < words.len() {
+            let ins = Self::decode(&words[k..]).unwrap_or_else(|| Self::from(words[k]));
+            out.push((k, ins));
+            k += ins.len();
+        }
+        out
     }
 }
+//Minimal immediate words of the extended form
+fn imm_words(opcode: u8, imm: u64) -> usize {
+    let fits = if Opcode::from(opcode).has_offset() {
+        i32::try_from(imm as i64).is_ok()
+    } else {
+        u32::try_from(imm).is_ok()
+    };
+    match imm {
+        0 => 0,
+        _ if fits => 1,
+        _ => 2,
+    }
+}
+//First word only, extended forms go through words
 impl From<Instruction> for u32 {
     fn from(val: Instruction) -> Self {
-        ((val.data as u32) << 16) & ((val.regs as u32) << 8) & (val.opcode as u32)
+        ((val.data as u32) << 16) | ((val.regs as u32) << 8) | (val.opcode as u32)
     }
 }
 impl From<u32> for Instruction {
     fn from(val: u32) -> Self {
-        Self {
-            opcode: (val as u8),
-            regs: ((val >> 8) as u8),
-            data: ((val >> 16) as u16),
-        }
+        Self::new(val as u8, (val >> 8) as u8, (val >> 16) as u16)
     }
 }
 impl From<&str> for Instruction {
     fn from(val: &str) -> Self {
-        let mut opcode = 0u8;
-        let mut reg0 = 0u8;
-        let mut reg1 = 0u8;
-        let mut data = 0u16;
-        for (k, v) in val.to_string().split(' ').enumerate() {
-            if k == 0 {
-                opcode = opcode::Opcode::from(v).into();
-            } else if k == 1 {
-                reg0 = register::Register::from(v).into();
-            } else if k == 2 {
-                reg1 = register::Register::from(v).into()
-            } else if k == 3 {
-                data = v.parse().unwrap_or(0);
-            }
+        let tokens: Vec<&str> = val.split(' ').collect();
+        let opcode = Opcode::from(tokens[0]);
+        if opcode == Opcode::Jl {
+            let offset = tokens.get(1).and_then(|v| v.trim().parse::<i64>().ok());
+            return Self::encode(opcode.into(), 0, 0, None, offset.unwrap_or(0) as u64);
         }
-        Instruction::new(opcode, (reg1 << 4) & reg0, data)
+        let reg = |k: usize| tokens.get(k).and_then(|v| register::index(v)).unwrap_or(0);
+        let mut rest = tokens.get(3..).unwrap_or_default();
+        //A register where a destructive op takes its data makes the three-register form
+        let reg2 = match rest.first().and_then(|v| register::index(v)) {
+            Some(index) if opcode.is_binary() => {
+                rest = &rest[1..];
+                Some(index)
+            }
+            _ => None,
+        };
+        let imm = rest.first().map_or(0, |v| parse_data(v));
+        Self::encode(opcode.into(), reg(1), reg(2), reg2, imm)
+    }
+}
+//Three-register forms take a register name, vector ops a lane type
+fn parse_data(val: &str) -> u64 {
+    //One signed width covers both i64 and u64, encode picks the form from the value
+    let number = val.parse::<i128>().ok();
+    if let Some(imm) = number.filter(|v| (i64::MIN as i128..=u64::MAX as i128).contains(v)) {
+        return imm as u64;
+    }
+    match register::index(val) {
+        Some(index) => index as u64,
+        None => lane::parse_lane(val).unwrap_or(0) as u64,
     }
 }
 impl From<Instruction> for String {
     fn from(val: Instruction) -> Self {
-        format!(
-            "{} {} {} {}",
-            val.opcode,
-            u8::from(Register::from(val.regs & 0b00001111)),
-            u8::from(Register::from(val.regs >> 4)),
-            val.data
-        )
+        let opcode = Opcode::from(val.opcode);
+        if opcode == Opcode::Jl {
+            return format!("jl {}", val.immediate() as i64);
+        }
+        let (reg0, reg1) = val.strip_registers();
+        let mut out = format!(
+            "{} {} {}",
+            <&str>::from(opcode),
+            register::name(reg0),
+            register::name(reg1),
+        );
+        if let Some(reg2) = val.reg2() {
+            out += &format!(" {}", register::name(reg2));
+        }
+        match val.ext {
+            Some(ext) if opcode.has_offset() => out += &format!(" {}", ext.imm as i64),
+            Some(ext) => out += &format!(" {}", ext.imm),
+            None if opcode.has_offset() => out += &format!(" {}", val.data as i16),
+            None => out += &format!(" {}", val.data),
+        }
+        out
+    }
+}
+
+//Readable JSON form, mnemonics are checked on import instead of falling back to nop/zr
+//Extended forms carry imm (and reg2) instead of data
+#[derive(Serialize, Deserialize)]
+struct DecodedInstruction {
+    opcode: String,
+    reg0: String,
+    reg1: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reg2: Option<String>,
+    #[serde(default)]
+    data: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    imm: Option<u64>,
+}
+
+impl From<Instruction> for DecodedInstruction {
+    fn from(val: Instruction) -> Self {
+        let (reg0, reg1) = val.strip_registers();
+        Self {
+            opcode: <&str>::from(Opcode::from(val.opcode)).to_string(),
+            reg0: register::name(reg0),
+            reg1: register::name(reg1),
+            reg2: val.reg2().map(register::name),
+            data: val.data,
+            imm: val.ext.map(|ext| ext.imm),
+        }
+    }
+}
+impl TryFrom<DecodedInstruction> for Instruction {
+    type Error = String;
+    fn try_from(val: DecodedInstruction) -> Result<Self, Self::Error> {
+        let opcode = Opcode::from(val.opcode.as_str());
+        if <&str>::from(opcode) != val.opcode {
+            return Err(format!("unknown opcode {}", val.opcode));
+        }
+        let mut regs = [0u8; 3];
+        let names = [Some(&val.reg0), Some(&val.reg1), val.reg2.as_ref()];
+        for (k, name) in names.into_iter().enumerate() {
+            if let Some(name) = name {
+                regs[k] = register::index(name).ok_or(format!("unknown register {}", name))?;
+            }
+        }
+        Ok(match (val.imm, val.reg2) {
+            (None, None) if regs[0] <= 0xF && regs[1] <= 0xF => {
+                Instruction::new(opcode.into(), (regs[1] << 4) | regs[0], val.data)
+            }
+            (imm, reg2) => Instruction::extended(
+                opcode.into(),
+                regs[0],
+                regs[1],
+                reg2.map(|_| regs[2]),
+                imm.unwrap_or(0),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let ops = [
+            Opcode::Dadd,
+            Opcode::Add,
+            Opcode::Inc,
+            Opcode::Jl,
+            Opcode::Vadd,
+        ];
+        let regs = [
+            (0, 0, None),
+            (1, 2, None),
+            (16, 3, None),
+            (4, 83, None),
+            (1, 2, Some(45)),
+        ];
+        let imms = [
+            0,
+            1,
+            0x7FFF,
+            0x8000,
+            0xFFFF,
+            0x1_0000,
+            u32::MAX as u64,
+            u64::MAX,
+            -5i64 as u64,
+        ];
+        for op in ops {
+            for (reg0, reg1, reg2) in regs {
+                for imm in imms {
+                    let ins = Instruction::encode(op.into(), reg0, reg1, reg2, imm);
+                    let words = ins.words();
+                    assert_eq!(words.len(), ins.len(), "{:?} {:x}", op, imm);
+                    let back = Instruction::decode(&words).unwrap();
+                    assert_eq!(back.words(), words, "{:?} {:x}", op, imm);
+                    assert_eq!(back.opcode, u8::from(op));
+                    assert_eq!(back.immediate(), imm, "{:?} {:x}", op, imm);
+                    if op != Opcode::Jl || ins.ext.is_some() {
+                        assert_eq!(back.strip_registers(), (reg0, reg1), "{:?} {:x}", op, imm);
+                        assert_eq!(back.reg2(), reg2, "{:?} {:x}", op, imm);
+                    }
+                }
+            }
+        }
+        //Truncated extended forms don't decode
+        let words = Instruction::encode(Opcode::Dadd.into(), 20, 1, None, u64::MAX).words();
+        for len in 0..words.len() {
+            assert!(Instruction::decode(&words[..len]).is_none(), "{}", len);
+        }
+    }
+    #[test]
+    fn text_immediates_keep_their_value() {
+        let cases = [
+            ("inc r0 zr 40000", 40000, true),
+            ("inc r0 zr 32767", 32767, false),
+            ("inc r0 zr -2", -2i64 as u64, false),
+            ("inc r0 zr -40000", -40000i64 as u64, true),
+            ("dadd r0 zr 40000", 40000, false),
+            ("dadd r0 zr 65536", 65536, true),
+            ("dadd r0 zr -1", u64::MAX, true),
+            ("dadd r0 zr 18446744073709551615", u64::MAX, true),
+        ];
+        for (text, imm, extended) in cases {
+            let ins = Instruction::from(text);
+            assert_eq!(ins.immediate(), imm, "{}", text);
+            assert_eq!(ins.ext.is_some(), extended, "{}", text);
+            let back = Instruction::from(String::from(ins).as_str());
+            assert_eq!(back.words(), ins.words(), "{}", text);
+        }
     }
 }
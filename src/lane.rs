@@ -0,0 +1,115 @@
+/*
+Lane element types for vector instructions, carried in the data field:
+low 3 bits element type, bit 3 selects the high half for widening ops,
+bits 4-7 a mask register (zr = all lanes active),
+the high byte is an immediate (shuffle pattern or lane index).
+*/
+
+use crate::register::Register;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum LaneType {
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+pub(crate) const LANE_HIGH: u16 = 1 << 3;
+pub(crate) const LANE_MASK_SHIFT: u16 = 4;
+pub(crate) const LANE_IMM_SHIFT: u16 = 8;
+
+impl LaneType {
+    pub(crate) fn bits(self) -> usize {
+        match self {
+            LaneType::I8 => 8,
+            LaneType::I16 => 16,
+            LaneType::I32 | LaneType::F32 => 32,
+            LaneType::I64 | LaneType::F64 => 64,
+        }
+    }
+}
+//Type codes 6 and 7 are unassigned, instructions carrying them are illegal
+pub(crate) fn is_valid(data: u16) -> bool {
+    data & 0b111 <= u16::from(LaneType::F64)
+}
+impl From<u16> for LaneType {
+    fn from(val: u16) -> Self {
+        match val & 0b111 {
+            0 => LaneType::I8,
+            1 => LaneType::I16,
+            2 => LaneType::I32,
+            3 => LaneType::I64,
+            4 => LaneType::F32,
+            5 => LaneType::F64,
+            _ => LaneType::I8,
+        }
+    }
+}
+impl From<LaneType> for u16 {
+    fn from(val: LaneType) -> Self {
+        match val {
+            LaneType::I8 => 0,
+            LaneType::I16 => 1,
+            LaneType::I32 => 2,
+            LaneType::I64 => 3,
+            LaneType::F32 => 4,
+            LaneType::F64 => 5,
+        }
+    }
+}
+impl From<&str> for LaneType {
+    fn from(val: &str) -> Self {
+        match val {
+            "i8" => LaneType::I8,
+            "i16" => LaneType::I16,
+            "i32" => LaneType::I32,
+            "i64" => LaneType::I64,
+            "f32" => LaneType::F32,
+            "f64" => LaneType::F64,
+            _ => LaneType::I8,
+        }
+    }
+}
+impl From<LaneType> for &str {
+    fn from(val: LaneType) -> Self {
+        match val {
+            LaneType::I8 => "i8",
+            LaneType::I16 => "i16",
+            LaneType::I32 => "i32",
+            LaneType::I64 => "i64",
+            LaneType::F32 => "f32",
+            LaneType::F64 => "f64",
+        }
+    }
+}
+//Mask register index of a data operand
+pub(crate) fn mask_register(data: u16) -> u8 {
+    ((data >> LANE_MASK_SHIFT) & 0b1111) as u8
+}
+//Parses a data operand such as "i16", "i16h" (high half), "i32:27" (immediate),
+//"i32:s1" (register as immediate) or "f32/s2" (masked by s2)
+pub(crate) fn parse_lane(val: &str) -> Option<u16> {
+    let (val, mask) = match val.split_once('/') {
+        Some((val, name)) => (val, register_index(name)?),
+        None => (val, 0),
+    };
+    let (val, imm) = match val.split_once(':') {
+        Some((val, imm)) => (val, imm.parse::<u8>().ok().or_else(|| register_index(imm))?),
+        None => (val, 0),
+    };
+    let (name, high) = match val.strip_suffix('h') {
+        Some(name) => (name, LANE_HIGH),
+        None => (val, 0),
+    };
+    let lane = LaneType::from(name);
+    (<&str>::from(lane) == name).then(|| {
+        u16::from(lane) | high | (mask as u16) << LANE_MASK_SHIFT | (imm as u16) << LANE_IMM_SHIFT
+    })
+}
+fn register_index(name: &str) -> Option<u8> {
+    let index = u8::from(Register::from(name));
+    (<&str>::from(Register::from(index)) == name).then_some(index)
+}
@@ -1,9 +1,18 @@
-#![feature(mixed_integer_ops)]
+#![feature(portable_simd)]
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
 
 mod cli;
 mod compiler;
 mod instruction;
+mod lane;
+mod objdump;
 mod opcode;
+mod output;
 mod register;
 mod vm;
-fn main() {}
+fn main() {
+    if let Err(err) = cli::run(std::env::args().collect()) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,360 @@
+/*
+objdump -x -d style inspection of SFiles.
+Malformed fields are printed as-is and explained at the end instead of aborting.
+*/
+
+use crate::{
+    compiler::decompile,
+    vm::sfile::{
+        Arch, Binding, FileType, RelocationKind, SFile, SFileError, SFileHeader, SectionKind,
+    },
+};
+
+pub(crate) fn inspect(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    match SFile::from_bytes(bytes) {
+        Ok(file) => {
+            dump_header(&mut out, &file.header);
+            dump_sections(&mut out, &file);
+            dump_symbols(&mut out, &file);
+            dump_relocations(&mut out, &file);
+            dump_debug(&mut out, &file);
+            dump_disassembly(&mut out, &file);
+            let problems = diagnose(&file);
+            if !problems.is_empty() {
+                out.push_str("\nMalformed fields:\n");
+                for problem in problems {
+                    out.push_str(&format!("  {}\n", problem));
+                }
+            }
+        }
+        Err(err) => {
+            //Still show whatever header fields are present
+            if let Ok(header) = SFileHeader::from_bytes(bytes) {
+                dump_header(&mut out, &header);
+            }
+            out.push_str("\nMalformed file:\n");
+            out.push_str(&format!("  {}\n", explain_error(&err, bytes.len())));
+            if bytes.len() >= 2 && bytes[..2] != *b"SF" {
+                out.push_str(&format!(
+                    "  magic: expected \"SF\", found {:02x} {:02x}, this is probably not an SFile\n",
+                    bytes[0], bytes[1]
+                ));
+            }
+        }
+    }
+    out
+}
+pub(crate) fn diagnose(file: &SFile) -> Vec<String> {
+    let mut problems = Vec::new();
+    let header = &file.header;
+    if header.magic() != *b"SF" {
+        problems.push(format!(
+            "magic: expected \"SF\", found {:02x} {:02x}",
+            header.magic()[0],
+            header.magic()[1]
+        ));
+    }
+    if filetype_name(header.filetype()).is_none() {
+        problems.push(format!(
+            "filetype: unknown value {} (0 = executable, 1 = shared)",
+            header.filetype()
+        ));
+    }
+    match arch_name(header.arch()) {
+        None => problems.push(format!(
+            "arch: unknown value {} (0..=6 are defined)",
+            header.arch()
+        )),
+        Some(name) if header.arch() != u8::from(Arch::SS64) => problems.push(format!(
+            "arch: {} code can't be run or disassembled as ss64",
+            name
+        )),
+        Some(_) => {}
+    }
+    if version_name(header.version()).is_none() {
+        problems.push(format!(
+            "version: unknown value {} (only 0 = v1 is defined)",
+            header.version()
+        ));
+    }
+    for (index, section) in file.sections.iter().enumerate() {
+        if kind_name(section.kind).is_none() {
+            problems.push(format!(
+                "section {} ({}): unknown kind {}",
+                index, section.name, section.kind
+            ));
+        }
+        if section.kind == u8::from(SectionKind::Code) {
+            if section.address % 4 != 0 {
+                problems.push(format!(
+                    "section {} ({}): code address {:#x} isn't 4-byte aligned",
+                    index, section.name, section.address
+                ));
+            }
+            if section.data.len() % 4 != 0 {
+                problems.push(format!(
+                    "section {} ({}): code size {} isn't a whole number of instructions",
+                    index,
+                    section.name,
+                    section.data.len()
+                ));
+            }
+        }
+    }
+    for (index, symbol) in file.symbols.iter().enumerate() {
+        match file.sections.get(symbol.section as usize) {
+            None => problems.push(format!(
+                "symbol {} ({}): section index {} out of range",
+                index, symbol.name, symbol.section
+            )),
+            Some(section) if symbol.offset as usize > section.data.len() => problems.push(format!(
+                "symbol {} ({}): offset {:#x} past the end of {}",
+                index, symbol.name, symbol.offset, section.name
+            )),
+            Some(_) => {}
+        }
+        if binding_name(symbol.binding).is_none() {
+            problems.push(format!(
+                "symbol {} ({}): unknown binding {}",
+                index, symbol.name, symbol.binding
+            ));
+        }
+        if symbol.binding == u8::from(Binding::Global)
+            && file.symbols[..index]
+                .iter()
+                .any(|v| v.binding == symbol.binding && v.name == symbol.name)
+        {
+            problems.push(format!(
+                "symbol {} ({}): duplicate global symbol",
+                index, symbol.name
+            ));
+        }
+    }
+    for (index, relocation) in file.relocations.iter().enumerate() {
+        match file.sections.get(relocation.section as usize) {
+            None => problems.push(format!(
+                "relocation {}: section index {} out of range",
+                index, relocation.section
+            )),
+            Some(section) => {
+                if section.kind != u8::from(SectionKind::Code) {
+                    problems.push(format!(
+                        "relocation {}: {} isn't a code section",
+                        index, section.name
+                    ));
+                }
+                if relocation.offset % 4 != 0 || relocation.offset as usize + 4 > section.data.len()
+                {
+                    problems.push(format!(
+                        "relocation {}: offset {:#x} isn't an instruction in {}",
+                        index, relocation.offset, section.name
+                    ));
+                }
+            }
+        }
+        if relocation.symbol as usize >= file.symbols.len() {
+            problems.push(format!(
+                "relocation {}: symbol index {} out of range",
+                index, relocation.symbol
+            ));
+        }
+        if reloc_name(relocation.kind).is_none() {
+            problems.push(format!(
+                "relocation {}: unknown kind {}",
+                index, relocation.kind
+            ));
+        }
+    }
+    for (index, line) in file.debug.iter().enumerate() {
+        match file.sections.get(line.section as usize) {
+            None => problems.push(format!(
+                "debug line {}: section index {} out of range",
+                index, line.section
+            )),
+            Some(section) if line.offset as usize >= section.data.len() => problems.push(format!(
+                "debug line {}: offset {:#x} past the end of {}",
+                index, line.offset, section.name
+            )),
+            Some(_) => {}
+        }
+    }
+    problems
+}
+fn explain_error(err: &SFileError, len: usize) -> String {
+    match err {
+        SFileError::Truncated(offset) => format!(
+            "truncated: a field at offset {:#x} runs past the end of the file ({} bytes)",
+            offset, len
+        ),
+        SFileError::BadString(offset) => {
+            format!("string at offset {:#x} isn't valid utf8", offset)
+        }
+        SFileError::SectionOutOfBounds(index) => format!(
+            "section {}: data offset/size point outside the file ({} bytes)",
+            index, len
+        ),
+        SFileError::TooMany(count) => format!("table has {} entries, more than 65535", count),
+        SFileError::TooLong(size) => format!("string is {} bytes, longer than 65535", size),
+        SFileError::TooLarge(size) => format!("file is {} bytes, larger than 4GiB", size),
+    }
+}
+fn dump_header(out: &mut String, header: &SFileHeader) {
+    let magic = header.magic();
+    out.push_str("Header:\n");
+    out.push_str(&format!(
+        "  magic     {:02x} {:02x} ({})\n",
+        magic[0],
+        magic[1],
+        String::from_utf8_lossy(&magic)
+    ));
+    out.push_str(&format!(
+        "  filetype  {} ({})\n",
+        header.filetype(),
+        filetype_name(header.filetype()).unwrap_or("unknown")
+    ));
+    out.push_str(&format!(
+        "  arch      {} ({})\n",
+        header.arch(),
+        arch_name(header.arch()).unwrap_or("unknown")
+    ));
+    out.push_str(&format!(
+        "  version   {} ({})\n",
+        header.version(),
+        version_name(header.version()).unwrap_or("unknown")
+    ));
+}
+fn dump_sections(out: &mut String, file: &SFile) {
+    out.push_str("\nSections:\nIdx Name             Kind     Address          Size\n");
+    for (index, section) in file.sections.iter().enumerate() {
+        out.push_str(&format!(
+            "{:3} {:16} {:8} {:016x} {:08x}\n",
+            index,
+            section.name,
+            kind_name(section.kind).unwrap_or("unknown"),
+            section.address,
+            section.data.len()
+        ));
+    }
+}
+fn dump_symbols(out: &mut String, file: &SFile) {
+    out.push_str("\nSymbols:\nAddress          Section          Binding  Name\n");
+    for symbol in &file.symbols {
+        out.push_str(&format!(
+            "{:016x} {:16} {:8} {}\n",
+            symbol_address(file, symbol.section, symbol.offset),
+            section_name(file, symbol.section),
+            binding_name(symbol.binding).unwrap_or("unknown"),
+            symbol.name
+        ));
+    }
+}
+fn dump_relocations(out: &mut String, file: &SFile) {
+    out.push_str("\nRelocations:\nAddress          Section          Kind     Symbol\n");
+    for relocation in &file.relocations {
+        out.push_str(&format!(
+            "{:016x} {:16} {:8} {}\n",
+            symbol_address(file, relocation.section, relocation.offset),
+            section_name(file, relocation.section),
+            reloc_name(relocation.kind).unwrap_or("unknown"),
+            symbol_name(file, relocation.symbol)
+        ));
+    }
+}
+fn dump_debug(out: &mut String, file: &SFile) {
+    out.push_str("\nDebug lines:\nAddress          Line     File\n");
+    for line in &file.debug {
+        out.push_str(&format!(
+            "{:016x} {:<8} {}\n",
+            symbol_address(file, line.section, line.offset),
+            line.line,
+            line.file
+        ));
+    }
+}
+fn dump_disassembly(out: &mut String, file: &SFile) {
+    if file.header.arch() != u8::from(Arch::SS64) {
+        return;
+    }
+    for (index, section) in file.sections.iter().enumerate() {
+        if section.kind != u8::from(SectionKind::Code) {
+            continue;
+        }
+        out.push_str(&format!("\nDisassembly of section {}:\n", section.name));
+        let words = section.words();
+        for (k, (text, len)) in decompile(&words) {
+            let offset = (k * 4) as u32;
+            for symbol in file
+                .symbols
+                .iter()
+                .filter(|v| v.section as usize == index && v.offset == offset)
+            {
+                out.push_str(&format!(
+                    "\n{:016x} <{}>:\n",
+                    section.address.wrapping_add(offset as u64),
+                    symbol.name
+                ));
+            }
+            //Extended forms list their extra words on the following lines
+            for (n, word) in words[k..k + len].iter().enumerate() {
+                let bytes = word.to_le_bytes();
+                out.push_str(&format!(
+                    "  {:8x}:  {:02x} {:02x} {:02x} {:02x}  {}\n",
+                    section.address.wrapping_add(offset as u64 + n as u64 * 4),
+                    bytes[0],
+                    bytes[1],
+                    bytes[2],
+                    bytes[3],
+                    if n == 0 { text.as_str() } else { "" }
+                ));
+            }
+            for relocation in file
+                .relocations
+                .iter()
+                .filter(|v| v.section as usize == index && v.offset == offset)
+            {
+                out.push_str(&format!(
+                    "             R_{} {}\n",
+                    reloc_name(relocation.kind)
+                        .unwrap_or("unknown")
+                        .to_uppercase(),
+                    symbol_name(file, relocation.symbol)
+                ));
+            }
+        }
+    }
+}
+fn symbol_address(file: &SFile, section: u16, offset: u32) -> u64 {
+    file.sections
+        .get(section as usize)
+        .map_or(0, |v| v.address)
+        .wrapping_add(offset as u64)
+}
+fn section_name(file: &SFile, section: u16) -> &str {
+    file.sections
+        .get(section as usize)
+        .map_or("?", |v| v.name.as_str())
+}
+fn symbol_name(file: &SFile, symbol: u16) -> &str {
+    file.symbols
+        .get(symbol as usize)
+        .map_or("?", |v| v.name.as_str())
+}
+fn filetype_name(val: u8) -> Option<&'static str> {
+    (val <= 1).then(|| FileType::from(val).into())
+}
+fn arch_name(val: u8) -> Option<&'static str> {
+    (val <= 6).then(|| Arch::from(val).into())
+}
+fn version_name(val: u8) -> Option<&'static str> {
+    (val == 0).then_some("v1")
+}
+fn kind_name(val: u8) -> Option<&'static str> {
+    (val <= 2).then(|| SectionKind::from(val).into())
+}
+fn binding_name(val: u8) -> Option<&'static str> {
+    (val <= 1).then(|| Binding::from(val).into())
+}
+fn reloc_name(val: u8) -> Option<&'static str> {
+    (val <= 1).then(|| RelocationKind::from(val).into())
+}
@@ -1,7 +1,8 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub(crate) enum Opcode {
     Nop,    //nop reg0 reg1 jump_index(Clears selected registers)
-    Set,    //set reg0 index data
-    Get,    //get reg0 index jump_index (gets from reg0+1)
+    Set,    //set reg0 index data (16-bit lane index of reg0=data)
+    Get,    //get reg0 index jump_index (r0=16-bit lane index of reg0)
     Update, //update reg0 reg1 jump_index
     Delete, //delete reg0 reg1 jump_index (clears reg1)
 
@@ -36,10 +37,628 @@ pub(crate) enum Opcode {
     Dinc, //dinc reg0 reg1 data (reg0=data+1) (reg1=data)
     Ddec, //ddec reg0 reg1 data (reg0=data-1) (reg1=data)
     Dneg, //dneg reg0 reg1 data (reg0=-data) (reg1=data)
+
+    Itof,  //itof reg0 reg1 jump_index (reg0=(f64)(i64)reg1)
+    Utof,  //utof reg0 reg1 jump_index (reg0=(f64)(u64)reg1)
+    Ftoi,  //ftoi reg0 reg1 jump_index (reg0=(i64)trunc(reg1), saturating)
+    Ftou,  //ftou reg0 reg1 jump_index (reg0=(u64)trunc(reg1), saturating)
+    Rftoi, //rftoi reg0 reg1 jump_index (reg0=(i64)round(reg1), current rounding mode)
+    Rftou, //rftou reg0 reg1 jump_index (reg0=(u64)round(reg1), current rounding mode)
+
+    Adds,  //adds reg0 reg1 jump_index (reg0+=reg1, f32 lanes)
+    Subs,  //subs reg0 reg1 jump_index (reg0-=reg1, f32 lanes)
+    Muls,  //muls reg0 reg1 jump_index (reg0*=reg1, f32 lanes)
+    Divs,  //divs reg0 reg1 jump_index (reg0/=reg1, f32 lanes)
+    Fcvts, //fcvts reg0 reg1 jump_index (reg0=(f32)(f64)reg1)
+    Fcvtd, //fcvtd reg0 reg1 jump_index (reg0=(f64)(f32)reg1)
+    Rfcsr, //rfcsr reg0 reg1 jump_index (reg0=fcsr)
+    Wfcsr, //wfcsr reg0 reg1 jump_index (fcsr=reg0)
+    Dfrm,  //dfrm reg0 reg1 data (rounding mode=data: 0 nearest-even, 1 toward zero, 2 down, 3 up)
+    Dfclr, //dfclr reg0 reg1 data (reg0=flags) (flags&=!data)
+
+    Sqrt,      //sqrt reg0 reg1 jump_index (reg0=sqrt(reg1))
+    Sqrts,     //sqrts reg0 reg1 jump_index (reg0=sqrt(reg1), f32 lanes)
+    Fabs,      //fabs reg0 reg1 jump_index (reg0=abs(reg1))
+    Fabss,     //fabss reg0 reg1 jump_index (reg0=abs(reg1), f32 lanes)
+    Floor,     //floor reg0 reg1 jump_index (reg0=floor(reg1))
+    Floors,    //floors reg0 reg1 jump_index (reg0=floor(reg1), f32 lanes)
+    Ceil,      //ceil reg0 reg1 jump_index (reg0=ceil(reg1))
+    Ceils,     //ceils reg0 reg1 jump_index (reg0=ceil(reg1), f32 lanes)
+    Round,     //round reg0 reg1 jump_index (reg0=round(reg1), ties away from zero)
+    Rounds,    //rounds reg0 reg1 jump_index (reg0=round(reg1), ties away from zero, f32 lanes)
+    Sin,       //sin reg0 reg1 jump_index (reg0=sin(reg1))
+    Sins,      //sins reg0 reg1 jump_index (reg0=sin(reg1), f32 lanes)
+    Cos,       //cos reg0 reg1 jump_index (reg0=cos(reg1))
+    Coss,      //coss reg0 reg1 jump_index (reg0=cos(reg1), f32 lanes)
+    Exp,       //exp reg0 reg1 jump_index (reg0=exp(reg1))
+    Exps,      //exps reg0 reg1 jump_index (reg0=exp(reg1), f32 lanes)
+    Log,       //log reg0 reg1 jump_index (reg0=ln(reg1))
+    Logs,      //logs reg0 reg1 jump_index (reg0=ln(reg1), f32 lanes)
+    Fmin,      //fmin reg0 reg1 jump_index (reg0=min(reg0,reg1))
+    Fmins,     //fmins reg0 reg1 jump_index (reg0=min(reg0,reg1), f32 lanes)
+    Fmax,      //fmax reg0 reg1 jump_index (reg0=max(reg0,reg1))
+    Fmaxs,     //fmaxs reg0 reg1 jump_index (reg0=max(reg0,reg1), f32 lanes)
+    Copysign,  //copysign reg0 reg1 jump_index (reg0=copysign(reg0,reg1))
+    Copysigns, //copysigns reg0 reg1 jump_index (reg0=copysign(reg0,reg1), f32 lanes)
+    Pow,       //pow reg0 reg1 jump_index (reg0=pow(reg0,reg1))
+    Pows,      //pows reg0 reg1 jump_index (reg0=pow(reg0,reg1), f32 lanes)
+    Fma,       //fma reg0 reg1 reg2 (reg0=reg0*reg1+reg2)
+    Fmas,      //fmas reg0 reg1 reg2 (reg0=reg0*reg1+reg2, f32 lanes)
+
+    Vadd,   //vadd reg0 reg1 lane/mask (reg0+=reg1 lane-wise, wrapping)
+    Vsub,   //vsub reg0 reg1 lane/mask (reg0-=reg1 lane-wise, wrapping)
+    Vmul,   //vmul reg0 reg1 lane/mask (reg0*=reg1 lane-wise, wrapping)
+    Vaddss, //vaddss reg0 reg1 lane/mask (signed saturating add)
+    Vaddus, //vaddus reg0 reg1 lane/mask (unsigned saturating add)
+    Vsubss, //vsubss reg0 reg1 lane/mask (signed saturating sub)
+    Vsubus, //vsubus reg0 reg1 lane/mask (unsigned saturating sub)
+    Vaddw,  //vaddw reg0 reg1 lane/mask (signed widening add of the low/high half lanes)
+    Vaddwu, //vaddwu reg0 reg1 lane/mask (unsigned widening add)
+    Vmulw,  //vmulw reg0 reg1 lane/mask (signed widening mul of the low/high half lanes)
+    Vmulwu, //vmulwu reg0 reg1 lane/mask (unsigned widening mul)
+
+    Vshuf, //vshuf reg0 reg1 lane:pattern (reg0 lanes from reg1 lanes, 2 bits per lane in each group of 4)
+    Vperm, //vperm reg0 reg1 lane (reg0[k]=reg1[reg0[k]], out of range gives 0)
+    Vins,  //vins reg0 reg1 lane:index (reg0[index]=reg1)
+    Vext,  //vext reg0 reg1 lane:index (reg0=reg1[index], zero extended)
+    Vbcast, //vbcast reg0 reg1 lane (every lane of reg0=reg1)
+    Vzipl, //vzipl reg0 reg1 lane (interleave the low half lanes of reg0 and reg1)
+    Vziph, //vziph reg0 reg1 lane (interleave the high half lanes)
+    Vuzpe, //vuzpe reg0 reg1 lane (even lanes of reg0 then of reg1)
+    Vuzpo, //vuzpo reg0 reg1 lane (odd lanes of reg0 then of reg1)
+    Vhsum, //vhsum reg0 reg1 lane (reg0=sum of reg1 lanes)
+    Vhmin, //vhmin reg0 reg1 lane (reg0=signed/float min of reg1 lanes)
+    Vhmax, //vhmax reg0 reg1 lane (reg0=signed/float max of reg1 lanes)
+    Vhminu, //vhminu reg0 reg1 lane (reg0=unsigned min of reg1 lanes)
+    Vhmaxu, //vhmaxu reg0 reg1 lane (reg0=unsigned max of reg1 lanes)
+    Vhany, //vhany reg0 reg1 lane (reg0=1 if any reg1 lane is non-zero)
+    Vhall, //vhall reg0 reg1 lane (reg0=1 if every reg1 lane is non-zero)
+
+    Vcmpeq,  //vcmpeq reg0 reg1 lane/mask (reg0 lanes=all ones where reg0==reg1, else 0)
+    Vcmpne,  //vcmpne reg0 reg1 lane/mask (reg0!=reg1, true for NaN lanes)
+    Vcmplt,  //vcmplt reg0 reg1 lane/mask (signed/float reg0<reg1)
+    Vcmple,  //vcmple reg0 reg1 lane/mask (signed/float reg0<=reg1)
+    Vcmpltu, //vcmpltu reg0 reg1 lane/mask (unsigned reg0<reg1)
+    Vcmpleu, //vcmpleu reg0 reg1 lane/mask (unsigned reg0<=reg1)
+    Vblend,  //vblend reg0 reg1 lane/mask (reg0 lanes=reg1 where the mask is set)
+    Vmovz,   //vmovz reg0 reg1 lane/mask (reg0 lanes=reg1 where the mask is set, else 0)
+
+    Vld,      //vld reg0 reg1 lane/mask (reg0=memory[reg1..], lanes are read individually)
+    Vlda,     //vlda reg0 reg1 lane/mask (vld, reg1 must be aligned to the register size)
+    Vlds,     //vlds reg0 reg1 lane:stride/mask (reg0[k]=memory[reg1+k*stride lanes])
+    Vgather,  //vgather reg0 reg1 lane:index/mask (reg0[k]=memory[reg1+index[k] lanes])
+    Vst,      //vst reg0 reg1 lane/mask (memory[reg1..]=reg0)
+    Vsta,     //vsta reg0 reg1 lane/mask (vst, reg1 must be aligned to the register size)
+    Vsts,     //vsts reg0 reg1 lane:stride/mask (memory[reg1+k*stride lanes]=reg0[k])
+    Vscatter, //vscatter reg0 reg1 lane:index/mask (memory[reg1+index[k] lanes]=reg0[k])
+
+    Wadd,  //wadd reg0 reg1 reg2 (reg0+=reg1 as one integer, reg2=carry out)
+    Wadc,  //wadc reg0 reg1 reg2 (reg0+=reg1+reg2, reg2=carry in/out)
+    Wsub,  //wsub reg0 reg1 reg2 (reg0-=reg1 as one integer, reg2=borrow out)
+    Wsbb,  //wsbb reg0 reg1 reg2 (reg0-=reg1+reg2, reg2=borrow in/out)
+    Wmul,  //wmul reg0 reg1 reg2 (reg2:reg0=reg0*reg1 unsigned, full width)
+    Wmuls, //wmuls reg0 reg1 reg2 (reg2:reg0=reg0*reg1 signed, full width)
+    Wshl,  //wshl reg0 reg1 jump_index (reg0<<=reg1 across the whole register)
+    Wshr,  //wshr reg0 reg1 jump_index (reg0>>=reg1, logical)
+    Wsar,  //wsar reg0 reg1 jump_index (reg0>>=reg1, arithmetic)
+    Wrotl, //wrotl reg0 reg1 jump_index (rotate reg0 left by reg1)
+    Wrotr, //wrotr reg0 reg1 jump_index (rotate reg0 right by reg1)
+    Wcmp,  //wcmp reg0 reg1 reg2 (reg2=-1/0/1 as reg0 is below/equal/above reg1, unsigned)
+    Wcmps, //wcmps reg0 reg1 reg2 (wcmp, signed)
+
+    Sdiv,    //sdiv reg0 reg1 jump_index (reg0/=reg1, signed)
+    Smod,    //smod reg0 reg1 jump_index (reg0%=reg1, signed, sign of reg0)
+    Sar,     //sar reg0 reg1 jump_index (reg0>>=reg1, arithmetic)
+    Mulh,    //mulh reg0 reg1 jump_index (reg0=high 64 bits of reg0*reg1, signed)
+    Mulhu,   //mulhu reg0 reg1 jump_index (reg0=high 64 bits of reg0*reg1, unsigned)
+    Divmod,  //divmod reg0 reg1 jump_index (reg0=reg0/reg1, reg1=reg0%reg1)
+    Sdivmod, //sdivmod reg0 reg1 jump_index (divmod, signed)
+    Sext,    //sext reg0 reg1 bits (reg0=reg1 sign extended from 8/16/32 bits)
+    Zext,    //zext reg0 reg1 bits (reg0=reg1 zero extended from 8/16/32 bits)
+
+    Cadd,   //cadd reg0 reg1 jump_index (add, traps on unsigned overflow)
+    Csub,   //csub reg0 reg1 jump_index (sub, traps on borrow)
+    Cmul,   //cmul reg0 reg1 jump_index (mul, traps on unsigned overflow)
+    Cinc,   //cinc reg0 reg1 jump_index (inc, traps on unsigned overflow)
+    Cdec,   //cdec reg0 reg1 jump_index (dec, traps on borrow)
+    Cneg,   //cneg reg0 reg1 jump_index (neg, traps on signed overflow)
+    Qadd,   //qadd reg0 reg1 jump_index (add, saturates at u64::MAX)
+    Qsub,   //qsub reg0 reg1 jump_index (sub, saturates at 0)
+    Qmul,   //qmul reg0 reg1 jump_index (mul, saturates at u64::MAX)
+    Qinc,   //qinc reg0 reg1 jump_index (inc, saturates at u64::MAX)
+    Qdec,   //qdec reg0 reg1 jump_index (dec, saturates at 0)
+    Qneg,   //qneg reg0 reg1 jump_index (neg, saturates at i64::MAX)
+    Rflags, //rflags reg0 reg1 jump_index (reg0=integer flags, 1 carry 2 overflow)
+
+    Popcnt,  //popcnt reg0 reg1 jump_index (reg0=number of set bits of reg1)
+    Clz,     //clz reg0 reg1 jump_index (reg0=leading zeros of reg1)
+    Ctz,     //ctz reg0 reg1 jump_index (reg0=trailing zeros of reg1)
+    Rotl,    //rotl reg0 reg1 jump_index (rotate reg0 left by reg1)
+    Rotr,    //rotr reg0 reg1 jump_index (rotate reg0 right by reg1)
+    Bswap,   //bswap reg0 reg1 jump_index (reg0=reg1 with the byte order reversed)
+    Bitrev,  //bitrev reg0 reg1 jump_index (reg0=reg1 with the bit order reversed)
+    Bfext,   //bfext reg0 reg1 field (reg0=reg1 bits pos..pos+width, field=pos|width<<8)
+    Bfins,   //bfins reg0 reg1 field (reg0 bits pos..pos+width=low bits of reg1)
+    Bset,    //bset reg0 reg1 jump_index (set bit reg1 of reg0)
+    Bclr,    //bclr reg0 reg1 jump_index (clear bit reg1 of reg0)
+    Btst,    //btst reg0 reg1 jump_index (jump if bit reg1 of reg0 is set)
+    Vpopcnt, //vpopcnt reg0 reg1 lane (popcnt of every reg1 lane)
+    Vclz,    //vclz reg0 reg1 lane (clz of every reg1 lane)
+    Vctz,    //vctz reg0 reg1 lane (ctz of every reg1 lane)
+    Vrotl,   //vrotl reg0 reg1 lane (rotate reg0 lanes left by reg1 lanes)
+    Vrotr,   //vrotr reg0 reg1 lane (rotate reg0 lanes right by reg1 lanes)
+    Vbswap,  //vbswap reg0 reg1 lane (bswap of every reg1 lane)
+    Vbitrev, //vbitrev reg0 reg1 lane (bitrev of every reg1 lane)
+
+    Cmovc,   //cmovc reg0 reg1 jump_index (reg0=reg1 if the carry flag is set)
+    Cmovnc,  //cmovnc reg0 reg1 jump_index (reg0=reg1 if the carry flag is clear)
+    Cmovo,   //cmovo reg0 reg1 jump_index (reg0=reg1 if the overflow flag is set)
+    Cmovno,  //cmovno reg0 reg1 jump_index (reg0=reg1 if the overflow flag is clear)
+    Cmovz,   //cmovz reg0 reg1 reg2 (reg0=reg1 where reg2==0, per u64 lane)
+    Cmovnz,  //cmovnz reg0 reg1 reg2 (reg0=reg1 where reg2!=0, per u64 lane)
+    Cmovlz,  //cmovlz reg0 reg1 reg2 (reg0=reg1 where reg2<0 signed, per u64 lane)
+    Cmovgez, //cmovgez reg0 reg1 reg2 (reg0=reg1 where reg2>=0 signed, per u64 lane)
+
+    Jmp,  //jmp reg0 reg1 data (pc=reg0)
+    Call, //call reg0 reg1 data (reg1=return pc, pc=reg0)
+    Jtab, //jtab reg0 reg1 count (pc=u64 entry reg1 of the table at address reg0, falls through when reg1>=count)
+    Adr,  //adr reg0 reg1 offset (reg0=pc+offset, the address of a label)
+    Jl,   //jl offset (pc+=offset, signed 24 bits over the register and data fields)
+
+    Memcpy,  //memcpy reg0 reg1 reg2 (copy reg2 bytes from address reg1 to reg0, front to back)
+    Memmove, //memmove reg0 reg1 reg2 (memcpy, back to front when reg0 overlaps above reg1)
+    Memset,  //memset reg0 reg1 reg2 (fill reg2 bytes at address reg0 with the low byte of reg1)
+    Memcmp,  //memcmp reg0 reg1 reg2 (skip equal bytes at reg0 and reg1, carry if reg0 is lower)
+    Memchr,  //memchr reg0 reg1 reg2 (skip bytes at address reg0 up to the low byte of reg1)
+
+    Ald,   //ald reg0 reg1 order (reg0=atomic load of the u64 at address reg1)
+    Ast,   //ast reg0 reg1 order (atomic store of reg0 to the u64 at address reg1)
+    Acas,  //acas reg0 reg1 reg2 (u64 at reg1=reg2 if it is reg0, reg0=old value, carry if swapped)
+    Aadd,  //aadd reg0 reg1 order (u64 at address reg1+=reg0, reg0=old value)
+    Aand,  //aand reg0 reg1 order (u64 at address reg1&=reg0, reg0=old value)
+    Aor,   //aor reg0 reg1 order (u64 at address reg1|=reg0, reg0=old value)
+    Axor,  //axor reg0 reg1 order (u64 at address reg1^=reg0, reg0=old value)
+    Aswp,  //aswp reg0 reg1 order (u64 at address reg1=reg0, reg0=old value)
+    Fence, //fence reg0 reg1 order (memory fence)
+
+    Cid,   //cid reg0 reg1 data (reg0=core id, reg1=number of cores)
+    Spawn, //spawn reg0 reg1 reg2 (start idle core reg0 at pc reg1, its r0=reg2, carry if not idle)
+    Park,  //park reg0 reg1 data (sleep until woken, can return early)
+    Wake,  //wake reg0 reg1 data (wake core reg0, or make its next park return)
+
+    Tvec, //tvec reg0 reg1 data (trap table address=reg0, reg1=the previous one)
+    Rtt,  //rtt reg0 reg1 data (return from a trap handler to the pc in the frame)
+
+    Ie,    //ie reg0 reg1 data (interrupts on if reg0!=0, reg1=1 if they were on)
+    Imask, //imask reg0 reg1 data (interrupt lines allowed=reg0, reg1=the previous mask)
+    Ipend, //ipend reg0 reg1 data (reg0=pending lines, then clears the lines set in reg1)
+    Timer, //timer reg0 reg1 source (irq after reg0 ticks of instructions (0) or virtual time (1), reg1=clock)
+
+    Ext = 255, //Prefix word of the extended encoding, never executed on its own
+}
+impl Opcode {
+    //Opcodes whose data field is a relative jump taken by jump_op
+    pub(crate) fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Opcode::Nop
+                | Opcode::Get
+                | Opcode::Update
+                | Opcode::Delete
+                | Opcode::Swap
+                | Opcode::And
+                | Opcode::Or
+                | Opcode::Xor
+                | Opcode::Not
+                | Opcode::Shl
+                | Opcode::Shr
+                | Opcode::Dnot
+                | Opcode::Add
+                | Opcode::Sub
+                | Opcode::Mul
+                | Opcode::Div
+                | Opcode::Mod
+                | Opcode::Inc
+                | Opcode::Dec
+                | Opcode::Neg
+                | Opcode::Itof
+                | Opcode::Utof
+                | Opcode::Ftoi
+                | Opcode::Ftou
+                | Opcode::Rftoi
+                | Opcode::Rftou
+                | Opcode::Adds
+                | Opcode::Subs
+                | Opcode::Muls
+                | Opcode::Divs
+                | Opcode::Fcvts
+                | Opcode::Fcvtd
+                | Opcode::Rfcsr
+                | Opcode::Wfcsr
+                | Opcode::Sqrt
+                | Opcode::Sqrts
+                | Opcode::Fabs
+                | Opcode::Fabss
+                | Opcode::Floor
+                | Opcode::Floors
+                | Opcode::Ceil
+                | Opcode::Ceils
+                | Opcode::Round
+                | Opcode::Rounds
+                | Opcode::Sin
+                | Opcode::Sins
+                | Opcode::Cos
+                | Opcode::Coss
+                | Opcode::Exp
+                | Opcode::Exps
+                | Opcode::Log
+                | Opcode::Logs
+                | Opcode::Fmin
+                | Opcode::Fmins
+                | Opcode::Fmax
+                | Opcode::Fmaxs
+                | Opcode::Copysign
+                | Opcode::Copysigns
+                | Opcode::Pow
+                | Opcode::Pows
+                | Opcode::Wshl
+                | Opcode::Wshr
+                | Opcode::Wsar
+                | Opcode::Wrotl
+                | Opcode::Wrotr
+                | Opcode::Sdiv
+                | Opcode::Smod
+                | Opcode::Sar
+                | Opcode::Mulh
+                | Opcode::Mulhu
+                | Opcode::Divmod
+                | Opcode::Sdivmod
+                | Opcode::Cadd
+                | Opcode::Csub
+                | Opcode::Cmul
+                | Opcode::Cinc
+                | Opcode::Cdec
+                | Opcode::Cneg
+                | Opcode::Qadd
+                | Opcode::Qsub
+                | Opcode::Qmul
+                | Opcode::Qinc
+                | Opcode::Qdec
+                | Opcode::Qneg
+                | Opcode::Rflags
+                | Opcode::Popcnt
+                | Opcode::Clz
+                | Opcode::Ctz
+                | Opcode::Rotl
+                | Opcode::Rotr
+                | Opcode::Bswap
+                | Opcode::Bitrev
+                | Opcode::Bset
+                | Opcode::Bclr
+                | Opcode::Btst
+                | Opcode::Cmovc
+                | Opcode::Cmovnc
+                | Opcode::Cmovo
+                | Opcode::Cmovno
+        )
+    }
+    //The data field is a signed pc-relative offset
+    pub(crate) fn has_offset(self) -> bool {
+        self.is_jump() || matches!(self, Opcode::Adr | Opcode::Jl)
+    }
+    //Destructive reg0 op= reg1 forms, extended three-register forms compute reg0 = reg1 op reg2
+    pub(crate) fn is_binary(self) -> bool {
+        matches!(
+            self,
+            Opcode::And
+                | Opcode::Or
+                | Opcode::Xor
+                | Opcode::Shl
+                | Opcode::Shr
+                | Opcode::Add
+                | Opcode::Sub
+                | Opcode::Mul
+                | Opcode::Div
+                | Opcode::Mod
+                | Opcode::Adds
+                | Opcode::Subs
+                | Opcode::Muls
+                | Opcode::Divs
+                | Opcode::Fmin
+                | Opcode::Fmins
+                | Opcode::Fmax
+                | Opcode::Fmaxs
+                | Opcode::Copysign
+                | Opcode::Copysigns
+                | Opcode::Pow
+                | Opcode::Pows
+                | Opcode::Vadd
+                | Opcode::Vsub
+                | Opcode::Vmul
+                | Opcode::Vaddss
+                | Opcode::Vaddus
+                | Opcode::Vsubss
+                | Opcode::Vsubus
+                | Opcode::Vcmpeq
+                | Opcode::Vcmpne
+                | Opcode::Vcmplt
+                | Opcode::Vcmple
+                | Opcode::Vcmpltu
+                | Opcode::Vcmpleu
+                | Opcode::Wshl
+                | Opcode::Wshr
+                | Opcode::Wsar
+                | Opcode::Wrotl
+                | Opcode::Wrotr
+                | Opcode::Sdiv
+                | Opcode::Smod
+                | Opcode::Sar
+                | Opcode::Mulh
+                | Opcode::Mulhu
+                | Opcode::Cadd
+                | Opcode::Csub
+                | Opcode::Cmul
+                | Opcode::Qadd
+                | Opcode::Qsub
+                | Opcode::Qmul
+                | Opcode::Rotl
+                | Opcode::Rotr
+                | Opcode::Vrotl
+                | Opcode::Vrotr
+        )
+    }
+    //The data field carries a lane type (see lane.rs)
+    pub(crate) fn has_lane(self) -> bool {
+        let op = u8::from(self);
+        (u8::from(Opcode::Vadd)..=u8::from(Opcode::Vscatter)).contains(&op)
+            || (u8::from(Opcode::Vpopcnt)..=u8::from(Opcode::Vbitrev)).contains(&op)
+    }
+    //The data field names a third register
+    pub(crate) fn data_register(self) -> bool {
+        matches!(
+            self,
+            Opcode::Fma
+                | Opcode::Fmas
+                | Opcode::Wadd
+                | Opcode::Wadc
+                | Opcode::Wsub
+                | Opcode::Wsbb
+                | Opcode::Wmul
+                | Opcode::Wmuls
+                | Opcode::Wcmp
+                | Opcode::Wcmps
+                | Opcode::Cmovz
+                | Opcode::Cmovnz
+                | Opcode::Cmovlz
+                | Opcode::Cmovgez
+                | Opcode::Memcpy
+                | Opcode::Memmove
+                | Opcode::Memset
+                | Opcode::Memcmp
+                | Opcode::Memchr
+                | Opcode::Acas
+                | Opcode::Spawn
+        )
+    }
 }
 impl From<u8> for Opcode {
-    fn from(_: u8) -> Self {
-        todo!()
+    fn from(val: u8) -> Self {
+        match val {
+            0 => Opcode::Nop,
+            1 => Opcode::Set,
+            2 => Opcode::Get,
+            3 => Opcode::Update,
+            4 => Opcode::Delete,
+            5 => Opcode::Swap,
+            6 => Opcode::And,
+            7 => Opcode::Or,
+            8 => Opcode::Xor,
+            9 => Opcode::Not,
+            10 => Opcode::Shl,
+            11 => Opcode::Shr,
+            12 => Opcode::Dand,
+            13 => Opcode::Dor,
+            14 => Opcode::Dxor,
+            15 => Opcode::Dnot,
+            16 => Opcode::Dshl,
+            17 => Opcode::Dshr,
+            18 => Opcode::Add,
+            19 => Opcode::Sub,
+            20 => Opcode::Mul,
+            21 => Opcode::Div,
+            22 => Opcode::Mod,
+            23 => Opcode::Inc,
+            24 => Opcode::Dec,
+            25 => Opcode::Neg,
+            26 => Opcode::Dadd,
+            27 => Opcode::Dsub,
+            28 => Opcode::Dmul,
+            29 => Opcode::Ddiv,
+            30 => Opcode::Dmod,
+            31 => Opcode::Dinc,
+            32 => Opcode::Ddec,
+            33 => Opcode::Dneg,
+            34 => Opcode::Itof,
+            35 => Opcode::Utof,
+            36 => Opcode::Ftoi,
+            37 => Opcode::Ftou,
+            38 => Opcode::Rftoi,
+            39 => Opcode::Rftou,
+            40 => Opcode::Adds,
+            41 => Opcode::Subs,
+            42 => Opcode::Muls,
+            43 => Opcode::Divs,
+            44 => Opcode::Fcvts,
+            45 => Opcode::Fcvtd,
+            46 => Opcode::Rfcsr,
+            47 => Opcode::Wfcsr,
+            48 => Opcode::Dfrm,
+            49 => Opcode::Dfclr,
+            50 => Opcode::Sqrt,
+            51 => Opcode::Sqrts,
+            52 => Opcode::Fabs,
+            53 => Opcode::Fabss,
+            54 => Opcode::Floor,
+            55 => Opcode::Floors,
+            56 => Opcode::Ceil,
+            57 => Opcode::Ceils,
+            58 => Opcode::Round,
+            59 => Opcode::Rounds,
+            60 => Opcode::Sin,
+            61 => Opcode::Sins,
+            62 => Opcode::Cos,
+            63 => Opcode::Coss,
+            64 => Opcode::Exp,
+            65 => Opcode::Exps,
+            66 => Opcode::Log,
+            67 => Opcode::Logs,
+            68 => Opcode::Fmin,
+            69 => Opcode::Fmins,
+            70 => Opcode::Fmax,
+            71 => Opcode::Fmaxs,
+            72 => Opcode::Copysign,
+            73 => Opcode::Copysigns,
+            74 => Opcode::Pow,
+            75 => Opcode::Pows,
+            76 => Opcode::Fma,
+            77 => Opcode::Fmas,
+            78 => Opcode::Vadd,
+            79 => Opcode::Vsub,
+            80 => Opcode::Vmul,
+            81 => Opcode::Vaddss,
+            82 => Opcode::Vaddus,
+            83 => Opcode::Vsubss,
+            84 => Opcode::Vsubus,
+            85 => Opcode::Vaddw,
+            86 => Opcode::Vaddwu,
+            87 => Opcode::Vmulw,
+            88 => Opcode::Vmulwu,
+            89 => Opcode::Vshuf,
+            90 => Opcode::Vperm,
+            91 => Opcode::Vins,
+            92 => Opcode::Vext,
+            93 => Opcode::Vbcast,
+            94 => Opcode::Vzipl,
+            95 => Opcode::Vziph,
+            96 => Opcode::Vuzpe,
+            97 => Opcode::Vuzpo,
+            98 => Opcode::Vhsum,
+            99 => Opcode::Vhmin,
+            100 => Opcode::Vhmax,
+            101 => Opcode::Vhminu,
+            102 => Opcode::Vhmaxu,
+            103 => Opcode::Vhany,
+            104 => Opcode::Vhall,
+            105 => Opcode::Vcmpeq,
+            106 => Opcode::Vcmpne,
+            107 => Opcode::Vcmplt,
+            108 => Opcode::Vcmple,
+            109 => Opcode::Vcmpltu,
+            110 => Opcode::Vcmpleu,
+            111 => Opcode::Vblend,
+            112 => Opcode::Vmovz,
+            113 => Opcode::Vld,
+            114 => Opcode::Vlda,
+            115 => Opcode::Vlds,
+            116 => Opcode::Vgather,
+            117 => Opcode::Vst,
+            118 => Opcode::Vsta,
+            119 => Opcode::Vsts,
+            120 => Opcode::Vscatter,
+            121 => Opcode::Wadd,
+            122 => Opcode::Wadc,
+            123 => Opcode::Wsub,
+            124 => Opcode::Wsbb,
+            125 => Opcode::Wmul,
+            126 => Opcode::Wmuls,
+            127 => Opcode::Wshl,
+            128 => Opcode::Wshr,
+            129 => Opcode::Wsar,
+            130 => Opcode::Wrotl,
+            131 => Opcode::Wrotr,
+            132 => Opcode::Wcmp,
+            133 => Opcode::Wcmps,
+            134 => Opcode::Sdiv,
+            135 => Opcode::Smod,
+            136 => Opcode::Sar,
+            137 => Opcode::Mulh,
+            138 => Opcode::Mulhu,
+            139 => Opcode::Divmod,
+            140 => Opcode::Sdivmod,
+            141 => Opcode::Sext,
+            142 => Opcode::Zext,
+            143 => Opcode::Cadd,
+            144 => Opcode::Csub,
+            145 => Opcode::Cmul,
+            146 => Opcode::Cinc,
+            147 => Opcode::Cdec,
+            148 => Opcode::Cneg,
+            149 => Opcode::Qadd,
+            150 => Opcode::Qsub,
+            151 => Opcode::Qmul,
+            152 => Opcode::Qinc,
+            153 => Opcode::Qdec,
+            154 => Opcode::Qneg,
+            155 => Opcode::Rflags,
+            156 => Opcode::Popcnt,
+            157 => Opcode::Clz,
+            158 => Opcode::Ctz,
+            159 => Opcode::Rotl,
+            160 => Opcode::Rotr,
+            161 => Opcode::Bswap,
+            162 => Opcode::Bitrev,
+            163 => Opcode::Bfext,
+            164 => Opcode::Bfins,
+            165 => Opcode::Bset,
+            166 => Opcode::Bclr,
+            167 => Opcode::Btst,
+            168 => Opcode::Vpopcnt,
+            169 => Opcode::Vclz,
+            170 => Opcode::Vctz,
+            171 => Opcode::Vrotl,
+            172 => Opcode::Vrotr,
+            173 => Opcode::Vbswap,
+            174 => Opcode::Vbitrev,
+            175 => Opcode::Cmovc,
+            176 => Opcode::Cmovnc,
+            177 => Opcode::Cmovo,
+            178 => Opcode::Cmovno,
+            179 => Opcode::Cmovz,
+            180 => Opcode::Cmovnz,
+            181 => Opcode::Cmovlz,
+            182 => Opcode::Cmovgez,
+            183 => Opcode::Jmp,
+            184 => Opcode::Call,
+            185 => Opcode::Jtab,
+            186 => Opcode::Adr,
+            187 => Opcode::Jl,
+            188 => Opcode::Memcpy,
+            189 => Opcode::Memmove,
+            190 => Opcode::Memset,
+            191 => Opcode::Memcmp,
+            192 => Opcode::Memchr,
+            193 => Opcode::Ald,
+            194 => Opcode::Ast,
+            195 => Opcode::Acas,
+            196 => Opcode::Aadd,
+            197 => Opcode::Aand,
+            198 => Opcode::Aor,
+            199 => Opcode::Axor,
+            200 => Opcode::Aswp,
+            201 => Opcode::Fence,
+            202 => Opcode::Cid,
+            203 => Opcode::Spawn,
+            204 => Opcode::Park,
+            205 => Opcode::Wake,
+            206 => Opcode::Tvec,
+            207 => Opcode::Rtt,
+            208 => Opcode::Ie,
+            209 => Opcode::Imask,
+            210 => Opcode::Ipend,
+            211 => Opcode::Timer,
+            255 => Opcode::Ext,
+            _ => Opcode::Nop,
+        }
     }
 }
 impl From<Opcode> for u8 {
@@ -48,12 +667,441 @@ impl From<Opcode> for u8 {
     }
 }
 impl From<&str> for Opcode {
-    fn from(_: &str) -> Self {
-        todo!()
+    fn from(val: &str) -> Self {
+        match val {
+            "nop" => Opcode::Nop,
+            "set" => Opcode::Set,
+            "get" => Opcode::Get,
+            "update" => Opcode::Update,
+            "delete" => Opcode::Delete,
+            "swap" => Opcode::Swap,
+            "and" => Opcode::And,
+            "or" => Opcode::Or,
+            "xor" => Opcode::Xor,
+            "not" => Opcode::Not,
+            "shl" => Opcode::Shl,
+            "shr" => Opcode::Shr,
+            "dand" => Opcode::Dand,
+            "dor" => Opcode::Dor,
+            "dxor" => Opcode::Dxor,
+            "dnot" => Opcode::Dnot,
+            "dshl" => Opcode::Dshl,
+            "dshr" => Opcode::Dshr,
+            "add" => Opcode::Add,
+            "sub" => Opcode::Sub,
+            "mul" => Opcode::Mul,
+            "div" => Opcode::Div,
+            "mod" => Opcode::Mod,
+            "inc" => Opcode::Inc,
+            "dec" => Opcode::Dec,
+            "neg" => Opcode::Neg,
+            "dadd" => Opcode::Dadd,
+            "dsub" => Opcode::Dsub,
+            "dmul" => Opcode::Dmul,
+            "ddiv" => Opcode::Ddiv,
+            "dmod" => Opcode::Dmod,
+            "dinc" => Opcode::Dinc,
+            "ddec" => Opcode::Ddec,
+            "dneg" => Opcode::Dneg,
+            "itof" => Opcode::Itof,
+            "utof" => Opcode::Utof,
+            "ftoi" => Opcode::Ftoi,
+            "ftou" => Opcode::Ftou,
+            "rftoi" => Opcode::Rftoi,
+            "rftou" => Opcode::Rftou,
+            "adds" => Opcode::Adds,
+            "subs" => Opcode::Subs,
+            "muls" => Opcode::Muls,
+            "divs" => Opcode::Divs,
+            "fcvts" => Opcode::Fcvts,
+            "fcvtd" => Opcode::Fcvtd,
+            "rfcsr" => Opcode::Rfcsr,
+            "wfcsr" => Opcode::Wfcsr,
+            "dfrm" => Opcode::Dfrm,
+            "dfclr" => Opcode::Dfclr,
+            "sqrt" => Opcode::Sqrt,
+            "sqrts" => Opcode::Sqrts,
+            "fabs" => Opcode::Fabs,
+            "fabss" => Opcode::Fabss,
+            "floor" => Opcode::Floor,
+            "floors" => Opcode::Floors,
+            "ceil" => Opcode::Ceil,
+            "ceils" => Opcode::Ceils,
+            "round" => Opcode::Round,
+            "rounds" => Opcode::Rounds,
+            "sin" => Opcode::Sin,
+            "sins" => Opcode::Sins,
+            "cos" => Opcode::Cos,
+            "coss" => Opcode::Coss,
+            "exp" => Opcode::Exp,
+            "exps" => Opcode::Exps,
+            "log" => Opcode::Log,
+            "logs" => Opcode::Logs,
+            "fmin" => Opcode::Fmin,
+            "fmins" => Opcode::Fmins,
+            "fmax" => Opcode::Fmax,
+            "fmaxs" => Opcode::Fmaxs,
+            "copysign" => Opcode::Copysign,
+            "copysigns" => Opcode::Copysigns,
+            "pow" => Opcode::Pow,
+            "pows" => Opcode::Pows,
+            "fma" => Opcode::Fma,
+            "fmas" => Opcode::Fmas,
+            "vadd" => Opcode::Vadd,
+            "vsub" => Opcode::Vsub,
+            "vmul" => Opcode::Vmul,
+            "vaddss" => Opcode::Vaddss,
+            "vaddus" => Opcode::Vaddus,
+            "vsubss" => Opcode::Vsubss,
+            "vsubus" => Opcode::Vsubus,
+            "vaddw" => Opcode::Vaddw,
+            "vaddwu" => Opcode::Vaddwu,
+            "vmulw" => Opcode::Vmulw,
+            "vmulwu" => Opcode::Vmulwu,
+            "vshuf" => Opcode::Vshuf,
+            "vperm" => Opcode::Vperm,
+            "vins" => Opcode::Vins,
+            "vext" => Opcode::Vext,
+            "vbcast" => Opcode::Vbcast,
+            "vzipl" => Opcode::Vzipl,
+            "vziph" => Opcode::Vziph,
+            "vuzpe" => Opcode::Vuzpe,
+            "vuzpo" => Opcode::Vuzpo,
+            "vhsum" => Opcode::Vhsum,
+            "vhmin" => Opcode::Vhmin,
+            "vhmax" => Opcode::Vhmax,
+            "vhminu" => Opcode::Vhminu,
+            "vhmaxu" => Opcode::Vhmaxu,
+            "vhany" => Opcode::Vhany,
+            "vhall" => Opcode::Vhall,
+            "vcmpeq" => Opcode::Vcmpeq,
+            "vcmpne" => Opcode::Vcmpne,
+            "vcmplt" => Opcode::Vcmplt,
+            "vcmple" => Opcode::Vcmple,
+            "vcmpltu" => Opcode::Vcmpltu,
+            "vcmpleu" => Opcode::Vcmpleu,
+            "vblend" => Opcode::Vblend,
+            "vmovz" => Opcode::Vmovz,
+            "vld" => Opcode::Vld,
+            "vlda" => Opcode::Vlda,
+            "vlds" => Opcode::Vlds,
+            "vgather" => Opcode::Vgather,
+            "vst" => Opcode::Vst,
+            "vsta" => Opcode::Vsta,
+            "vsts" => Opcode::Vsts,
+            "vscatter" => Opcode::Vscatter,
+            "wadd" => Opcode::Wadd,
+            "wadc" => Opcode::Wadc,
+            "wsub" => Opcode::Wsub,
+            "wsbb" => Opcode::Wsbb,
+            "wmul" => Opcode::Wmul,
+            "wmuls" => Opcode::Wmuls,
+            "wshl" => Opcode::Wshl,
+            "wshr" => Opcode::Wshr,
+            "wsar" => Opcode::Wsar,
+            "wrotl" => Opcode::Wrotl,
+            "wrotr" => Opcode::Wrotr,
+            "wcmp" => Opcode::Wcmp,
+            "wcmps" => Opcode::Wcmps,
+            "sdiv" => Opcode::Sdiv,
+            "smod" => Opcode::Smod,
+            "sar" => Opcode::Sar,
+            "mulh" => Opcode::Mulh,
+            "mulhu" => Opcode::Mulhu,
+            "divmod" => Opcode::Divmod,
+            "sdivmod" => Opcode::Sdivmod,
+            "sext" => Opcode::Sext,
+            "zext" => Opcode::Zext,
+            "cadd" => Opcode::Cadd,
+            "csub" => Opcode::Csub,
+            "cmul" => Opcode::Cmul,
+            "cinc" => Opcode::Cinc,
+            "cdec" => Opcode::Cdec,
+            "cneg" => Opcode::Cneg,
+            "qadd" => Opcode::Qadd,
+            "qsub" => Opcode::Qsub,
+            "qmul" => Opcode::Qmul,
+            "qinc" => Opcode::Qinc,
+            "qdec" => Opcode::Qdec,
+            "qneg" => Opcode::Qneg,
+            "rflags" => Opcode::Rflags,
+            "popcnt" => Opcode::Popcnt,
+            "clz" => Opcode::Clz,
+            "ctz" => Opcode::Ctz,
+            "rotl" => Opcode::Rotl,
+            "rotr" => Opcode::Rotr,
+            "bswap" => Opcode::Bswap,
+            "bitrev" => Opcode::Bitrev,
+            "bfext" => Opcode::Bfext,
+            "bfins" => Opcode::Bfins,
+            "bset" => Opcode::Bset,
+            "bclr" => Opcode::Bclr,
+            "btst" => Opcode::Btst,
+            "vpopcnt" => Opcode::Vpopcnt,
+            "vclz" => Opcode::Vclz,
+            "vctz" => Opcode::Vctz,
+            "vrotl" => Opcode::Vrotl,
+            "vrotr" => Opcode::Vrotr,
+            "vbswap" => Opcode::Vbswap,
+            "vbitrev" => Opcode::Vbitrev,
+            "cmovc" => Opcode::Cmovc,
+            "cmovnc" => Opcode::Cmovnc,
+            "cmovo" => Opcode::Cmovo,
+            "cmovno" => Opcode::Cmovno,
+            "cmovz" => Opcode::Cmovz,
+            "cmovnz" => Opcode::Cmovnz,
+            "cmovlz" => Opcode::Cmovlz,
+            "cmovgez" => Opcode::Cmovgez,
+            "jmp" => Opcode::Jmp,
+            "call" => Opcode::Call,
+            "jtab" => Opcode::Jtab,
+            "adr" => Opcode::Adr,
+            "jl" => Opcode::Jl,
+            "memcpy" => Opcode::Memcpy,
+            "memmove" => Opcode::Memmove,
+            "memset" => Opcode::Memset,
+            "memcmp" => Opcode::Memcmp,
+            "memchr" => Opcode::Memchr,
+            "ald" => Opcode::Ald,
+            "ast" => Opcode::Ast,
+            "acas" => Opcode::Acas,
+            "aadd" => Opcode::Aadd,
+            "aand" => Opcode::Aand,
+            "aor" => Opcode::Aor,
+            "axor" => Opcode::Axor,
+            "aswp" => Opcode::Aswp,
+            "fence" => Opcode::Fence,
+            "cid" => Opcode::Cid,
+            "spawn" => Opcode::Spawn,
+            "park" => Opcode::Park,
+            "wake" => Opcode::Wake,
+            "tvec" => Opcode::Tvec,
+            "rtt" => Opcode::Rtt,
+            "ie" => Opcode::Ie,
+            "imask" => Opcode::Imask,
+            "ipend" => Opcode::Ipend,
+            "timer" => Opcode::Timer,
+            "ext" => Opcode::Ext,
+            _ => Opcode::Nop,
+        }
     }
 }
 impl From<Opcode> for &str {
-    fn from(_: Opcode) -> Self {
-        todo!()
+    fn from(val: Opcode) -> Self {
+        match val {
+            Opcode::Nop => "nop",
+            Opcode::Set => "set",
+            Opcode::Get => "get",
+            Opcode::Update => "update",
+            Opcode::Delete => "delete",
+            Opcode::Swap => "swap",
+            Opcode::And => "and",
+            Opcode::Or => "or",
+            Opcode::Xor => "xor",
+            Opcode::Not => "not",
+            Opcode::Shl => "shl",
+            Opcode::Shr => "shr",
+            Opcode::Dand => "dand",
+            Opcode::Dor => "dor",
+            Opcode::Dxor => "dxor",
+            Opcode::Dnot => "dnot",
+            Opcode::Dshl => "dshl",
+            Opcode::Dshr => "dshr",
+            Opcode::Add => "add",
+            Opcode::Sub => "sub",
+            Opcode::Mul => "mul",
+            Opcode::Div => "div",
+            Opcode::Mod => "mod",
+            Opcode::Inc => "inc",
+            Opcode::Dec => "dec",
+            Opcode::Neg => "neg",
+            Opcode::Dadd => "dadd",
+            Opcode::Dsub => "dsub",
+            Opcode::Dmul => "dmul",
+            Opcode::Ddiv => "ddiv",
+            Opcode::Dmod => "dmod",
+            Opcode::Dinc => "dinc",
+            Opcode::Ddec => "ddec",
+            Opcode::Dneg => "dneg",
+            Opcode::Itof => "itof",
+            Opcode::Utof => "utof",
+            Opcode::Ftoi => "ftoi",
+            Opcode::Ftou => "ftou",
+            Opcode::Rftoi => "rftoi",
+            Opcode::Rftou => "rftou",
+            Opcode::Adds => "adds",
+            Opcode::Subs => "subs",
+            Opcode::Muls => "muls",
+            Opcode::Divs => "divs",
+            Opcode::Fcvts => "fcvts",
+            Opcode::Fcvtd => "fcvtd",
+            Opcode::Rfcsr => "rfcsr",
+            Opcode::Wfcsr => "wfcsr",
+            Opcode::Dfrm => "dfrm",
+            Opcode::Dfclr => "dfclr",
+            Opcode::Sqrt => "sqrt",
+            Opcode::Sqrts => "sqrts",
+            Opcode::Fabs => "fabs",
+            Opcode::Fabss => "fabss",
+            Opcode::Floor => "floor",
+            Opcode::Floors => "floors",
+            Opcode::Ceil => "ceil",
+            Opcode::Ceils => "ceils",
+            Opcode::Round => "round",
+            Opcode::Rounds => "rounds",
+            Opcode::Sin => "sin",
+            Opcode::Sins => "sins",
+            Opcode::Cos => "cos",
+            Opcode::Coss => "coss",
+            Opcode::Exp => "exp",
+            Opcode::Exps => "exps",
+            Opcode::Log => "log",
+            Opcode::Logs => "logs",
+            Opcode::Fmin => "fmin",
+            Opcode::Fmins => "fmins",
+            Opcode::Fmax => "fmax",
+            Opcode::Fmaxs => "fmaxs",
+            Opcode::Copysign => "copysign",
+            Opcode::Copysigns => "copysigns",
+            Opcode::Pow => "pow",
+            Opcode::Pows => "pows",
+            Opcode::Fma => "fma",
+            Opcode::Fmas => "fmas",
+            Opcode::Vadd => "vadd",
+            Opcode::Vsub => "vsub",
+            Opcode::Vmul => "vmul",
+            Opcode::Vaddss => "vaddss",
+            Opcode::Vaddus => "vaddus",
+            Opcode::Vsubss => "vsubss",
+            Opcode::Vsubus => "vsubus",
+            Opcode::Vaddw => "vaddw",
+            Opcode::Vaddwu => "vaddwu",
+            Opcode::Vmulw => "vmulw",
+            Opcode::Vmulwu => "vmulwu",
+            Opcode::Vshuf => "vshuf",
+            Opcode::Vperm => "vperm",
+            Opcode::Vins => "vins",
+            Opcode::Vext => "vext",
+            Opcode::Vbcast => "vbcast",
+            Opcode::Vzipl => "vzipl",
+            Opcode::Vziph => "vziph",
+            Opcode::Vuzpe => "vuzpe",
+            Opcode::Vuzpo => "vuzpo",
+            Opcode::Vhsum => "vhsum",
+            Opcode::Vhmin => "vhmin",
+            Opcode::Vhmax => "vhmax",
+            Opcode::Vhminu => "vhminu",
+            Opcode::Vhmaxu => "vhmaxu",
+            Opcode::Vhany => "vhany",
+            Opcode::Vhall => "vhall",
+            Opcode::Vcmpeq => "vcmpeq",
+            Opcode::Vcmpne => "vcmpne",
+            Opcode::Vcmplt => "vcmplt",
+            Opcode::Vcmple => "vcmple",
+            Opcode::Vcmpltu => "vcmpltu",
+            Opcode::Vcmpleu => "vcmpleu",
+            Opcode::Vblend => "vblend",
+            Opcode::Vmovz => "vmovz",
+            Opcode::Vld => "vld",
+            Opcode::Vlda => "vlda",
+            Opcode::Vlds => "vlds",
+            Opcode::Vgather => "vgather",
+            Opcode::Vst => "vst",
+            Opcode::Vsta => "vsta",
+            Opcode::Vsts => "vsts",
+            Opcode::Vscatter => "vscatter",
+            Opcode::Wadd => "wadd",
+            Opcode::Wadc => "wadc",
+            Opcode::Wsub => "wsub",
+            Opcode::Wsbb => "wsbb",
+            Opcode::Wmul => "wmul",
+            Opcode::Wmuls => "wmuls",
+            Opcode::Wshl => "wshl",
+            Opcode::Wshr => "wshr",
+            Opcode::Wsar => "wsar",
+            Opcode::Wrotl => "wrotl",
+            Opcode::Wrotr => "wrotr",
+            Opcode::Wcmp => "wcmp",
+            Opcode::Wcmps => "wcmps",
+            Opcode::Sdiv => "sdiv",
+            Opcode::Smod => "smod",
+            Opcode::Sar => "sar",
+            Opcode::Mulh => "mulh",
+            Opcode::Mulhu => "mulhu",
+            Opcode::Divmod => "divmod",
+            Opcode::Sdivmod => "sdivmod",
+            Opcode::Sext => "sext",
+            Opcode::Zext => "zext",
+            Opcode::Cadd => "cadd",
+            Opcode::Csub => "csub",
+            Opcode::Cmul => "cmul",
+            Opcode::Cinc => "cinc",
+            Opcode::Cdec => "cdec",
+            Opcode::Cneg => "cneg",
+            Opcode::Qadd => "qadd",
+            Opcode::Qsub => "qsub",
+            Opcode::Qmul => "qmul",
+            Opcode::Qinc => "qinc",
+            Opcode::Qdec => "qdec",
+            Opcode::Qneg => "qneg",
+            Opcode::Rflags => "rflags",
+            Opcode::Popcnt => "popcnt",
+            Opcode::Clz => "clz",
+            Opcode::Ctz => "ctz",
+            Opcode::Rotl => "rotl",
+            Opcode::Rotr => "rotr",
+            Opcode::Bswap => "bswap",
+            Opcode::Bitrev => "bitrev",
+            Opcode::Bfext => "bfext",
+            Opcode::Bfins => "bfins",
+            Opcode::Bset => "bset",
+            Opcode::Bclr => "bclr",
+            Opcode::Btst => "btst",
+            Opcode::Vpopcnt => "vpopcnt",
+            Opcode::Vclz => "vclz",
+            Opcode::Vctz => "vctz",
+            Opcode::Vrotl => "vrotl",
+            Opcode::Vrotr => "vrotr",
+            Opcode::Vbswap => "vbswap",
+            Opcode::Vbitrev => "vbitrev",
+            Opcode::Cmovc => "cmovc",
+            Opcode::Cmovnc => "cmovnc",
+            Opcode::Cmovo => "cmovo",
+            Opcode::Cmovno => "cmovno",
+            Opcode::Cmovz => "cmovz",
+            Opcode::Cmovnz => "cmovnz",
+            Opcode::Cmovlz => "cmovlz",
+            Opcode::Cmovgez => "cmovgez",
+            Opcode::Jmp => "jmp",
+            Opcode::Call => "call",
+            Opcode::Jtab => "jtab",
+            Opcode::Adr => "adr",
+            Opcode::Jl => "jl",
+            Opcode::Memcpy => "memcpy",
+            Opcode::Memmove => "memmove",
+            Opcode::Memset => "memset",
+            Opcode::Memcmp => "memcmp",
+            Opcode::Memchr => "memchr",
+            Opcode::Ald => "ald",
+            Opcode::Ast => "ast",
+            Opcode::Acas => "acas",
+            Opcode::Aadd => "aadd",
+            Opcode::Aand => "aand",
+            Opcode::Aor => "aor",
+            Opcode::Axor => "axor",
+            Opcode::Aswp => "aswp",
+            Opcode::Fence => "fence",
+            Opcode::Cid => "cid",
+            Opcode::Spawn => "spawn",
+            Opcode::Park => "park",
+            Opcode::Wake => "wake",
+            Opcode::Tvec => "tvec",
+            Opcode::Rtt => "rtt",
+            Opcode::Ie => "ie",
+            Opcode::Imask => "imask",
+            Opcode::Ipend => "ipend",
+            Opcode::Timer => "timer",
+            Opcode::Ext => "ext",
+        }
     }
 }
@@ -0,0 +1,176 @@
+/*
+Output formats for assembled S64 code.
+Addresses are instruction indices (pc units), byte addresses are address * 4.
+Code whose byte addresses don't fit the format is rejected rather than wrapped.
+*/
+
+use crate::{
+    compiler::decompile,
+    vm::sfile::{
+        Arch, Binding, FileType, Magic, SFile, SFileError, SFileHeader, Section, SectionKind,
+        Symbol,
+    },
+};
+
+pub(crate) enum OutputFormat {
+    Raw,      //Little-endian u32 instruction stream
+    IntelHex, //Intel HEX records
+    Hexdump,  //Annotated hexdump listing
+    SFile,    //Executable SFile with a single .text section
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum OutputError {
+    AddressRange(u64), //Address (pc units) whose byte address the format can't hold
+    SFile(SFileError), //SFile tables don't fit their u16/u32 fields
+}
+
+impl From<&str> for OutputFormat {
+    fn from(val: &str) -> Self {
+        match val {
+            "raw" | "bin" => OutputFormat::Raw,
+            "ihex" | "hex" => OutputFormat::IntelHex,
+            "hexdump" | "dump" => OutputFormat::Hexdump,
+            "sfile" | "sf" => OutputFormat::SFile,
+            _ => OutputFormat::Raw,
+        }
+    }
+}
+impl From<OutputFormat> for &str {
+    fn from(val: OutputFormat) -> Self {
+        match val {
+            OutputFormat::Raw => "raw",
+            OutputFormat::IntelHex => "ihex",
+            OutputFormat::Hexdump => "hexdump",
+            OutputFormat::SFile => "sfile",
+        }
+    }
+}
+
+pub(crate) fn emit(
+    code: &[u32],
+    format: OutputFormat,
+    base: u64,
+    entry: u64,
+) -> Result<Vec<u8>, OutputError> {
+    Ok(match format {
+        OutputFormat::Raw => emit_raw(code),
+        OutputFormat::IntelHex => emit_ihex(code, base, entry)?.into_bytes(),
+        OutputFormat::Hexdump => emit_hexdump(code, base)?.into_bytes(),
+        OutputFormat::SFile => emit_sfile(code, base, entry)?
+            .to_bytes()
+            .map_err(OutputError::SFile)?,
+    })
+}
+pub(crate) fn emit_sfile(code: &[u32], base: u64, entry: u64) -> Result<SFile, OutputError> {
+    let address = byte_address(base)?;
+    let offset = entry
+        .checked_sub(base)
+        .and_then(|v| u32::try_from(v.checked_mul(4)?).ok())
+        .ok_or(OutputError::AddressRange(entry))?;
+    let mut file = SFile::new(SFileHeader::new(
+        Magic::default(),
+        FileType::Executable,
+        Arch::SS64,
+        0,
+    ));
+    file.sections.push(Section {
+        name: String::from(".text"),
+        kind: SectionKind::Code.into(),
+        address,
+        data: emit_raw(code),
+    });
+    file.symbols.push(Symbol {
+        name: String::from("_start"),
+        section: 0,
+        offset,
+        binding: Binding::Global.into(),
+    });
+    Ok(file)
+}
+pub(crate) fn emit_raw(code: &[u32]) -> Vec<u8> {
+    code.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+//Intel HEX reaches 4G bytes through extended linear address records
+pub(crate) fn emit_ihex(code: &[u32], base: u64, entry: u64) -> Result<String, OutputError> {
+    let bytes = emit_raw(code);
+    let start = byte_address(base)?;
+    let end = end_address(code, base)?;
+    if end > 1 << 32 {
+        let address = if start >= 1 << 32 { base } else { end / 4 };
+        return Err(OutputError::AddressRange(address));
+    }
+    let entry =
+        u32::try_from(byte_address(entry)?).map_err(|_| OutputError::AddressRange(entry))?;
+    let mut out = String::new();
+    let mut upper = None;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let address = start + offset as u64;
+        //Records must not cross a 64K segment
+        let len = (bytes.len() - offset)
+            .min(16)
+            .min(0x10000 - (address & 0xFFFF) as usize);
+        let segment =
+            u16::try_from(address >> 16).map_err(|_| OutputError::AddressRange(address / 4))?;
+        if upper != Some(segment) {
+            upper = Some(segment);
+            out.push_str(&ihex_record(0x04, 0, &segment.to_be_bytes()));
+        }
+        out.push_str(&ihex_record(
+            0x00,
+            address as u16,
+            &bytes[offset..offset + len],
+        ));
+        offset += len;
+    }
+    out.push_str(&ihex_record(0x05, 0, &entry.to_be_bytes()));
+    out.push_str(&ihex_record(0x01, 0, &[]));
+    Ok(out)
+}
+pub(crate) fn emit_hexdump(code: &[u32], base: u64) -> Result<String, OutputError> {
+    end_address(code, base)?;
+    let mut out = String::new();
+    for (k, (text, len)) in decompile(code) {
+        for (n, v) in code[k..k + len].iter().enumerate() {
+            let bytes = v.to_le_bytes();
+            out.push_str(&format!(
+                "{:08x}  {:02x} {:02x} {:02x} {:02x}  {}\n",
+                (base + (k + n) as u64) * 4,
+                bytes[0],
+                bytes[1],
+                bytes[2],
+                bytes[3],
+                if n == 0 { text.as_str() } else { "" }
+            ));
+        }
+    }
+    Ok(out)
+}
+fn byte_address(address: u64) -> Result<u64, OutputError> {
+    address
+        .checked_mul(4)
+        .ok_or(OutputError::AddressRange(address))
+}
+//Byte address just past the code
+fn end_address(code: &[u32], base: u64) -> Result<u64, OutputError> {
+    let end = base.checked_add(code.len() as u64);
+    byte_address(end.ok_or(OutputError::AddressRange(base))?)
+}
+fn ihex_record(kind: u8, address: u16, data: &[u8]) -> String {
+    let mut record = vec![data.len() as u8];
+    record.extend_from_slice(&address.to_be_bytes());
+    record.push(kind);
+    record.extend_from_slice(data);
+    let checksum = record
+        .iter()
+        .fold(0u8, |acc, v| acc.wrapping_add(*v))
+        .wrapping_neg();
+    record.push(checksum);
+    let mut line = String::from(":");
+    for v in record {
+        line.push_str(&format!("{:02X}", v));
+    }
+    line.push('\n');
+    line
+}
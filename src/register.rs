@@ -90,3 +90,69 @@ impl From<&str> for Register {
         }
     }
 }
+
+//Registers past the compact 16 come in banks that extend a kind of compact register,
+//only the extended encoding reaches them
+pub(crate) struct Bank {
+    pub prefix: &'static str,
+    pub start: u8, //Index of the first register of the bank
+    pub first: u8, //Number in the name of the first register
+    pub count: u8,
+    pub compact: [u8; 3], //Compact registers of the same kind
+}
+
+pub(crate) const BANKS: [Bank; 4] = [
+    Bank {
+        prefix: "r",
+        start: 16,
+        first: 3,
+        count: 29,
+        compact: [Register::R0 as u8, Register::R1 as u8, Register::R2 as u8],
+    },
+    Bank {
+        prefix: "f",
+        start: 45,
+        first: 3,
+        count: 29,
+        compact: [Register::F0 as u8, Register::F1 as u8, Register::F2 as u8],
+    },
+    Bank {
+        prefix: "s",
+        start: 74,
+        first: 3,
+        count: 5,
+        compact: [Register::S0 as u8, Register::S1 as u8, Register::S2 as u8],
+    },
+    Bank {
+        prefix: "x",
+        start: 79,
+        first: 3,
+        count: 5,
+        compact: [Register::X0 as u8, Register::X1 as u8, Register::X2 as u8],
+    },
+];
+pub(crate) const REGISTERS: usize = 84;
+
+pub(crate) fn bank(index: u8) -> Option<&'static Bank> {
+    BANKS
+        .iter()
+        .find(|v| (v.start..v.start + v.count).contains(&index))
+}
+//r0..r31, f0..f31, p0..p2, s0..s7, x0..x7, unknown indices read as zr
+pub(crate) fn name(index: u8) -> String {
+    match bank(index) {
+        Some(bank) => format!("{}{}", bank.prefix, index - bank.start + bank.first),
+        None => <&str>::from(Register::from(index)).to_string(),
+    }
+}
+pub(crate) fn index(name: &str) -> Option<u8> {
+    let compact = u8::from(Register::from(name));
+    if <&str>::from(Register::from(compact)) == name {
+        return Some(compact);
+    }
+    BANKS.iter().find_map(|bank| {
+        let number = name.strip_prefix(bank.prefix)?.parse::<u8>().ok()?;
+        let offset = number.checked_sub(bank.first).filter(|v| *v < bank.count)?;
+        Some(bank.start + offset)
+    })
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{self, AtomicU64, Ordering};
+
+/*
+Guest atomics on aligned u64 words of data memory, carried out by the host atomics.
+The order operand picks the memory ordering: 0 seq_cst, 1 acquire, 2 release, 3 acq_rel and
+4 relaxed, anything else is seq_cst. Orderings that don't apply to an access are strengthened
+(a release load or acquire store is seq_cst) and a relaxed fence does nothing.
+*/
+
+#[derive(Clone, Copy)]
+pub(crate) enum AtomicOp {
+    Add,
+    And,
+    Or,
+    Xor,
+    Swap,
+}
+
+pub(crate) fn ordering(order: u64) -> Ordering {
+    match order {
+        1 => Ordering::Acquire,
+        2 => Ordering::Release,
+        3 => Ordering::AcqRel,
+        4 => Ordering::Relaxed,
+        _ => Ordering::SeqCst,
+    }
+}
+pub(crate) fn load(word: &AtomicU64, order: u64) -> u64 {
+    match ordering(order) {
+        Ordering::Release | Ordering::AcqRel => word.load(Ordering::SeqCst),
+        order => word.load(order),
+    }
+}
+pub(crate) fn store(word: &AtomicU64, val: u64, order: u64) {
+    match ordering(order) {
+        Ordering::Acquire | Ordering::AcqRel => word.store(val, Ordering::SeqCst),
+        order => word.store(val, order),
+    }
+}
+//Previous value
+pub(crate) fn fetch(word: &AtomicU64, op: AtomicOp, val: u64, order: u64) -> u64 {
+    let order = ordering(order);
+    match op {
+        AtomicOp::Add => word.fetch_add(val, order),
+        AtomicOp::And => word.fetch_and(val, order),
+        AtomicOp::Or => word.fetch_or(val, order),
+        AtomicOp::Xor => word.fetch_xor(val, order),
+        AtomicOp::Swap => word.swap(val, order),
+    }
+}
+//Previous value and whether it was swapped, always seq_cst
+pub(crate) fn compare_swap(word: &AtomicU64, expected: u64, new: u64) -> (u64, bool) {
+    match word.compare_exchange(expected, new, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(val) => (val, true),
+        Err(val) => (val, false),
+    }
+}
+pub(crate) fn fence(order: u64) {
+    match ordering(order) {
+        Ordering::Relaxed => {}
+        order => atomic::fence(order),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orderings_are_strengthened_where_they_dont_apply() {
+        use Ordering::*;
+        let orders = [0, 1, 2, 3, 4, 5].map(ordering);
+        assert_eq!(orders, [SeqCst, Acquire, Release, AcqRel, Relaxed, SeqCst]);
+        //std panics on a release load or an acquire store, every order is valid here
+        let word = AtomicU64::new(1);
+        for order in 0..6 {
+            store(&word, order + 10, order);
+            assert_eq!(load(&word, order), order + 10);
+            fence(order);
+        }
+        assert_eq!(fetch(&word, AtomicOp::Add, 5, 2), 15);
+        assert_eq!(fetch(&word, AtomicOp::Swap, 3, 1), 20);
+        assert_eq!(compare_swap(&word, 4, 9), (3, false));
+        assert_eq!(compare_swap(&word, 3, 9), (3, true));
+        assert_eq!(word.load(SeqCst), 9);
+    }
+}
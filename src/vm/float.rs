@@ -0,0 +1,922 @@
+/*
+IEEE-754 helpers for the F registers and float lanes.
+The host always rounds to nearest-even, other rounding modes are derived from the
+sign of the rounding error, which is recovered with error-free transformations.
+fcsr layout: bits 0..5 exception flags, bits 5..7 rounding mode.
+*/
+
+use std::cmp::Ordering;
+
+pub(crate) const FLAG_INEXACT: u64 = 1 << 0;
+pub(crate) const FLAG_UNDERFLOW: u64 = 1 << 1;
+pub(crate) const FLAG_OVERFLOW: u64 = 1 << 2;
+pub(crate) const FLAG_DIVZERO: u64 = 1 << 3;
+pub(crate) const FLAG_INVALID: u64 = 1 << 4;
+pub(crate) const FLAGS: u64 = 0b11111;
+pub(crate) const RM_SHIFT: u64 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RoundingMode {
+    NearestEven,
+    TowardZero,
+    Down,
+    Up,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum FloatOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+//Unary ops read the second operand, binary ops are (first, second)
+#[derive(Clone, Copy)]
+pub(crate) enum MathOp {
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Sin,
+    Cos,
+    Exp,
+    Log,
+    Min,
+    Max,
+    Copysign,
+    Pow,
+}
+
+impl From<u64> for RoundingMode {
+    fn from(val: u64) -> Self {
+        match val & 0b11 {
+            0 => RoundingMode::NearestEven,
+            1 => RoundingMode::TowardZero,
+            2 => RoundingMode::Down,
+            3 => RoundingMode::Up,
+            _ => RoundingMode::NearestEven,
+        }
+    }
+}
+impl From<RoundingMode> for u64 {
+    fn from(val: RoundingMode) -> Self {
+        match val {
+            RoundingMode::NearestEven => 0,
+            RoundingMode::TowardZero => 1,
+            RoundingMode::Down => 2,
+            RoundingMode::Up => 3,
+        }
+    }
+}
+
+//Result and the sign of (exact - result), computed with the host's nearest-even rounding
+fn exact64(op: FloatOp, a: f64, b: f64) -> (f64, Ordering) {
+    match op {
+        FloatOp::Add | FloatOp::Sub => {
+            let b = if matches!(op, FloatOp::Sub) { -b } else { b };
+            let (s, err) = two_sum(a, b);
+            if !s.is_finite() {
+                return (s, Ordering::Equal);
+            }
+            (s, sign(err))
+        }
+        FloatOp::Mul => {
+            let p = a * b;
+            if !p.is_finite() {
+                return (p, Ordering::Equal);
+            }
+            if p == 0.0 && a != 0.0 && b != 0.0 {
+                //Underflowed to zero, the exact product has the sign of the inputs
+                return (p, sign(a.signum() * b.signum()));
+            }
+            (p, sign(a.mul_add(b, -p)))
+        }
+        FloatOp::Div => {
+            let q = a / b;
+            if !q.is_finite() || b == 0.0 {
+                return (q, Ordering::Equal);
+            }
+            if q == 0.0 && a != 0.0 && b.is_finite() {
+                return (q, sign(a.signum() * b.signum()));
+            }
+            //a = q*b + r, so the exact quotient is above q when r and b share a sign
+            let r = (-q).mul_add(b, a);
+            (q, sign(r * b.signum()))
+        }
+        FloatOp::Rem => (a % b, Ordering::Equal),
+    }
+}
+fn sign(val: f64) -> Ordering {
+    val.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+}
+fn invalid(op: FloatOp, a: f64, b: f64) -> bool {
+    match op {
+        FloatOp::Add => a.is_infinite() && b.is_infinite() && a.signum() != b.signum(),
+        FloatOp::Sub => a.is_infinite() && b.is_infinite() && a.signum() == b.signum(),
+        FloatOp::Mul => (a.is_infinite() && b == 0.0) || (a == 0.0 && b.is_infinite()),
+        FloatOp::Div => (a == 0.0 && b == 0.0) || (a.is_infinite() && b.is_infinite()),
+        FloatOp::Rem => a.is_infinite() || b == 0.0,
+    }
+}
+//Flags that only depend on the operands
+fn operand_flags(op: FloatOp, a: f64, b: f64) -> u64 {
+    let mut flags = 0;
+    if is_snan64(a) || is_snan64(b) || (!a.is_nan() && !b.is_nan() && invalid(op, a, b)) {
+        flags |= FLAG_INVALID;
+    }
+    if matches!(op, FloatOp::Div) && b == 0.0 && a.is_finite() && a != 0.0 {
+        flags |= FLAG_DIVZERO;
+    }
+    flags
+}
+pub(crate) fn op64(op: FloatOp, a: f64, b: f64, rm: RoundingMode) -> (f64, u64) {
+    let mut flags = operand_flags(op, a, b);
+    let (val, err) = exact64(op, a, b);
+    //Division by zero gives an exact infinity, it doesn't overflow
+    let finite = a.is_finite() && b.is_finite() && flags & FLAG_DIVZERO == 0;
+    let val = round64(val, err, finite, rm, &mut flags);
+    (canonical64(val), flags)
+}
+pub(crate) fn op32(op: FloatOp, a: f32, b: f32, rm: RoundingMode) -> (f32, u64) {
+    let (wide_a, wide_b) = (a as f64, b as f64);
+    let mut flags = operand_flags(op, wide_a, wide_b);
+    if is_snan32(a) || is_snan32(b) {
+        flags |= FLAG_INVALID;
+    }
+    //f64 has more than 2*24+2 bits, so rounding the f64 result again is still correct
+    let (wide, wide_err) = exact64(op, wide_a, wide_b);
+    let (val, err) = narrow(wide, wide_err);
+    let finite = a.is_finite() && b.is_finite() && flags & FLAG_DIVZERO == 0;
+    let val = round32(val, err, finite, rm, &mut flags);
+    (canonical32(val), flags)
+}
+//f64 -> f32 with the sign of (exact - result), given the sign of the f64's own error
+fn narrow(wide: f64, wide_err: Ordering) -> (f32, Ordering) {
+    let val = wide as f32;
+    if val.is_nan() || val.is_infinite() {
+        return (val, Ordering::Equal);
+    }
+    match (val as f64).partial_cmp(&wide) {
+        Some(Ordering::Less) => (val, Ordering::Greater),
+        Some(Ordering::Greater) => (val, Ordering::Less),
+        _ => (val, wide_err),
+    }
+}
+fn round64(val: f64, err: Ordering, finite: bool, rm: RoundingMode, flags: &mut u64) -> f64 {
+    if val.is_nan() {
+        return val;
+    }
+    if val.is_infinite() {
+        if finite {
+            *flags |= FLAG_OVERFLOW | FLAG_INEXACT;
+            return overflow64(val, rm);
+        }
+        return val;
+    }
+    if err == Ordering::Equal {
+        return val;
+    }
+    *flags |= FLAG_INEXACT;
+    let val = match (rm, err) {
+        (RoundingMode::Up, Ordering::Greater) => val.next_up(),
+        (RoundingMode::Down, Ordering::Less) => val.next_down(),
+        (RoundingMode::TowardZero, Ordering::Less) if val > 0.0 => val.next_down(),
+        (RoundingMode::TowardZero, Ordering::Greater) if val < 0.0 => val.next_up(),
+        //A zero result from a non-zero exact value keeps the exact value's sign
+        (_, Ordering::Less) if val == 0.0 => -0.0,
+        _ => val,
+    };
+    if val.is_infinite() {
+        *flags |= FLAG_OVERFLOW;
+    } else if val.abs() < f64::MIN_POSITIVE {
+        *flags |= FLAG_UNDERFLOW;
+    }
+    val
+}
+fn round32(val: f32, err: Ordering, finite: bool, rm: RoundingMode, flags: &mut u64) -> f32 {
+    if val.is_nan() {
+        return val;
+    }
+    if val.is_infinite() {
+        if finite {
+            *flags |= FLAG_OVERFLOW | FLAG_INEXACT;
+            return overflow32(val, rm);
+        }
+        return val;
+    }
+    if err == Ordering::Equal {
+        return val;
+    }
+    *flags |= FLAG_INEXACT;
+    let val = match (rm, err) {
+        (RoundingMode::Up, Ordering::Greater) => val.next_up(),
+        (RoundingMode::Down, Ordering::Less) => val.next_down(),
+        (RoundingMode::TowardZero, Ordering::Less) if val > 0.0 => val.next_down(),
+        (RoundingMode::TowardZero, Ordering::Greater) if val < 0.0 => val.next_up(),
+        (_, Ordering::Less) if val == 0.0 => -0.0,
+        _ => val,
+    };
+    if val.is_infinite() {
+        *flags |= FLAG_OVERFLOW;
+    } else if val.abs() < f32::MIN_POSITIVE {
+        *flags |= FLAG_UNDERFLOW;
+    }
+    val
+}
+fn overflow64(val: f64, rm: RoundingMode) -> f64 {
+    match rm {
+        RoundingMode::NearestEven => val,
+        RoundingMode::TowardZero => f64::MAX.copysign(val),
+        RoundingMode::Up if val < 0.0 => f64::MIN,
+        RoundingMode::Down if val > 0.0 => f64::MAX,
+        _ => val,
+    }
+}
+fn overflow32(val: f32, rm: RoundingMode) -> f32 {
+    match rm {
+        RoundingMode::NearestEven => val,
+        RoundingMode::TowardZero => f32::MAX.copysign(val),
+        RoundingMode::Up if val < 0.0 => f32::MIN,
+        RoundingMode::Down if val > 0.0 => f32::MAX,
+        _ => val,
+    }
+}
+//f64 -> f32 under the given rounding mode
+pub(crate) fn narrow64(val: f64, rm: RoundingMode) -> (f32, u64) {
+    let mut flags = if is_snan64(val) { FLAG_INVALID } else { 0 };
+    let (narrowed, err) = narrow(val, Ordering::Equal);
+    let narrowed = round32(narrowed, err, val.is_finite(), rm, &mut flags);
+    (canonical32(narrowed), flags)
+}
+pub(crate) fn math64(op: MathOp, a: f64, b: f64, rm: RoundingMode) -> (f64, u64) {
+    let mut flags = 0;
+    let binary = matches!(
+        op,
+        MathOp::Min | MathOp::Max | MathOp::Copysign | MathOp::Pow
+    );
+    if is_snan64(b) || (binary && is_snan64(a)) {
+        flags |= FLAG_INVALID;
+    }
+    let val = match op {
+        MathOp::Sqrt => {
+            if b < 0.0 {
+                flags |= FLAG_INVALID;
+            }
+            let root = b.sqrt();
+            let err = if root.is_finite() {
+                sign((-root).mul_add(root, b))
+            } else {
+                Ordering::Equal
+            };
+            round64(root, err, b.is_finite(), rm, &mut flags)
+        }
+        MathOp::Abs => b.abs(),
+        MathOp::Floor => b.floor(),
+        MathOp::Ceil => b.ceil(),
+        MathOp::Round => b.round(),
+        //Transcendentals are computed by the host and always round to nearest
+        MathOp::Sin | MathOp::Cos | MathOp::Exp | MathOp::Log => transcendental(op, b, &mut flags),
+        MathOp::Min => min_num(a, b, true),
+        MathOp::Max => min_num(a, b, false),
+        MathOp::Copysign => a.copysign(b),
+        MathOp::Pow => {
+            let val = a.powf(b);
+            if val.is_nan() && !a.is_nan() && !b.is_nan() {
+                flags |= FLAG_INVALID;
+            } else if val.is_infinite() && a.is_finite() && b.is_finite() {
+                flags |= if a == 0.0 {
+                    FLAG_DIVZERO
+                } else {
+                    FLAG_OVERFLOW | FLAG_INEXACT
+                };
+            }
+            val
+        }
+    };
+    (canonical64(val), flags)
+}
+pub(crate) fn math32(op: MathOp, a: f32, b: f32, rm: RoundingMode) -> (f32, u64) {
+    let binary = matches!(
+        op,
+        MathOp::Min | MathOp::Max | MathOp::Copysign | MathOp::Pow
+    );
+    let mut flags = if is_snan32(b) || (binary && is_snan32(a)) {
+        FLAG_INVALID
+    } else {
+        0
+    };
+    let (wide, wide_err) = match op {
+        MathOp::Sqrt => {
+            let wide = (b as f64).sqrt();
+            if b < 0.0 {
+                flags |= FLAG_INVALID;
+            }
+            let err = if wide.is_finite() {
+                sign((-wide).mul_add(wide, b as f64))
+            } else {
+                Ordering::Equal
+            };
+            (wide, err)
+        }
+        _ => {
+            let (wide, wide_flags) = math64(op, a as f64, b as f64, RoundingMode::NearestEven);
+            flags |= wide_flags & !(FLAG_INEXACT | FLAG_OVERFLOW | FLAG_UNDERFLOW);
+            (wide, Ordering::Equal)
+        }
+    };
+    let (val, err) = narrow(wide, wide_err);
+    let finite = a.is_finite() && b.is_finite();
+    let val = round32(val, err, finite, rm, &mut flags);
+    if matches!(op, MathOp::Sin | MathOp::Cos | MathOp::Exp | MathOp::Log) {
+        transcendental_flags(op, b as f64, val as f64, &mut flags);
+    }
+    (canonical32(val), flags)
+}
+pub(crate) fn fma64(a: f64, b: f64, c: f64, rm: RoundingMode) -> (f64, u64) {
+    let mut flags = fma_invalid(a, b, c);
+    let val = a.mul_add(b, c);
+    let finite = a.is_finite() && b.is_finite() && c.is_finite();
+    let val = round64(val, fma_error(a, b, c, val), finite, rm, &mut flags);
+    (canonical64(val), flags)
+}
+pub(crate) fn fma32(a: f32, b: f32, c: f32, rm: RoundingMode) -> (f32, u64) {
+    let (wide_a, wide_b, wide_c) = (a as f64, b as f64, c as f64);
+    let mut flags = fma_invalid(wide_a, wide_b, wide_c);
+    if is_snan32(a) || is_snan32(b) || is_snan32(c) {
+        flags |= FLAG_INVALID;
+    }
+    //Unlike the basic ops, an f64 fma rounded to nearest can round again to the wrong f32,
+    //rounding it to odd instead keeps the second rounding correct
+    let wide = wide_a.mul_add(wide_b, wide_c);
+    let (wide, wide_err) = round_odd(wide, fma_error(wide_a, wide_b, wide_c, wide));
+    let (val, err) = narrow(wide, wide_err);
+    let finite = a.is_finite() && b.is_finite() && c.is_finite();
+    let val = round32(val, err, finite, rm, &mut flags);
+    (canonical32(val), flags)
+}
+fn fma_invalid(a: f64, b: f64, c: f64) -> u64 {
+    let product_invalid = (a.is_infinite() && b == 0.0) || (a == 0.0 && b.is_infinite());
+    let sum_invalid = (a * b).is_infinite() && c.is_infinite() && (a * b).signum() != c.signum();
+    if is_snan64(a) || is_snan64(b) || is_snan64(c) || product_invalid || sum_invalid {
+        FLAG_INVALID
+    } else {
+        0
+    }
+}
+//Sign of (a*b + c - val) for the host's fused result val. The product splits exactly into
+//p + pe unless it is near either end of the exponent range, such operands are scaled by a
+//power of two first
+fn fma_error(a: f64, b: f64, c: f64, val: f64) -> Ordering {
+    let finite = a.is_finite() && b.is_finite() && c.is_finite() && val.is_finite();
+    if !finite || a == 0.0 || b == 0.0 {
+        //Non-finite results are left to round64, zero products are exact
+        return Ordering::Equal;
+    }
+    if val == c {
+        //The product is below half an ulp of c
+        return sign(a.signum() * b.signum());
+    }
+    let (large, small) = if a.abs() < b.abs() { (b, a) } else { (a, b) };
+    let p = a * b;
+    if p.abs() < 2f64.powi(-900) {
+        //c is below 2^-846 here, otherwise the product would be under half its ulp
+        let up = 2f64.powi(600);
+        let (small, c, val) = (small * up * up, c * up * up, val * up * up);
+        let p = large * small;
+        return expansion_sign(&[large.mul_add(small, -p), p, c, -val]);
+    }
+    if p.abs() > 2f64.powi(1000) {
+        //Quartered, a c too small to quarter exactly only decides a tie of a*b and val
+        let (large, val) = (large * 0.25, val * 0.25);
+        let tiny = c.abs() < 2f64.powi(-1000);
+        let p = large * small;
+        let terms = [
+            large.mul_add(small, -p),
+            p,
+            if tiny { 0.0 } else { c * 0.25 },
+            -val,
+        ];
+        return match expansion_sign(&terms) {
+            Ordering::Equal if tiny => sign(c),
+            err => err,
+        };
+    }
+    expansion_sign(&[a.mul_add(b, -p), p, c, -val])
+}
+//Sign of the exact sum: the largest component of its non-overlapping expansion
+fn expansion_sign(terms: &[f64]) -> Ordering {
+    let mut expansion: Vec<f64> = Vec::new();
+    for &term in terms {
+        let mut sum = term;
+        for component in expansion.iter_mut() {
+            (sum, *component) = two_sum(sum, *component);
+        }
+        expansion.push(sum);
+    }
+    expansion
+        .iter()
+        .rev()
+        .find(|v| **v != 0.0)
+        .map_or(Ordering::Equal, |v| sign(*v))
+}
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+//Moves an inexact val to its odd neighbour towards the exact value
+fn round_odd(val: f64, err: Ordering) -> (f64, Ordering) {
+    if err == Ordering::Equal || val.to_bits() & 1 == 1 || !val.is_finite() {
+        return (val, err);
+    }
+    match err {
+        Ordering::Greater => (val.next_up(), Ordering::Less),
+        _ => (val.next_down(), Ordering::Greater),
+    }
+}
+fn transcendental(op: MathOp, val: f64, flags: &mut u64) -> f64 {
+    let out = match op {
+        MathOp::Sin => val.sin(),
+        MathOp::Cos => val.cos(),
+        MathOp::Exp => val.exp(),
+        _ => val.ln(),
+    };
+    transcendental_flags(op, val, out, flags);
+    out
+}
+fn transcendental_flags(op: MathOp, val: f64, out: f64, flags: &mut u64) {
+    if val.is_nan() {
+        return;
+    }
+    match op {
+        MathOp::Sin | MathOp::Cos if val.is_infinite() => *flags |= FLAG_INVALID,
+        MathOp::Log if val == 0.0 => *flags |= FLAG_DIVZERO,
+        MathOp::Log if val < 0.0 => *flags |= FLAG_INVALID,
+        MathOp::Exp if out.is_infinite() && val.is_finite() => {
+            *flags |= FLAG_OVERFLOW | FLAG_INEXACT
+        }
+        MathOp::Exp if out == 0.0 && val.is_finite() => *flags |= FLAG_UNDERFLOW | FLAG_INEXACT,
+        _ => {
+            //Only the trivial points (sin 0, cos 0, exp 0, ln 1) have exact results
+            let exact = match op {
+                MathOp::Sin => val == 0.0,
+                MathOp::Cos | MathOp::Exp => val == 0.0 || val.is_infinite(),
+                _ => val == 1.0 || val.is_infinite(),
+            };
+            if !exact {
+                *flags |= FLAG_INEXACT;
+            }
+        }
+    }
+}
+//minNum/maxNum: a quiet NaN operand is ignored, -0 orders below +0
+fn min_num(a: f64, b: f64, min: bool) -> f64 {
+    if a.is_nan() {
+        return b;
+    }
+    if b.is_nan() {
+        return a;
+    }
+    let less = a < b || (a == b && a.is_sign_negative());
+    if less == min {
+        a
+    } else {
+        b
+    }
+}
+//Integer -> f64 under the given rounding mode
+pub(crate) fn from_int(val: i128, rm: RoundingMode) -> (f64, u64) {
+    let mut flags = 0;
+    let wide = val as f64;
+    let err = val.cmp(&(wide as i128));
+    (round64(wide, err, true, rm, &mut flags), flags)
+}
+//Rounds to an integral value under the given rounding mode
+pub(crate) fn round_int(val: f64, rm: RoundingMode) -> f64 {
+    match rm {
+        RoundingMode::NearestEven => val.round_ties_even(),
+        RoundingMode::TowardZero => val.trunc(),
+        RoundingMode::Down => val.floor(),
+        RoundingMode::Up => val.ceil(),
+    }
+}
+//Float -> integer, saturating with NaN -> 0, flags invalid when out of range
+pub(crate) fn to_int(val: f64, rm: RoundingMode, signed: bool) -> (u64, u64) {
+    let rounded = round_int(val, rm);
+    let (min, max) = if signed {
+        (i64::MIN as f64, -(i64::MIN as f64))
+    } else {
+        (0.0, u64::MAX as f64 + 1.0)
+    };
+    if val.is_nan() || rounded < min || rounded >= max {
+        let saturated = if signed {
+            rounded as i64 as u64
+        } else {
+            rounded as u64
+        };
+        return (saturated, FLAG_INVALID);
+    }
+    let flags = if rounded != val { FLAG_INEXACT } else { 0 };
+    if signed {
+        (rounded as i64 as u64, flags)
+    } else {
+        (rounded as u64, flags)
+    }
+}
+pub(crate) fn is_snan64(val: f64) -> bool {
+    val.is_nan() && val.to_bits() & (1 << 51) == 0
+}
+pub(crate) fn is_snan32(val: f32) -> bool {
+    val.is_nan() && val.to_bits() & (1 << 22) == 0
+}
+//NaN results are canonicalized so payloads don't depend on the host
+pub(crate) fn canonical64(val: f64) -> f64 {
+    if val.is_nan() {
+        f64::NAN
+    } else {
+        val
+    }
+}
+pub(crate) fn canonical32(val: f32) -> f32 {
+    if val.is_nan() {
+        f32::NAN
+    } else {
+        val
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [RoundingMode; 4] = [
+        RoundingMode::NearestEven,
+        RoundingMode::TowardZero,
+        RoundingMode::Down,
+        RoundingMode::Up,
+    ];
+
+    #[test]
+    fn inexact_results_round_by_mode() {
+        let tiny = 2f64.powi(-60);
+        let up = [1.0, 1.0, 1.0, 1f64.next_up()];
+        let down = [-1.0, -1.0, (-1f64).next_down(), -1.0];
+        for (k, rm) in MODES.into_iter().enumerate() {
+            assert_eq!(
+                op64(FloatOp::Add, 1.0, tiny, rm),
+                (up[k], FLAG_INEXACT),
+                "{}",
+                k
+            );
+            assert_eq!(
+                op64(FloatOp::Sub, -1.0, tiny, rm),
+                (down[k], FLAG_INEXACT),
+                "{}",
+                k
+            );
+            assert_eq!(op64(FloatOp::Add, 1.0, 2.0, rm), (3.0, 0), "{}", k);
+        }
+        //1/3 sits between two doubles, up and down differ by one ulp
+        let (low, _) = op64(FloatOp::Div, 1.0, 3.0, RoundingMode::Down);
+        let (high, _) = op64(FloatOp::Div, 1.0, 3.0, RoundingMode::Up);
+        assert_eq!(low.next_up(), high);
+        let (low, _) = op32(FloatOp::Mul, 0.1, 0.1, RoundingMode::TowardZero);
+        let (high, flags) = op32(FloatOp::Mul, 0.1, 0.1, RoundingMode::Up);
+        assert_eq!((low.next_up(), flags), (high, FLAG_INEXACT));
+        assert_eq!(
+            narrow64(1.0 + 2f64.powi(-40), RoundingMode::Up),
+            (1f32.next_up(), FLAG_INEXACT)
+        );
+        let big = (1i128 << 53) + 1;
+        assert_eq!(
+            from_int(big, RoundingMode::Down),
+            (2f64.powi(53), FLAG_INEXACT)
+        );
+        assert_eq!(
+            from_int(big, RoundingMode::Up),
+            (2f64.powi(53) + 2.0, FLAG_INEXACT)
+        );
+    }
+    #[test]
+    fn exceptions_set_their_flags() {
+        let overflow = FLAG_OVERFLOW | FLAG_INEXACT;
+        let max = [f64::INFINITY, f64::MAX, f64::MAX, f64::INFINITY];
+        for (k, rm) in MODES.into_iter().enumerate() {
+            assert_eq!(
+                op64(FloatOp::Mul, f64::MAX, 2.0, rm),
+                (max[k], overflow),
+                "{}",
+                k
+            );
+        }
+        assert_eq!(
+            op32(FloatOp::Add, f32::MAX, f32::MAX, RoundingMode::NearestEven),
+            (f32::INFINITY, overflow)
+        );
+        assert_eq!(
+            op64(FloatOp::Div, 1.0, 0.0, RoundingMode::Up),
+            (f64::INFINITY, FLAG_DIVZERO)
+        );
+        assert_eq!(
+            op64(FloatOp::Div, -1.0, 0.0, RoundingMode::TowardZero),
+            (f64::NEG_INFINITY, FLAG_DIVZERO)
+        );
+        assert_eq!(
+            op32(FloatOp::Div, 1.0, 0.0, RoundingMode::Down),
+            (f32::INFINITY, FLAG_DIVZERO)
+        );
+        let (nan, flags) = op64(FloatOp::Div, 0.0, 0.0, RoundingMode::NearestEven);
+        assert!(nan.is_nan() && flags == FLAG_INVALID);
+        let (nan, flags) = op64(
+            FloatOp::Sub,
+            f64::INFINITY,
+            f64::INFINITY,
+            RoundingMode::NearestEven,
+        );
+        assert!(nan.is_nan() && flags == FLAG_INVALID);
+        //Quiet NaNs propagate silently, signaling ones raise invalid
+        let snan = f64::from_bits(0x7FF0_0000_0000_0001);
+        assert_eq!(
+            op64(FloatOp::Add, f64::NAN, 1.0, RoundingMode::NearestEven).1,
+            0
+        );
+        assert_eq!(
+            op64(FloatOp::Add, snan, 1.0, RoundingMode::NearestEven).1,
+            FLAG_INVALID
+        );
+        //Exact subnormals don't underflow, inexact ones do
+        assert_eq!(
+            op64(
+                FloatOp::Mul,
+                f64::MIN_POSITIVE,
+                0.5,
+                RoundingMode::NearestEven
+            )
+            .1,
+            0
+        );
+        let flags = op64(
+            FloatOp::Div,
+            f64::MIN_POSITIVE,
+            3.0,
+            RoundingMode::NearestEven,
+        )
+        .1;
+        assert_eq!(flags, FLAG_UNDERFLOW | FLAG_INEXACT);
+        assert_eq!(
+            to_int(f64::NAN, RoundingMode::NearestEven, true),
+            (0, FLAG_INVALID)
+        );
+        assert_eq!(
+            to_int(2.5, RoundingMode::NearestEven, true),
+            (2, FLAG_INEXACT)
+        );
+        assert_eq!(to_int(-0.5, RoundingMode::Down, false), (0, FLAG_INVALID));
+    }
+    #[test]
+    fn math_intrinsics() {
+        let rm = RoundingMode::NearestEven;
+        let (low, flags) = math64(MathOp::Sqrt, 0.0, 2.0, RoundingMode::Down);
+        let (high, _) = math64(MathOp::Sqrt, 0.0, 2.0, RoundingMode::Up);
+        assert_eq!((low.next_up(), flags), (high, FLAG_INEXACT));
+        assert_eq!(math64(MathOp::Sqrt, 0.0, 4.0, rm), (2.0, 0));
+        let (nan, flags) = math64(MathOp::Sqrt, 0.0, -1.0, rm);
+        assert!(nan.is_nan() && flags == FLAG_INVALID);
+        assert_eq!(math32(MathOp::Sqrt, 0.0, 9.0, rm), (3.0, 0));
+        assert_eq!(math64(MathOp::Round, 0.0, -2.5, rm).0, -3.0);
+        assert_eq!(math64(MathOp::Floor, 0.0, -2.5, rm).0, -3.0);
+        assert_eq!(math64(MathOp::Ceil, 0.0, -2.5, rm).0, -2.0);
+        assert_eq!(math64(MathOp::Abs, 0.0, -0.0, rm).0.to_bits(), 0);
+        assert_eq!(math64(MathOp::Copysign, 3.0, -0.0, rm).0, -3.0);
+        //min/max ignore a quiet NaN and order -0 below +0
+        assert_eq!(math64(MathOp::Min, f64::NAN, 1.0, rm), (1.0, 0));
+        assert_eq!(math64(MathOp::Max, 2.0, f64::NAN, rm), (2.0, 0));
+        assert!(math64(MathOp::Min, 0.0, -0.0, rm).0.is_sign_negative());
+        assert!(math64(MathOp::Max, -0.0, 0.0, rm).0.is_sign_positive());
+        assert_eq!(math32(MathOp::Max, 1.5, -4.0, rm), (1.5, 0));
+    }
+    #[test]
+    fn transcendental_flags() {
+        let rm = RoundingMode::NearestEven;
+        assert_eq!(math64(MathOp::Exp, 0.0, 0.0, rm), (1.0, 0));
+        assert_eq!(math64(MathOp::Log, 0.0, 1.0, rm), (0.0, 0));
+        assert_eq!(
+            math64(MathOp::Sin, 0.0, 1.0, rm),
+            (1f64.sin(), FLAG_INEXACT)
+        );
+        assert_eq!(
+            math64(MathOp::Log, 0.0, 0.0, rm),
+            (f64::NEG_INFINITY, FLAG_DIVZERO)
+        );
+        assert_eq!(math64(MathOp::Log, 0.0, -1.0, rm).1, FLAG_INVALID);
+        assert_eq!(math64(MathOp::Cos, 0.0, f64::INFINITY, rm).1, FLAG_INVALID);
+        let overflow = FLAG_OVERFLOW | FLAG_INEXACT;
+        assert_eq!(
+            math64(MathOp::Exp, 0.0, 1000.0, rm),
+            (f64::INFINITY, overflow)
+        );
+        assert_eq!(
+            math32(MathOp::Exp, 0.0, 100.0, rm),
+            (f32::INFINITY, overflow)
+        );
+        assert_eq!(
+            math64(MathOp::Exp, 0.0, -1000.0, rm).1,
+            FLAG_UNDERFLOW | FLAG_INEXACT
+        );
+        assert_eq!(math64(MathOp::Pow, 2.0, 10.0, rm), (1024.0, 0));
+        assert_eq!(
+            math64(MathOp::Pow, 0.0, -1.0, rm),
+            (f64::INFINITY, FLAG_DIVZERO)
+        );
+        assert_eq!(math64(MathOp::Pow, -2.0, 0.5, rm).1, FLAG_INVALID);
+        assert_eq!(math64(MathOp::Pow, 10.0, 400.0, rm).1, overflow);
+    }
+    #[test]
+    fn fma_rounds_once() {
+        //0.1*10-1 is 2^-54 exactly when fused, a separate multiply rounds it away
+        let rne = RoundingMode::NearestEven;
+        assert_eq!(fma64(0.1, 10.0, -1.0, rne), (2f64.powi(-54), 0));
+        assert_eq!(0.1f64 * 10.0 - 1.0, 0.0);
+        let (nan, flags) = fma64(f64::INFINITY, 0.0, 1.0, rne);
+        assert!(nan.is_nan() && flags == FLAG_INVALID);
+        assert_eq!(
+            fma64(f64::MAX, 2.0, 0.0, rne),
+            (f64::INFINITY, FLAG_OVERFLOW | FLAG_INEXACT)
+        );
+        assert_eq!(fma32(2.0, 3.0, 1.0, rne), (7.0, 0));
+        //1+2^-24+2^-70 rounds to the f64 midpoint 1+2^-24, a second rounding to f32 would tie to 1
+        let (a, b) = (
+            -(2f32.powi(-12) + 2f32.powi(-35)),
+            2f32.powi(-12) - 2f32.powi(-35),
+        );
+        let c = 1.0 + 2f32.powi(-23);
+        assert_eq!(fma32(a, b, c, rne), (c, FLAG_INEXACT));
+        assert_eq!(fma32(a, b, c, rne).0, a.mul_add(b, c));
+    }
+    #[test]
+    fn fma_rounds_by_mode() {
+        //(1+2^-52)^2 = 1+2^-51+2^-104
+        let x = 1f64.next_up();
+        let square = 1.0 + 2f64.powi(-51);
+        let up = [square, square, square, square.next_up()];
+        let down = [-square, -square, (-square).next_down(), -square];
+        for (k, rm) in MODES.into_iter().enumerate() {
+            assert_eq!(fma64(x, x, 0.0, rm), (up[k], FLAG_INEXACT), "{}", k);
+            assert_eq!(fma64(-x, x, 0.0, rm), (down[k], FLAG_INEXACT), "{}", k);
+            assert_eq!(fma64(0.1, 10.0, -1.0, rm), (2f64.powi(-54), 0), "{}", k);
+            assert_eq!(fma64(x, x, -square, rm), (2f64.powi(-104), 0), "{}", k);
+        }
+        //The product is far below half an ulp of c, only directed modes move c
+        let tiny = 2f64.powi(-600);
+        let up = [1.0, 1.0, 1.0, 1f64.next_up()];
+        let below = [1.0, 1f64.next_down(), 1f64.next_down(), 1.0];
+        for (k, rm) in MODES.into_iter().enumerate() {
+            assert_eq!(fma64(tiny, tiny, 1.0, rm), (up[k], FLAG_INEXACT), "{}", k);
+            assert_eq!(
+                fma64(-tiny, tiny, 1.0, rm),
+                (below[k], FLAG_INEXACT),
+                "{}",
+                k
+            );
+        }
+        //Products near either end of the exponent range are scaled before splitting
+        for scale in [2f64.powi(-500), 2f64.powi(500)] {
+            let x = scale.next_up();
+            let square = scale * scale * (1.0 + 2f64.powi(-51));
+            let up = [square, square, square, square.next_up()];
+            for (k, rm) in MODES.into_iter().enumerate() {
+                assert_eq!(fma64(x, x, 0.0, rm), (up[k], FLAG_INEXACT), "{}", k);
+            }
+        }
+        let x = 1f32.next_up();
+        let square = 1.0 + 2f32.powi(-22);
+        let up = [square, square, square, square.next_up()];
+        for (k, rm) in MODES.into_iter().enumerate() {
+            assert_eq!(fma32(x, x, 0.0, rm), (up[k], FLAG_INEXACT), "{}", k);
+        }
+        //Overflow and underflow go through the same rounding as the basic ops
+        let max = [f64::INFINITY, f64::MAX, f64::MAX, f64::INFINITY];
+        for (k, rm) in MODES.into_iter().enumerate() {
+            assert_eq!(
+                fma64(f64::MAX, 2.0, 0.0, rm),
+                (max[k], FLAG_OVERFLOW | FLAG_INEXACT),
+                "{}",
+                k
+            );
+        }
+        let (min, flags) = fma64(f64::MIN_POSITIVE, 0.75, 0.0, RoundingMode::Up);
+        assert_eq!(min, f64::MIN_POSITIVE * 0.75);
+        assert_eq!(flags, 0);
+        let (min, flags) = fma64(f64::from_bits(1), 0.5, 0.0, RoundingMode::Up);
+        assert_eq!(min, f64::from_bits(1));
+        assert_eq!(flags, FLAG_UNDERFLOW | FLAG_INEXACT);
+    }
+    //Value as mantissa * 2^exponent
+    fn parts(val: f64) -> (i128, i32) {
+        let bits = val.to_bits();
+        let exp = ((bits >> 52) & 0x7ff) as i32;
+        let frac = (bits & ((1 << 52) - 1)) as i128;
+        let (mant, exp) = if exp == 0 {
+            (frac, -1074)
+        } else {
+            (frac | 1 << 52, exp - 1075)
+        };
+        (if val < 0.0 { -mant } else { mant }, exp)
+    }
+    fn exact_cmp(x: (i128, i32), y: (i128, i32)) -> Ordering {
+        if x.1 < y.1 {
+            return exact_cmp(y, x).reverse();
+        }
+        let shift = (x.1 - y.1) as u32;
+        if shift >= 127 {
+            //Any non-zero x is larger in magnitude than y
+            return if x.0 == 0 { 0.cmp(&y.0) } else { x.0.cmp(&0) };
+        }
+        //y = q*2^shift + r with 0 <= r < 2^shift
+        let (q, r) = (y.0 >> shift, y.0 & ((1 << shift) - 1));
+        x.0.cmp(&q).then(if r == 0 {
+            Ordering::Equal
+        } else {
+            Ordering::Less
+        })
+    }
+    fn exact_fma(a: f64, b: f64, c: f64) -> (i128, i32) {
+        let ((ma, ea), (mb, eb), (mc, ec)) = (parts(a), parts(b), parts(c));
+        let (p, ep) = (ma * mb, ea + eb);
+        let exp = ep.min(ec);
+        (p * (1 << (ep - exp)) + mc * (1 << (ec - exp)), exp)
+    }
+    //Rounded the way rm asks: on the right side of the exact value, with nothing between
+    fn check_rounding(val: [f64; 3], exact: (i128, i32), rm: RoundingMode, flags: u64) -> bool {
+        let [down, val, up] = val.map(parts);
+        let err = exact_cmp(val, exact);
+        let toward_zero = if exact.0 > 0 {
+            err != Ordering::Greater
+        } else {
+            err != Ordering::Less
+        };
+        let within = match rm {
+            RoundingMode::NearestEven => true,
+            RoundingMode::TowardZero => toward_zero,
+            RoundingMode::Down => err != Ordering::Greater,
+            RoundingMode::Up => err != Ordering::Less,
+        };
+        let adjacent = match err {
+            Ordering::Equal => true,
+            Ordering::Greater => exact_cmp(down, exact) == Ordering::Less,
+            Ordering::Less => exact_cmp(up, exact) == Ordering::Greater,
+        };
+        ((flags & FLAG_INEXACT != 0) == (err != Ordering::Equal)) && within && adjacent
+    }
+    #[test]
+    fn fma_matches_exact_arithmetic() {
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        //Exponents of c within 20 of the product's keep the exact sum inside an i128
+        let mut float = |exp: i64| {
+            let frac = (next() & ((1 << 52) - 1)) | ((next() & 1) << 63);
+            f64::from_bits(frac | ((exp + 1023) as u64) << 52)
+        };
+        for i in 0..20000 {
+            let (ea, eb) = (i % 61 - 30, (i * 7) % 61 - 30);
+            let (a, b) = (float(ea), float(eb));
+            let c = float(ea + eb + (i * 13) % 41 - 20);
+            let exact = exact_fma(a, b, c);
+            let (a32, b32, c32) = (a as f32, b as f32, c as f32);
+            let exact32 = exact_fma(a32 as f64, b32 as f64, c32 as f64);
+            for (k, rm) in MODES.into_iter().enumerate() {
+                let (val, flags) = fma64(a, b, c, rm);
+                let around = [val.next_down(), val, val.next_up()];
+                assert!(
+                    check_rounding(around, exact, rm, flags),
+                    "{} {} {} {}",
+                    k,
+                    a,
+                    b,
+                    c
+                );
+                let (val, flags) = fma32(a32, b32, c32, rm);
+                let around = [val.next_down(), val, val.next_up()].map(f64::from);
+                assert!(
+                    check_rounding(around, exact32, rm, flags),
+                    "{} {} {} {}",
+                    k,
+                    a32,
+                    b32,
+                    c32
+                );
+            }
+            let rne = RoundingMode::NearestEven;
+            assert_eq!(fma64(a, b, c, rne).0, a.mul_add(b, c), "{} {} {}", a, b, c);
+            assert_eq!(fma32(a32, b32, c32, rne).0, a32.mul_add(b32, c32));
+        }
+    }
+}
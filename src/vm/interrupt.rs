@@ -0,0 +1,101 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/*
+Interrupt controller of a core with 64 lines, line 0 is the timer and the others are free for
+the host. A line interrupts when it is pending, set in the mask and interrupts are enabled.
+The run loop delivers the lowest such line with a handler between instructions through the trap
+table (cause trap::INTERRUPTS + line), clearing its pending bit. Nothing is delivered inside
+a handler. A line without a handler stays pending and keeps its mask bit, it is only passed
+over for that delivery, so the lines above it are still delivered and it is taken once a handler
+is installed. Parked cores take interrupts once woken.
+The timer counts ticks of its source: executed instructions, or virtual time the host advances.
+*/
+
+pub(crate) const TIMER: u32 = 0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TimerSource {
+    Instructions,
+    Virtual,
+}
+
+impl From<u64> for TimerSource {
+    fn from(val: u64) -> Self {
+        match val {
+            1 => TimerSource::Virtual,
+            _ => TimerSource::Instructions,
+        }
+    }
+}
+
+//Shared with the host handles
+#[derive(Default)]
+struct Lines {
+    pending: AtomicU64,
+    time: AtomicU64, //Virtual time
+}
+
+#[derive(Default)]
+pub(crate) struct Interrupts {
+    pub enabled: bool,
+    pub mask: u64,
+    pub instret: u64,                     //Executed instructions
+    deadline: Option<(TimerSource, u64)>, //Clock value the timer fires at
+    lines: Arc<Lines>,
+}
+
+//Host side of a core's interrupt controller, usable from other threads while it runs
+#[derive(Clone)]
+pub(crate) struct Irq(Arc<Lines>);
+
+impl Irq {
+    pub(crate) fn raise(&self, line: u32) {
+        self.0.pending.fetch_or(1 << (line % 64), Ordering::Release);
+    }
+    pub(crate) fn advance(&self, ticks: u64) {
+        self.0.time.fetch_add(ticks, Ordering::Release);
+    }
+}
+
+impl Interrupts {
+    pub(crate) fn handle(&self) -> Irq {
+        Irq(Arc::clone(&self.lines))
+    }
+    pub(crate) fn clock(&self, source: TimerSource) -> u64 {
+        match source {
+            TimerSource::Instructions => self.instret,
+            TimerSource::Virtual => self.lines.time.load(Ordering::Acquire),
+        }
+    }
+    pub(crate) fn pending(&self) -> u64 {
+        self.lines.pending.load(Ordering::Acquire)
+    }
+    pub(crate) fn clear(&self, lines: u64) {
+        self.lines.pending.fetch_and(!lines, Ordering::AcqRel);
+    }
+    //Fires after ticks of the source, 0 stops the timer
+    pub(crate) fn arm(&mut self, source: TimerSource, ticks: u64) {
+        self.deadline = match ticks {
+            0 => None,
+            _ => Some((source, self.clock(source).saturating_add(ticks))),
+        };
+    }
+    //After every executed instruction
+    pub(crate) fn tick(&mut self) {
+        self.instret = self.instret.wrapping_add(1);
+        if let Some((source, deadline)) = self.deadline {
+            if self.clock(source) >= deadline {
+                self.deadline = None;
+                self.lines.pending.fetch_or(1 << TIMER, Ordering::AcqRel);
+            }
+        }
+    }
+    //Lowest line that would interrupt now, apart from the skipped ones
+    pub(crate) fn next(&self, skip: u64) -> Option<u32> {
+        let lines = self.pending() & self.mask & !skip;
+        (self.enabled && lines != 0).then(|| lines.trailing_zeros())
+    }
+}
@@ -0,0 +1,205 @@
+/*
+Loads raw binary and Intel HEX code into an S64 image.
+base and entry are instruction indices (pc units), Intel HEX addresses are bytes.
+*/
+
+#[derive(Clone)]
+pub(crate) struct Image {
+    pub base: u64,  //Address of code[0]
+    pub entry: u64, //Initial pc
+    pub code: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum LoadError {
+    Misaligned(u64),          //Byte length is not a multiple of 4
+    EntryOutOfRange(u64),     //Entry point is outside the loaded code
+    BadRecord(usize),         //Malformed Intel HEX record (line)
+    BadChecksum(usize),       //Intel HEX checksum mismatch (line)
+    AddressBelowBase(usize),  //Intel HEX data placed before the load address (line)
+    UnsupportedRecord(usize), //Intel HEX record type we don't handle (line)
+    MissingEof,               //Intel HEX input without an EOF record
+    TooLarge(usize),          //Intel HEX data more than MAX_IHEX_BYTES past the load address (line)
+}
+
+//Bound on the image an Intel HEX file may describe, a single record can place data up to 4G away
+pub(crate) const MAX_IHEX_BYTES: u64 = 1 << 26;
+
+impl Image {
+    pub(crate) fn new(base: u64, entry: u64, code: Vec<u32>) -> Result<Self, LoadError> {
+        if entry < base || entry - base > code.len() as u64 {
+            return Err(LoadError::EntryOutOfRange(entry));
+        }
+        Ok(Self { base, entry, code })
+    }
+}
+
+pub(crate) fn load_raw(bytes: &[u8], base: u64, entry: u64) -> Result<Image, LoadError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(LoadError::Misaligned(bytes.len() as u64));
+    }
+    let code = bytes
+        .chunks_exact(4)
+        .map(|v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+        .collect();
+    Image::new(base, entry, code)
+}
+pub(crate) fn load_ihex(text: &str, base: u64, entry: u64) -> Result<Image, LoadError> {
+    //A base past the 32-bit Intel HEX address space leaves every record below it
+    let start = base.saturating_mul(4);
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut upper = 0u64;
+    let mut eof = false;
+    for (line, record) in text.lines().enumerate() {
+        let record = record.trim();
+        if record.is_empty() {
+            continue;
+        }
+        if eof {
+            return Err(LoadError::BadRecord(line));
+        }
+        let raw = parse_record(record).ok_or(LoadError::BadRecord(line))?;
+        if raw.len() < 5 || raw.len() != raw[0] as usize + 5 {
+            return Err(LoadError::BadRecord(line));
+        }
+        if raw.iter().fold(0u8, |acc, v| acc.wrapping_add(*v)) != 0 {
+            return Err(LoadError::BadChecksum(line));
+        }
+        let address = u16::from_be_bytes([raw[1], raw[2]]) as u64;
+        let data = &raw[4..raw.len() - 1];
+        match raw[3] {
+            0x00 => {
+                let address = upper + address;
+                if address < start {
+                    return Err(LoadError::AddressBelowBase(line));
+                }
+                if address - start + data.len() as u64 > MAX_IHEX_BYTES {
+                    return Err(LoadError::TooLarge(line));
+                }
+                let offset = (address - start) as usize;
+                if bytes.len() < offset + data.len() {
+                    bytes.resize(offset + data.len(), 0);
+                }
+                bytes[offset..offset + data.len()].copy_from_slice(data);
+            }
+            0x01 => eof = true,
+            0x02 if data.len() == 2 => upper = (u16::from_be_bytes([data[0], data[1]]) as u64) << 4,
+            0x04 if data.len() == 2 => {
+                upper = (u16::from_be_bytes([data[0], data[1]]) as u64) << 16
+            }
+            //Start address records are informational, the entry point is given by the caller
+            0x03 | 0x05 => {}
+            _ => return Err(LoadError::UnsupportedRecord(line)),
+        }
+    }
+    if !eof {
+        return Err(LoadError::MissingEof);
+    }
+    //Pad a trailing partial instruction instead of rejecting the file
+    bytes.resize(bytes.len().div_ceil(4) * 4, 0);
+    load_raw(&bytes, base, entry)
+}
+fn parse_record(record: &str) -> Option<Vec<u8>> {
+    let hex = record.strip_prefix(':')?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|k| u8::from_str_radix(hex.get(k..k + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compiler::assemble,
+        output::{emit_hexdump, emit_ihex, emit_raw, OutputError},
+    };
+
+    fn program() -> Vec<u32> {
+        (0..40)
+            .map(|k| 0x0102_0304u32.wrapping_mul(k + 1))
+            .collect()
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let code = program();
+        let image = load_raw(&emit_raw(&code), 8, 10).unwrap();
+        assert_eq!((image.base, image.entry, image.code), (8, 10, code));
+        assert_eq!(
+            load_raw(&[0; 6], 0, 0).err(),
+            Some(LoadError::Misaligned(6))
+        );
+        assert_eq!(
+            load_raw(&[0; 8], 0, 3).err(),
+            Some(LoadError::EntryOutOfRange(3))
+        );
+    }
+    #[test]
+    fn ihex_round_trip_across_segments() {
+        let code = program();
+        //Starts 16 bytes below a 64K boundary so records continue in the next segment
+        let base = 0x3FFC;
+        let text = emit_ihex(&code, base, base + 3).unwrap();
+        assert_eq!(text.matches(":02000004").count(), 2);
+        let image = load_ihex(&text, base, base + 3).unwrap();
+        assert_eq!(
+            (image.base, image.entry, image.code),
+            (base, base + 3, code)
+        );
+    }
+    #[test]
+    fn ihex_rejects_far_records() {
+        //One record at the top of the 4G address space must not allocate the gap below it
+        let text = emit_ihex(&[1, 2], 0x3FFF_C000, 0x3FFF_C000).unwrap();
+        assert_eq!(load_ihex(&text, 0, 0).err(), Some(LoadError::TooLarge(1)));
+        assert_eq!(
+            load_ihex(&text, u64::MAX, u64::MAX).err(),
+            Some(LoadError::AddressBelowBase(1))
+        );
+        let text = text.replace(":00000001FF\n", "");
+        assert_eq!(
+            load_ihex(&text, 0x3FFF_C000, 0x3FFF_C000).err(),
+            Some(LoadError::MissingEof)
+        );
+    }
+    #[test]
+    fn output_rejects_unencodable_addresses() {
+        assert_eq!(
+            emit_ihex(&[1], 1 << 30, 1 << 30).err(),
+            Some(OutputError::AddressRange(1 << 30))
+        );
+        assert_eq!(
+            emit_ihex(&[1], 0, 1 << 30).err(),
+            Some(OutputError::AddressRange(1 << 30))
+        );
+        assert_eq!(
+            emit_hexdump(&[1], u64::MAX).err(),
+            Some(OutputError::AddressRange(u64::MAX))
+        );
+        assert_eq!(
+            emit_ihex(&[1], 1 << 31, 0).err(),
+            Some(OutputError::AddressRange(1 << 31))
+        );
+        //Code running past 4G reports its end
+        assert_eq!(
+            emit_ihex(&[1, 2, 3], (1 << 30) - 1, 0).err(),
+            Some(OutputError::AddressRange((1 << 30) + 2))
+        );
+        assert!(emit_ihex(&[1], (1 << 30) - 1, 0).is_ok());
+    }
+    #[test]
+    fn hexdump_lists_every_word() {
+        let code = assemble(vec!["dadd r0 zr 5", "dadd r1 zr 100000"]).unwrap();
+        let dump = emit_hexdump(&code, 4).unwrap();
+        let expected = "\
+00000010  1a 01 05 00  dadd r0 zr 5
+00000014  ff 1a 01 00  dadd r1 zr 100000
+00000018  02 00 00 00  \n\
+0000001c  a0 86 01 00  \n";
+        assert_eq!(dump, expected);
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Condvar, Mutex,
+};
+
+use super::{
+    loader::Image,
+    memory::Memory,
+    overflow::FLAG_CARRY,
+    s64::{CoreEvent, Exit, VmError, S64},
+};
+
+/*
+Several S64 cores over one shared data memory. Core 0 starts at the image entry, the others
+are idle until a spawn starts them and go idle again when their pc leaves the code.
+Park sleeps until a wake, a wake sent to a core that isn't parked makes its next park return.
+The machine stops once no core is running (parked cores stay parked) or at the first fault.
+Threaded runs put every core on its own host thread, interleaved runs step the running cores
+round-robin on the calling thread, a quantum of instructions at a time, for reproducible runs.
+*/
+
+//Instructions a threaded core runs between checks for a fault on another core
+const QUANTUM: u64 = 1 << 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Status {
+    Idle,
+    Running,
+    Parked,
+}
+
+struct Control {
+    status: Vec<Status>,
+    permits: Vec<bool>,              //Wakes waiting for the next park
+    starts: Vec<Option<(u64, u64)>>, //pc and r0 of a spawned core that hasn't started yet
+    fault: Option<(usize, VmError)>,
+}
+
+impl Control {
+    fn new(cores: usize, entry: u64) -> Self {
+        let mut control = Self {
+            status: vec![Status::Idle; cores],
+            permits: vec![false; cores],
+            starts: vec![None; cores],
+            fault: None,
+        };
+        control.status[0] = Status::Running;
+        control.starts[0] = Some((entry, 0));
+        control
+    }
+    fn finished(&self) -> bool {
+        self.fault.is_some() || !self.status.contains(&Status::Running)
+    }
+    //Whether the core was idle and is now started
+    fn spawn(&mut self, core: u64, pc: u64, arg: u64) -> bool {
+        let core = match usize::try_from(core) {
+            Ok(core) if self.status.get(core) == Some(&Status::Idle) => core,
+            _ => return false,
+        };
+        self.status[core] = Status::Running;
+        self.starts[core] = Some((pc, arg));
+        true
+    }
+    //Sleeps unless a wake came first
+    fn park(&mut self, core: usize) {
+        if !std::mem::take(&mut self.permits[core]) {
+            self.status[core] = Status::Parked;
+        }
+    }
+    fn wake(&mut self, core: u64) {
+        let core = match usize::try_from(core) {
+            Ok(core) if core < self.status.len() => core,
+            _ => return,
+        };
+        match self.status[core] {
+            Status::Parked => self.status[core] = Status::Running,
+            _ => self.permits[core] = true,
+        }
+    }
+}
+
+//The data memory is shared by the cores, any core's mem reaches it
+pub(crate) struct Machine {
+    pub cores: Vec<S64>,
+    entry: u64,
+}
+
+impl Machine {
+    pub(crate) fn new(cores: usize, memory: usize) -> Self {
+        let mem = Memory::new(memory);
+        let cores = (0..cores.max(1))
+            .map(|id| S64::core(mem.share(), id as u64, cores.max(1) as u64))
+            .collect();
+        Self { cores, entry: 0 }
+    }
+    pub(crate) fn load(&mut self, image: Image) {
+        self.entry = image.entry;
+        for core in &mut self.cores {
+            core.load(image.clone());
+        }
+    }
+    //Faults carry the id of the faulting core
+    pub(crate) fn run_threaded(&mut self) -> Result<(), (usize, VmError)> {
+        let control = Mutex::new(Control::new(self.cores.len(), self.entry));
+        let (changed, stop) = (Condvar::new(), AtomicBool::new(false));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.cores.len())
+            .build()
+            .expect("host threads for the cores");
+        pool.scope(|s| {
+            for (k, core) in self.cores.iter_mut().enumerate() {
+                let (control, changed, stop) = (&control, &changed, &stop);
+                s.spawn(move |_| run_core(k, core, control, changed, stop));
+            }
+        });
+        match control.into_inner().unwrap().fault {
+            Some(fault) => Err(fault),
+            None => Ok(()),
+        }
+    }
+    pub(crate) fn run_interleaved(&mut self, quantum: u64) -> Result<(), (usize, VmError)> {
+        let mut control = Control::new(self.cores.len(), self.entry);
+        while !control.finished() {
+            for k in 0..self.cores.len() {
+                if control.status[k] != Status::Running {
+                    continue;
+                }
+                let core = &mut self.cores[k];
+                if let Some((pc, arg)) = control.starts[k].take() {
+                    core.start(pc, arg);
+                }
+                match core.run_for(quantum.max(1)) {
+                    Err(err) => return Err((k, err)),
+                    Ok(Exit::Done) => control.status[k] = Status::Idle,
+                    Ok(Exit::Event(event)) => handle(&mut control, k, core, event),
+                    Ok(Exit::Budget) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle(control: &mut Control, k: usize, core: &mut S64, event: CoreEvent) {
+    match event {
+        CoreEvent::Spawn { core: id, pc, arg } => {
+            core.flags = if control.spawn(id, pc, arg) {
+                0
+            } else {
+                FLAG_CARRY
+            };
+        }
+        CoreEvent::Park => control.park(k),
+        CoreEvent::Wake(id) => control.wake(id),
+    }
+}
+
+fn run_core(
+    k: usize,
+    core: &mut S64,
+    control: &Mutex<Control>,
+    changed: &Condvar,
+    stop: &AtomicBool,
+) {
+    let mut guard = control.lock().unwrap();
+    loop {
+        //Idle or parked until started or woken
+        while guard.status[k] != Status::Running {
+            if guard.finished() {
+                return;
+            }
+            guard = changed.wait(guard).unwrap();
+        }
+        if let Some((pc, arg)) = guard.starts[k].take() {
+            core.start(pc, arg);
+        }
+        drop(guard);
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let exit = core.run_for(QUANTUM);
+        guard = control.lock().unwrap();
+        match exit {
+            Err(err) => {
+                guard.fault.get_or_insert((k, err));
+                stop.store(true, Ordering::Relaxed);
+            }
+            Ok(Exit::Done) => guard.status[k] = Status::Idle,
+            Ok(Exit::Event(event)) => handle(&mut guard, k, core, event),
+            Ok(Exit::Budget) => continue,
+        }
+        changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::assemble;
+
+    //Core 0 starts core 1 on worker with r0=5 and parks until it stores 10 and wakes core 0.
+    //The wake is consumed by the first park, so the second one sleeps for good
+    const PROGRAM: [&str; 13] = [
+        "adr r1 zr worker",
+        "dadd r2 zr 5",
+        "dadd r0 zr 1",
+        "spawn r0 r1 r2",
+        "park",
+        "ald r0 zr 0",
+        "park",
+        "dadd r2 zr 99",
+        "nop zr zr end",
+        "worker:",
+        "add r0 r0",
+        "ast r0 zr 0",
+        "wake zr",
+    ];
+
+    fn build(cores: usize, lines: &[&str]) -> Machine {
+        let mut machine = Machine::new(cores, 64);
+        let code = assemble(lines.iter().copied().chain(["end:"]).collect()).unwrap();
+        machine.load(Image::new(0, 0, code).unwrap());
+        machine
+    }
+
+    #[test]
+    fn interleaved_spawn_park_and_wake() {
+        //Quantum 1 parks core 0 before the wake, a long quantum leaves the wake as a permit
+        for quantum in [1, 2, 3, 1000] {
+            let mut machine = build(2, &PROGRAM);
+            assert_eq!(machine.run_interleaved(quantum), Ok(()), "{}", quantum);
+            assert_eq!(
+                machine.cores[1].mem.read(0, 8),
+                Ok(10u64.to_le_bytes().to_vec())
+            );
+            let core = &machine.cores[0];
+            assert_eq!((core.r0, core.r2, core.flags), (10, 5, 0), "{}", quantum);
+        }
+    }
+    #[test]
+    fn threaded_spawn_park_and_wake() {
+        for _ in 0..20 {
+            let mut machine = build(2, &PROGRAM);
+            assert_eq!(machine.run_threaded(), Ok(()));
+            assert_eq!((machine.cores[0].r0, machine.cores[0].r2), (10, 5));
+        }
+    }
+    #[test]
+    fn spawn_of_a_busy_core_sets_carry() {
+        let lines = [
+            "adr r1 zr end",
+            "dadd r0 zr 1",
+            "spawn r0 r1 r2",
+            "spawn zr r1 r2",
+            "dadd r0 zr 7",
+            "spawn r0 r1 r2",
+        ];
+        let mut machine = build(2, &lines);
+        assert_eq!(machine.run_interleaved(1000), Ok(()));
+        assert_eq!(machine.cores[0].flags, FLAG_CARRY);
+        let mut machine = build(2, &lines[..3]);
+        assert_eq!(machine.run_interleaved(1000), Ok(()));
+        assert_eq!(machine.cores[0].flags, 0);
+    }
+    #[test]
+    fn faults_carry_the_core_id() {
+        let lines = [
+            "adr r1 zr worker",
+            "dadd r0 zr 1",
+            "spawn r0 r1 r2",
+            "park",
+            "worker:",
+            "dadd r1 zr 3",
+            "ald r0 r1 0",
+        ];
+        let mut interleaved = build(2, &lines);
+        assert_eq!(
+            interleaved.run_interleaved(1),
+            Err((1, VmError::Misaligned(3)))
+        );
+        let mut threaded = build(2, &lines);
+        assert_eq!(threaded.run_threaded(), Err((1, VmError::Misaligned(3))));
+    }
+    #[test]
+    fn contended_counters_lose_no_updates() {
+        //Every core adds 1 to the word at 0 with aadd and to the word at 8 with an acas retry
+        //loop, 500 times each
+        let lines = [
+            "adr r1 zr worker",
+            "dadd r0 zr 1",
+            "spawn r0 r1 r2",
+            "dadd r0 zr 2",
+            "spawn r0 r1 r2",
+            "dadd r0 zr 3",
+            "spawn r0 r1 r2",
+            "worker:",
+            "dadd p0 zr 500",
+            "dadd r1 zr 8",
+            "adr p1 zr end",
+            "loop:",
+            "dadd r0 zr 1",
+            "aadd r0 zr 0",
+            "retry:",
+            "ald r0 r1 1",
+            "dadd r2 r0 1",
+            "acas r0 r1 r2",
+            "rflags r2",
+            "dxor r2 r2 1",
+            "btst r2 zr retry",
+            "dec p0 zr",
+            "adr p2 zr loop",
+            "cmovz p2 p1 p0",
+            "jmp p2",
+        ];
+        let expected = Ok([2000u64.to_le_bytes(), 2000u64.to_le_bytes()].concat());
+        for _ in 0..5 {
+            let mut machine = build(4, &lines);
+            assert_eq!(machine.run_threaded(), Ok(()));
+            assert_eq!(machine.cores[0].mem.read(0, 16), expected);
+        }
+        for quantum in [1, 7] {
+            let mut machine = build(4, &lines);
+            assert_eq!(machine.run_interleaved(quantum), Ok(()));
+            assert_eq!(machine.cores[3].mem.read(0, 16), expected);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/*
+Flat little-endian data memory, separate from the code (Harvard model).
+Accesses outside of it fail with the first address that isn't backed, empty ones never fail.
+The bytes live in host AtomicU64 words so guest atomics map onto host atomics, plain
+accesses are relaxed per word: racing plain accesses can tear across words but stay defined.
+*/
+
+#[derive(Default)]
+pub(crate) struct Memory {
+    words: Arc<[AtomicU64]>,
+    size: usize,
+}
+
+impl Memory {
+    pub(crate) fn new(size: usize) -> Self {
+        Self {
+            words: (0..size.div_ceil(8)).map(|_| AtomicU64::new(0)).collect(),
+            size,
+        }
+    }
+    //Another handle on the same bytes, for the cores of a machine
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            words: Arc::clone(&self.words),
+            size: self.size,
+        }
+    }
+    pub(crate) fn check(&self, address: u64, len: usize) -> Result<(), u64> {
+        self.range(address, len).map(|_| ())
+    }
+    pub(crate) fn read(&self, address: u64, len: usize) -> Result<Vec<u8>, u64> {
+        let range = self.range(address, len)?;
+        let mut out = Vec::with_capacity(len);
+        let mut k = range.start;
+        while k < range.end {
+            let (offset, n) = (k % 8, (8 - k % 8).min(range.end - k));
+            let word = self.words[k / 8].load(Ordering::Relaxed).to_le_bytes();
+            out.extend_from_slice(&word[offset..offset + n]);
+            k += n;
+        }
+        Ok(out)
+    }
+    pub(crate) fn write(&self, address: u64, data: &[u8]) -> Result<(), u64> {
+        let range = self.range(address, data.len())?;
+        let (mut k, mut data) = (range.start, data);
+        while k < range.end {
+            let (offset, n) = (k % 8, (8 - k % 8).min(range.end - k));
+            let word = &self.words[k / 8];
+            if n == 8 {
+                word.store(
+                    u64::from_le_bytes(data[..8].try_into().unwrap()),
+                    Ordering::Relaxed,
+                );
+            } else {
+                let _ = word.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+                    let mut bytes = v.to_le_bytes();
+                    bytes[offset..offset + n].copy_from_slice(&data[..n]);
+                    Some(u64::from_le_bytes(bytes))
+                });
+            }
+            (k, data) = (k + n, &data[n..]);
+        }
+        Ok(())
+    }
+    //Overlapping ranges copy as if through a temporary
+    pub(crate) fn copy(&self, dst: u64, src: u64, len: usize) -> Result<(), u64> {
+        self.check(dst, len)?;
+        self.write(dst, &self.read(src, len)?)
+    }
+    pub(crate) fn fill(&self, address: u64, len: usize, byte: u8) -> Result<(), u64> {
+        self.check(address, len)?;
+        self.write(address, &vec![byte; len])
+    }
+    //The host atomic holding the aligned u64 at address
+    pub(crate) fn word(&self, address: u64) -> Result<&AtomicU64, u64> {
+        let range = self.range(address, 8)?;
+        Ok(&self.words[range.start / 8])
+    }
+    fn range(&self, address: u64, len: usize) -> Result<std::ops::Range<usize>, u64> {
+        let size = self.size as u64;
+        if len == 0 {
+            return Ok(0..0);
+        }
+        if address >= size {
+            return Err(address);
+        }
+        match address.checked_add(len as u64) {
+            Some(end) if end <= size => Ok(address as usize..end as usize),
+            _ => Err(size),
+        }
+    }
+}
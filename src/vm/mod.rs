@@ -1,2 +1,13 @@
+mod atomic;
+mod float;
+mod interrupt;
+pub(crate) mod loader;
+pub(crate) mod machine;
+mod memory;
+mod overflow;
 mod s64;
-mod sfile;
+pub(crate) mod sfile;
+mod simd;
+mod trap;
+mod vector;
+mod wide;
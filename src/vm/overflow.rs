@@ -0,0 +1,117 @@
+/*
+Integer overflow handling for the u64 arithmetic ops.
+Carry is unsigned overflow (borrow for sub), overflow is signed overflow.
+Checked ops trap and saturating ops clamp on carry, except neg which is signed.
+*/
+
+pub(crate) const FLAG_CARRY: u64 = 1 << 0;
+pub(crate) const FLAG_OVERFLOW: u64 = 1 << 1;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub(crate) enum OverflowPolicy {
+    #[default]
+    Wrapping,
+    Checked,
+    Saturating,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IntOp {
+    Add,
+    Sub,
+    Mul,
+    Neg, //0 - a, b is ignored
+}
+
+//Result and flags, None when a checked op overflows
+pub(crate) fn apply(policy: OverflowPolicy, op: IntOp, a: u64, b: u64) -> Option<(u64, u64)> {
+    let (val, carry, overflow) = match op {
+        IntOp::Add => {
+            let (val, carry) = a.overflowing_add(b);
+            (val, carry, (a as i64).overflowing_add(b as i64).1)
+        }
+        IntOp::Sub => {
+            let (val, carry) = a.overflowing_sub(b);
+            (val, carry, (a as i64).overflowing_sub(b as i64).1)
+        }
+        IntOp::Mul => {
+            let (val, carry) = a.overflowing_mul(b);
+            (val, carry, (a as i64).overflowing_mul(b as i64).1)
+        }
+        IntOp::Neg => (a.wrapping_neg(), a != 0, a == i64::MIN as u64),
+    };
+    let flags = if carry { FLAG_CARRY } else { 0 } | if overflow { FLAG_OVERFLOW } else { 0 };
+    let overflowed = if op == IntOp::Neg { overflow } else { carry };
+    if !overflowed {
+        return Some((val, flags));
+    }
+    match policy {
+        OverflowPolicy::Wrapping => Some((val, flags)),
+        OverflowPolicy::Checked => None,
+        OverflowPolicy::Saturating => {
+            let val = match op {
+                IntOp::Add | IntOp::Mul => u64::MAX,
+                IntOp::Sub => 0,
+                IntOp::Neg => i64::MAX as u64,
+            };
+            Some((val, flags))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: u64 = i64::MIN as u64;
+    const MAX: u64 = i64::MAX as u64;
+
+    #[test]
+    fn policies_differ_only_on_overflow() {
+        use OverflowPolicy::*;
+        for policy in [Wrapping, Checked, Saturating] {
+            assert_eq!(apply(policy, IntOp::Add, 2, 3), Some((5, 0)));
+            assert_eq!(apply(policy, IntOp::Sub, 3, 2), Some((1, 0)));
+            assert_eq!(apply(policy, IntOp::Mul, 6, 7), Some((42, 0)));
+            assert_eq!(apply(policy, IntOp::Neg, 0, 9), Some((0, 0)));
+            //Signed overflow of add/sub and carry of neg are only flagged
+            assert_eq!(
+                apply(policy, IntOp::Add, MAX, 1),
+                Some((MIN, FLAG_OVERFLOW))
+            );
+            assert_eq!(
+                apply(policy, IntOp::Sub, MIN, 1),
+                Some((MAX, FLAG_OVERFLOW))
+            );
+            assert_eq!(
+                apply(policy, IntOp::Neg, 1, 0),
+                Some((u64::MAX, FLAG_CARRY))
+            );
+        }
+        let both = FLAG_CARRY | FLAG_OVERFLOW;
+        let cases = [
+            (IntOp::Add, u64::MAX, 1, 0, FLAG_CARRY, u64::MAX),
+            (IntOp::Add, MIN, MIN, 0, both, u64::MAX),
+            (IntOp::Sub, 1, 2, u64::MAX, FLAG_CARRY, 0),
+            (IntOp::Mul, 1 << 32, 1 << 32, 0, both, u64::MAX),
+            (IntOp::Neg, MIN, 0, MIN, both, MAX),
+        ];
+        for (op, a, b, wrapped, flags, saturated) in cases {
+            assert_eq!(
+                apply(Wrapping, op, a, b),
+                Some((wrapped, flags)),
+                "{} {}",
+                a,
+                b
+            );
+            assert_eq!(apply(Checked, op, a, b), None, "{} {}", a, b);
+            assert_eq!(
+                apply(Saturating, op, a, b),
+                Some((saturated, flags)),
+                "{} {}",
+                a,
+                b
+            );
+        }
+    }
+}
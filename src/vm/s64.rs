@@ -1,7 +1,22 @@
-use crate::{instruction::Instruction, opcode::Opcode, register::Register};
+use super::{
+    atomic::{self, AtomicOp},
+    float::{self, FloatOp, MathOp, RoundingMode, FLAGS, RM_SHIFT},
+    interrupt::{Interrupts, TimerSource},
+    loader::Image,
+    memory::Memory,
+    overflow::{self, IntOp, OverflowPolicy, FLAG_CARRY, FLAG_OVERFLOW},
+    trap,
+    vector::{self, BitOp, Bytes, CompareOp, ReduceOp, VectorOp},
+    wide::{self, Limbs},
+};
+use crate::{
+    instruction::Instruction,
+    lane::{self, LaneType, LANE_HIGH, LANE_IMM_SHIFT},
+    opcode::Opcode,
+    register::{self, Register},
+};
 #[derive(Default)]
 pub(crate) struct S64 {
-    zr: u64, //Zero Register
     pub r0: u64,
     pub r1: u64,
     pub r2: u64,
@@ -11,20 +26,179 @@ pub(crate) struct S64 {
     pub p0: u64,
     pub p1: u64,
     pub p2: u64,
-    pub s0: [u64; 2], //SIMD128
-    pub s1: [u64; 2], //SIMD128
-    pub s2: [u64; 2], //SIMD128
-    pub x0: [u64; 4], //SIMD256
-    pub x1: [u64; 4], //SIMD256
-    pub x2: [u64; 4], //SIMD256
+    pub s0: [u64; 2],             //SIMD128
+    pub s1: [u64; 2],             //SIMD128
+    pub s2: [u64; 2],             //SIMD128
+    pub x0: [u64; 4],             //SIMD256
+    pub x1: [u64; 4],             //SIMD256
+    pub x2: [u64; 4],             //SIMD256
+    banked: Vec<Limbs>,           //Registers past the compact 16, by index - 16
+    pub fcsr: u64,                //FP control/status (flags, rounding mode)
+    pub flags: u64, //Integer status (carry, overflow) of the last add/sub/mul/inc/dec/neg
+    pub overflow: OverflowPolicy, //Integer overflow policy of the plain arithmetic ops
+    pub mem: Memory, //Data memory
     pc: u64,
+    base: u64,                //Address of code[0]
+    code: Vec<u32>,           //Loaded program
+    fault: Option<VmError>,   //Raised by the current instruction, stops run
+    resume: bool,             //Set by a block op with bytes left, the instruction runs again
+    pub id: u64,              //Core id within a machine
+    pub cores: u64,           //Cores of the machine, 0 for a lone S64
+    event: Option<CoreEvent>, //Raised by a core op for the machine, stops run_for
+    pub tvec: u64,            //Trap table address, 0 leaves every fault to the host
+    in_trap: bool,            //Running a trap handler, until rtt
+    pub irq: Interrupts,      //Interrupt controller and timer
+}
+
+//Core ops the machine carries out, values are read when the op executes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CoreEvent {
+    Spawn { core: u64, pc: u64, arg: u64 },
+    Park,
+    Wake(u64),
+}
+
+pub(crate) enum Exit {
+    Done,             //pc left the code
+    Event(CoreEvent), //After the instruction raising it
+    Budget,           //Ran the given number of instructions
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum VmError {
+    LaneFault { lane: usize, address: u64 }, //Vector lane outside data memory
+    Misaligned(u64),                         //Aligned vector access at an unaligned address
+    IntegerOverflow,                         //Checked arithmetic overflowed
+    LaneIndex { index: usize, lanes: usize }, //Lane insert/extract past the last lane
+    BadAddress(u64),                         //Scalar access outside data memory
+    BankConflict, //More banked registers than free compact registers of their kind
+    IllegalOpcode(u8),
+    DivideByZero,              //Integer division or remainder by zero
+    DoubleFault(Box<VmError>), //Fault inside a trap handler
+}
+
+//Bytes a block op handles per execution, it runs again for the rest so long blocks can be
+//interrupted between chunks and resumed from the registers
+const BLOCK_CHUNK: u64 = 4096;
+
+//Banked register, the compact register standing in for it and that register's own value
+type Swapped = Vec<(u8, u8, Limbs)>;
+
+//How the lanes of a vector load/store are addressed
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Addressing {
+    Contiguous,
+    Aligned, //Contiguous, base aligned to the register size
+    Strided, //Signed stride in lanes from the immediate
+    Indexed, //Lane indices from the register in the immediate
 }
 
 impl S64 {
+    //Core id of a machine whose cores share mem
+    pub(crate) fn core(mem: Memory, id: u64, cores: u64) -> Self {
+        Self {
+            mem,
+            id,
+            cores,
+            ..Default::default()
+        }
+    }
     pub(crate) fn execute_instruction(&mut self, instruction: Instruction) {
         let (reg0, reg1) = instruction.strip_registers();
-        let data = instruction.data;
-        match Opcode::from(instruction.opcode) {
+        let opcode = Opcode::from(instruction.opcode);
+        let data = instruction.immediate();
+        if u8::from(opcode) != instruction.opcode
+            || (opcode.has_lane() && !lane::is_valid(data as u16))
+        {
+            return self.fault(VmError::IllegalOpcode(instruction.opcode));
+        }
+        let mut regs = [Some(reg0), Some(reg1), instruction.reg2(), None];
+        if opcode.data_register() {
+            regs[3] = Some(data as u8);
+        }
+        //Lane masks and register immediates name compact registers in data
+        let reserved = if opcode.has_lane() {
+            [
+                lane::mask_register(data as u16),
+                (data >> LANE_IMM_SHIFT) as u8,
+            ]
+        } else {
+            [0; 2]
+        };
+        let swapped = match self.bank_in(&mut regs, &reserved) {
+            Ok(v) => v,
+            Err(swapped) => {
+                self.bank_out(swapped);
+                return self.fault(VmError::BankConflict);
+            }
+        };
+        let (reg0, reg1) = (regs[0].unwrap_or(0), regs[1].unwrap_or(0));
+        let data = regs[3].map_or(data, u64::from);
+        match regs[2] {
+            Some(reg2) if opcode.is_binary() => self.three(opcode, reg0, reg1, reg2, data),
+            _ => self.execute(opcode, reg0, reg1, data),
+        }
+        self.bank_out(swapped);
+        if std::mem::take(&mut self.resume) {
+            self.pc = self.pc.wrapping_sub(instruction.len() as u64);
+        }
+    }
+    //Banked registers stand in a compact register of their kind the instruction doesn't name
+    //or reserve, returns what bank_out needs to put both back. Registers read or written
+    //implicitly (r0 of get, masks in data) are never banked
+    fn bank_in(&mut self, regs: &mut [Option<u8>; 4], reserved: &[u8]) -> Result<Swapped, Swapped> {
+        let mut swapped: Swapped = Vec::new();
+        for k in 0..regs.len() {
+            let (reg, bank) = match regs[k].and_then(|v| Some((v, register::bank(v)?))) {
+                Some(v) => v,
+                None => continue,
+            };
+            if let Some(v) = swapped.iter().find(|v| v.0 == reg) {
+                regs[k] = Some(v.1);
+                continue;
+            }
+            if self.banked.is_empty() {
+                self.banked = vec![[0; 4]; register::REGISTERS - 16];
+            }
+            //From the last so that ops implicitly writing r0 keep it
+            let slot = bank.compact.iter().rev().copied().find(|v| {
+                !regs.contains(&Some(*v))
+                    && !reserved.contains(v)
+                    && !swapped.iter().any(|s| s.1 == *v)
+            });
+            let Some(slot) = slot else {
+                return Err(swapped);
+            };
+            swapped.push((reg, slot, self.get_limbs(slot)));
+            self.set_limbs(slot, self.banked[reg as usize - 16]);
+            regs[k] = Some(slot);
+        }
+        Ok(swapped)
+    }
+    fn bank_out(&mut self, swapped: Swapped) {
+        for (reg, slot, saved) in swapped {
+            self.banked[reg as usize - 16] = self.get_limbs(slot);
+            self.set_limbs(slot, saved);
+        }
+    }
+    //reg0 = reg1 op reg2 as a move followed by the destructive reg0 op= reg2
+    fn three(&mut self, opcode: Opcode, reg0: u8, reg1: u8, reg2: u8, data: u64) {
+        let (a, b) = (self.get_vals(reg1), self.get_vals(reg2));
+        if reg2 != reg0 {
+            self.set_vals(reg0, a);
+            self.execute(opcode, reg0, reg2, data);
+        } else if reg1 == reg0 {
+            self.execute(opcode, reg0, reg0, data);
+        } else {
+            //The move overwrites reg2, so reg1 holds its value for the op and is restored after
+            self.set_vals(reg1, b);
+            self.set_vals(reg0, a);
+            self.execute(opcode, reg0, reg1, data);
+            self.set_vals(reg1, a);
+        }
+    }
+    fn execute(&mut self, opcode: Opcode, reg0: u8, reg1: u8, data: u64) {
+        match opcode {
             Opcode::Nop => self.nop(reg0, reg1, data),
             Opcode::Set => self.set(reg0, reg1, data),
             Opcode::Get => self.get(reg0, reg1, data),
@@ -59,12 +233,223 @@ impl S64 {
             Opcode::Ddec => self.ddec(reg0, reg1, data),
             Opcode::Dneg => self.dneg(reg0, reg1, data),
             Opcode::Swap => self.swap(reg0, reg1, data),
+            Opcode::Itof => self.itof(reg0, reg1, data),
+            Opcode::Utof => self.utof(reg0, reg1, data),
+            Opcode::Ftoi => self.ftoi(reg0, reg1, data),
+            Opcode::Ftou => self.ftou(reg0, reg1, data),
+            Opcode::Rftoi => self.rftoi(reg0, reg1, data),
+            Opcode::Rftou => self.rftou(reg0, reg1, data),
+            Opcode::Adds => self.adds(reg0, reg1, data),
+            Opcode::Subs => self.subs(reg0, reg1, data),
+            Opcode::Muls => self.muls(reg0, reg1, data),
+            Opcode::Divs => self.divs(reg0, reg1, data),
+            Opcode::Fcvts => self.fcvts(reg0, reg1, data),
+            Opcode::Fcvtd => self.fcvtd(reg0, reg1, data),
+            Opcode::Rfcsr => self.rfcsr(reg0, reg1, data),
+            Opcode::Wfcsr => self.wfcsr(reg0, reg1, data),
+            Opcode::Dfrm => self.dfrm(reg0, reg1, data),
+            Opcode::Dfclr => self.dfclr(reg0, reg1, data),
+            Opcode::Sqrt => self.sqrt(reg0, reg1, data),
+            Opcode::Sqrts => self.sqrts(reg0, reg1, data),
+            Opcode::Fabs => self.fabs(reg0, reg1, data),
+            Opcode::Fabss => self.fabss(reg0, reg1, data),
+            Opcode::Floor => self.floor(reg0, reg1, data),
+            Opcode::Floors => self.floors(reg0, reg1, data),
+            Opcode::Ceil => self.ceil(reg0, reg1, data),
+            Opcode::Ceils => self.ceils(reg0, reg1, data),
+            Opcode::Round => self.round(reg0, reg1, data),
+            Opcode::Rounds => self.rounds(reg0, reg1, data),
+            Opcode::Sin => self.sin(reg0, reg1, data),
+            Opcode::Sins => self.sins(reg0, reg1, data),
+            Opcode::Cos => self.cos(reg0, reg1, data),
+            Opcode::Coss => self.coss(reg0, reg1, data),
+            Opcode::Exp => self.exp(reg0, reg1, data),
+            Opcode::Exps => self.exps(reg0, reg1, data),
+            Opcode::Log => self.log(reg0, reg1, data),
+            Opcode::Logs => self.logs(reg0, reg1, data),
+            Opcode::Fmin => self.fmin(reg0, reg1, data),
+            Opcode::Fmins => self.fmins(reg0, reg1, data),
+            Opcode::Fmax => self.fmax(reg0, reg1, data),
+            Opcode::Fmaxs => self.fmaxs(reg0, reg1, data),
+            Opcode::Copysign => self.copysign(reg0, reg1, data),
+            Opcode::Copysigns => self.copysigns(reg0, reg1, data),
+            Opcode::Pow => self.pow(reg0, reg1, data),
+            Opcode::Pows => self.pows(reg0, reg1, data),
+            Opcode::Fma => self.fma(reg0, reg1, data),
+            Opcode::Fmas => self.fmas(reg0, reg1, data),
+            Opcode::Vadd => self.vector(VectorOp::Add, reg0, reg1, data),
+            Opcode::Vsub => self.vector(VectorOp::Sub, reg0, reg1, data),
+            Opcode::Vmul => self.vector(VectorOp::Mul, reg0, reg1, data),
+            Opcode::Vaddss => self.vector(VectorOp::AddSat, reg0, reg1, data),
+            Opcode::Vaddus => self.vector(VectorOp::AddSatU, reg0, reg1, data),
+            Opcode::Vsubss => self.vector(VectorOp::SubSat, reg0, reg1, data),
+            Opcode::Vsubus => self.vector(VectorOp::SubSatU, reg0, reg1, data),
+            Opcode::Vaddw => self.vector(VectorOp::AddWide, reg0, reg1, data),
+            Opcode::Vaddwu => self.vector(VectorOp::AddWideU, reg0, reg1, data),
+            Opcode::Vmulw => self.vector(VectorOp::MulWide, reg0, reg1, data),
+            Opcode::Vmulwu => self.vector(VectorOp::MulWideU, reg0, reg1, data),
+            Opcode::Vshuf => self.vshuf(reg0, reg1, data),
+            Opcode::Vperm => self.vperm(reg0, reg1, data),
+            Opcode::Vins => self.vins(reg0, reg1, data),
+            Opcode::Vext => self.vext(reg0, reg1, data),
+            Opcode::Vbcast => self.vbcast(reg0, reg1, data),
+            Opcode::Vzipl => self.zip(reg0, reg1, data, false),
+            Opcode::Vziph => self.zip(reg0, reg1, data, true),
+            Opcode::Vuzpe => self.unzip(reg0, reg1, data, false),
+            Opcode::Vuzpo => self.unzip(reg0, reg1, data, true),
+            Opcode::Vhsum => self.reduce(ReduceOp::Sum, reg0, reg1, data),
+            Opcode::Vhmin => self.reduce(ReduceOp::Min, reg0, reg1, data),
+            Opcode::Vhmax => self.reduce(ReduceOp::Max, reg0, reg1, data),
+            Opcode::Vhminu => self.reduce(ReduceOp::MinU, reg0, reg1, data),
+            Opcode::Vhmaxu => self.reduce(ReduceOp::MaxU, reg0, reg1, data),
+            Opcode::Vhany => self.reduce(ReduceOp::Any, reg0, reg1, data),
+            Opcode::Vhall => self.reduce(ReduceOp::All, reg0, reg1, data),
+            Opcode::Vcmpeq => self.compare(CompareOp::Eq, reg0, reg1, data),
+            Opcode::Vcmpne => self.compare(CompareOp::Ne, reg0, reg1, data),
+            Opcode::Vcmplt => self.compare(CompareOp::Lt, reg0, reg1, data),
+            Opcode::Vcmple => self.compare(CompareOp::Le, reg0, reg1, data),
+            Opcode::Vcmpltu => self.compare(CompareOp::LtU, reg0, reg1, data),
+            Opcode::Vcmpleu => self.compare(CompareOp::LeU, reg0, reg1, data),
+            Opcode::Vblend => self.blend(reg0, reg1, data, false),
+            Opcode::Vmovz => self.blend(reg0, reg1, data, true),
+            Opcode::Vld => self.vload(Addressing::Contiguous, reg0, reg1, data),
+            Opcode::Vlda => self.vload(Addressing::Aligned, reg0, reg1, data),
+            Opcode::Vlds => self.vload(Addressing::Strided, reg0, reg1, data),
+            Opcode::Vgather => self.vload(Addressing::Indexed, reg0, reg1, data),
+            Opcode::Vst => self.vstore(Addressing::Contiguous, reg0, reg1, data),
+            Opcode::Vsta => self.vstore(Addressing::Aligned, reg0, reg1, data),
+            Opcode::Vsts => self.vstore(Addressing::Strided, reg0, reg1, data),
+            Opcode::Vscatter => self.vstore(Addressing::Indexed, reg0, reg1, data),
+            Opcode::Wadd => self.wadd(reg0, reg1, data, false),
+            Opcode::Wadc => self.wadd(reg0, reg1, data, true),
+            Opcode::Wsub => self.wsub(reg0, reg1, data, false),
+            Opcode::Wsbb => self.wsub(reg0, reg1, data, true),
+            Opcode::Wmul => self.wmul(reg0, reg1, data, false),
+            Opcode::Wmuls => self.wmul(reg0, reg1, data, true),
+            Opcode::Wshl => self.wshift(reg0, reg1, data, wide::shl),
+            Opcode::Wshr => self.wshift(reg0, reg1, data, |a, v, n| wide::shr(a, v, n, false)),
+            Opcode::Wsar => self.wshift(reg0, reg1, data, |a, v, n| wide::shr(a, v, n, true)),
+            Opcode::Wrotl => self.wshift(reg0, reg1, data, wide::rotl),
+            Opcode::Wrotr => self.wshift(reg0, reg1, data, wide::rotr),
+            Opcode::Wcmp => self.wcmp(reg0, reg1, data, false),
+            Opcode::Wcmps => self.wcmp(reg0, reg1, data, true),
+            Opcode::Sdiv => self.sdiv(reg0, reg1, data),
+            Opcode::Smod => self.smod(reg0, reg1, data),
+            Opcode::Sar => self.sar(reg0, reg1, data),
+            Opcode::Mulh => self.mulh(reg0, reg1, data),
+            Opcode::Mulhu => self.mulhu(reg0, reg1, data),
+            Opcode::Divmod => self.divmod(reg0, reg1, data, false),
+            Opcode::Sdivmod => self.divmod(reg0, reg1, data, true),
+            Opcode::Sext => self.sext(reg0, reg1, data),
+            Opcode::Zext => self.zext(reg0, reg1, data),
+            Opcode::Cadd => self.binary(OverflowPolicy::Checked, IntOp::Add, reg0, reg1, data),
+            Opcode::Csub => self.binary(OverflowPolicy::Checked, IntOp::Sub, reg0, reg1, data),
+            Opcode::Cmul => self.binary(OverflowPolicy::Checked, IntOp::Mul, reg0, reg1, data),
+            Opcode::Cinc => self.step(OverflowPolicy::Checked, IntOp::Add, reg0, reg1, data),
+            Opcode::Cdec => self.step(OverflowPolicy::Checked, IntOp::Sub, reg0, reg1, data),
+            Opcode::Cneg => self.step(OverflowPolicy::Checked, IntOp::Neg, reg0, reg1, data),
+            Opcode::Qadd => self.binary(OverflowPolicy::Saturating, IntOp::Add, reg0, reg1, data),
+            Opcode::Qsub => self.binary(OverflowPolicy::Saturating, IntOp::Sub, reg0, reg1, data),
+            Opcode::Qmul => self.binary(OverflowPolicy::Saturating, IntOp::Mul, reg0, reg1, data),
+            Opcode::Qinc => self.step(OverflowPolicy::Saturating, IntOp::Add, reg0, reg1, data),
+            Opcode::Qdec => self.step(OverflowPolicy::Saturating, IntOp::Sub, reg0, reg1, data),
+            Opcode::Qneg => self.step(OverflowPolicy::Saturating, IntOp::Neg, reg0, reg1, data),
+            Opcode::Rflags => self.rflags(reg0, reg1, data),
+            Opcode::Popcnt => self.bit_unary(BitOp::Popcnt, reg0, reg1, data),
+            Opcode::Clz => self.bit_unary(BitOp::Clz, reg0, reg1, data),
+            Opcode::Ctz => self.bit_unary(BitOp::Ctz, reg0, reg1, data),
+            Opcode::Bswap => self.bit_unary(BitOp::Bswap, reg0, reg1, data),
+            Opcode::Bitrev => self.bit_unary(BitOp::Bitrev, reg0, reg1, data),
+            Opcode::Rotl => self.bit_rotate(BitOp::Rotl, reg0, reg1, data),
+            Opcode::Rotr => self.bit_rotate(BitOp::Rotr, reg0, reg1, data),
+            Opcode::Bfext => self.bfext(reg0, reg1, data),
+            Opcode::Bfins => self.bfins(reg0, reg1, data),
+            Opcode::Bset => self.bset(reg0, reg1, data),
+            Opcode::Bclr => self.bclr(reg0, reg1, data),
+            Opcode::Btst => self.btst(reg0, reg1, data),
+            Opcode::Vpopcnt => self.vbits(BitOp::Popcnt, reg0, reg1, data),
+            Opcode::Vclz => self.vbits(BitOp::Clz, reg0, reg1, data),
+            Opcode::Vctz => self.vbits(BitOp::Ctz, reg0, reg1, data),
+            Opcode::Vrotl => self.vbits(BitOp::Rotl, reg0, reg1, data),
+            Opcode::Vrotr => self.vbits(BitOp::Rotr, reg0, reg1, data),
+            Opcode::Vbswap => self.vbits(BitOp::Bswap, reg0, reg1, data),
+            Opcode::Vbitrev => self.vbits(BitOp::Bitrev, reg0, reg1, data),
+            Opcode::Cmovc => self.cmov_flag(reg0, reg1, data, FLAG_CARRY, true),
+            Opcode::Cmovnc => self.cmov_flag(reg0, reg1, data, FLAG_CARRY, false),
+            Opcode::Cmovo => self.cmov_flag(reg0, reg1, data, FLAG_OVERFLOW, true),
+            Opcode::Cmovno => self.cmov_flag(reg0, reg1, data, FLAG_OVERFLOW, false),
+            Opcode::Cmovz => self.cmov(reg0, reg1, data, |v| v == 0),
+            Opcode::Cmovnz => self.cmov(reg0, reg1, data, |v| v != 0),
+            Opcode::Cmovlz => self.cmov(reg0, reg1, data, |v| v < 0),
+            Opcode::Cmovgez => self.cmov(reg0, reg1, data, |v| v >= 0),
+            Opcode::Jmp => self.jmp(reg0, reg1, data),
+            Opcode::Call => self.call(reg0, reg1, data),
+            Opcode::Jtab => self.jtab(reg0, reg1, data),
+            Opcode::Adr => self.adr(reg0, reg1, data),
+            Opcode::Jl => self.jump_op(data),
+            Opcode::Memcpy => self.memcpy(reg0, reg1, data, false),
+            Opcode::Memmove => self.memcpy(reg0, reg1, data, true),
+            Opcode::Memset => self.memset(reg0, reg1, data),
+            Opcode::Memcmp => self.memcmp(reg0, reg1, data),
+            Opcode::Memchr => self.memchr(reg0, reg1, data),
+            Opcode::Ald => self.ald(reg0, reg1, data),
+            Opcode::Ast => self.ast(reg0, reg1, data),
+            Opcode::Acas => self.acas(reg0, reg1, data),
+            Opcode::Aadd => self.afetch(reg0, reg1, data, AtomicOp::Add),
+            Opcode::Aand => self.afetch(reg0, reg1, data, AtomicOp::And),
+            Opcode::Aor => self.afetch(reg0, reg1, data, AtomicOp::Or),
+            Opcode::Axor => self.afetch(reg0, reg1, data, AtomicOp::Xor),
+            Opcode::Aswp => self.afetch(reg0, reg1, data, AtomicOp::Swap),
+            Opcode::Fence => atomic::fence(data),
+            Opcode::Cid => self.cid(reg0, reg1, data),
+            Opcode::Spawn => self.spawn(reg0, reg1, data),
+            Opcode::Park => self.event = Some(CoreEvent::Park),
+            Opcode::Wake => self.event = Some(CoreEvent::Wake(self.get_vals(reg0).0)),
+            Opcode::Tvec => self.tvec(reg0, reg1, data),
+            Opcode::Rtt => self.rtt(reg0, reg1, data),
+            Opcode::Ie => self.ie(reg0, reg1, data),
+            Opcode::Imask => self.imask(reg0, reg1, data),
+            Opcode::Ipend => self.ipend(reg0, reg1, data),
+            Opcode::Timer => self.timer(reg0, reg1, data),
+            Opcode::Ext => {}
+        }
+    }
+    pub(crate) fn load(&mut self, image: Image) {
+        self.base = image.base;
+        self.pc = image.entry;
+        self.code = image.code;
+    }
+    pub(crate) fn run_for(&mut self, budget: u64) -> Result<Exit, VmError> {
+        for _ in 0..budget {
+            if !self.in_trap {
+                self.interrupt();
+            }
+            let Some(ins) = self.fetch() else {
+                return Ok(Exit::Done);
+            };
+            let pc = self.pc;
+            self.pc = self.pc.wrapping_add(ins.len() as u64);
+            self.execute_instruction(ins);
+            self.irq.tick();
+            if let Some(err) = self.fault.take() {
+                self.trap(pc, err)?;
+            }
+            if let Some(event) = self.event.take() {
+                return Ok(Exit::Event(event));
+            }
         }
+        Ok(Exit::Budget)
     }
-    pub(crate) fn execute_code(&mut self, code: Vec<u32>) {
-        // code.par_iter().map(|ins| Instruction::from(*ins)).collect();
+    //Starts the core at pc with arg in r0, the other registers keep their values
+    pub(crate) fn start(&mut self, pc: u64, arg: u64) {
+        self.pc = pc;
+        self.r0 = arg;
     }
-    fn nop(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn fetch(&self) -> Option<Instruction> {
+        let index = self.pc.checked_sub(self.base)?;
+        Instruction::decode(self.code.get(usize::try_from(index).ok()?..)?)
+    }
+    fn nop(&mut self, reg0: u8, reg1: u8, data: u64) {
         match Register::from(reg0) {
             Register::ZR => {}
             Register::R0 => self.r0 = 0,
@@ -103,222 +488,18 @@ impl S64 {
         };
         self.jump_op(data)
     }
-    fn set(&mut self, reg0: u8, reg1: u8, data: u16) {
-        match Register::from(reg0) {
-            Register::ZR => return,
-            Register::R0 => {
-                self.r0 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.r0 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::R1 => {
-                self.r1 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.r1 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::R2 => {
-                self.r2 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.r2 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::F0 => {
-                self.f0 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.f0 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::F1 => {
-                self.f1 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.f1 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::F2 => {
-                self.f2 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.f2 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::P0 => {
-                self.p0 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.p0 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::P1 => {
-                self.p1 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.p1 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::P2 => {
-                self.p2 &= !(0xFFFF << (16 * if reg1 < 4 { reg1 } else { 0 }));
-                self.p2 |= (data as u64) << (16 * if reg1 < 4 { reg1 } else { 0 });
-            }
-            Register::S0 => {
-                if reg1 < 4 {
-                    self.s0[0] &= !(0xFFFF << (16 * reg1));
-                    self.s0[0] |= (data as u64) << (16 * reg1);
-                } else if reg1 < 8 {
-                    self.s0[1] &= !(0xFFFF << (16 * (reg1 - 4)));
-                    self.s0[1] |= (data as u64) << (16 * (reg1 - 4));
-                } else {
-                    self.s0[0] &= !(0xFFFF << (0));
-                    self.s0[0] |= data as u64;
-                }
-            }
-            Register::S1 => {
-                if reg1 < 4 {
-                    self.s1[0] &= !(0xFFFF << (16 * reg1));
-                    self.s1[0] |= (data as u64) << (16 * reg1);
-                } else if reg1 < 8 {
-                    self.s1[1] &= !(0xFFFF << (16 * (reg1 - 4)));
-                    self.s1[1] |= (data as u64) << (16 * (reg1 - 4));
-                } else {
-                    self.s1[0] &= !(0xFFFF << (0));
-                    self.s1[0] |= data as u64;
-                }
-            }
-            Register::S2 => {
-                if reg1 < 4 {
-                    self.s2[0] &= !(0xFFFF << (16 * reg1));
-                    self.s2[0] |= (data as u64) << (16 * reg1);
-                } else if reg1 < 8 {
-                    self.s2[1] &= !(0xFFFF << (16 * (reg1 - 4)));
-                    self.s2[1] |= (data as u64) << (16 * (reg1 - 4));
-                } else {
-                    self.s2[0] &= !(0xFFFF << 0);
-                    self.s2[0] |= data as u64;
-                }
-            }
-            Register::X0 => {
-                if reg1 < 4 {
-                    self.x0[0] &= !(0xFFFF << (16 * reg1));
-                    self.x0[0] &= (data as u64) << (16 * reg1 as u64);
-                } else if reg1 < 8 {
-                    self.x0[1] &= !(0xFFFF << (16 * (reg1 - 4)));
-                    self.x0[1] &= (data as u64) << ((16 * reg1 - 4) as u64);
-                } else if reg1 < 12 {
-                    self.x0[2] &= !(0xFFFF << (16 * (reg1 - 8)));
-                    self.x0[2] &= (data as u64) << ((16 * reg1 - 8) as u64);
-                } else if reg1 < 16 {
-                    self.s2[3] &= !(0xFFFF << (16 * (reg1 - 12)));
-                    self.x0[3] &= (data as u64) << ((16 * reg1 - 12) as u64);
-                } else {
-                    self.x0[0] &= !(0xFFFF << 0);
-                    self.x0[0] &= data as u64;
-                }
-            }
-            Register::X1 => {
-                if reg1 < 4 {
-                    self.x1[0] &= !(0xFFFF << (16 * reg1));
-                    self.x1[0] &= (data as u64) << (16 * reg1 as u64);
-                } else if reg1 < 8 {
-                    self.x1[1] &= !(0xFFFF << (16 * (reg1 - 4)));
-                    self.x1[1] &= (data as u64) << ((16 * reg1 - 4) as u64);
-                } else if reg1 < 12 {
-                    self.x1[2] &= !(0xFFFF << (16 * (reg1 - 8)));
-                    self.x1[2] &= (data as u64) << ((16 * reg1 - 8) as u64);
-                } else if reg1 < 16 {
-                    self.x1[3] &= !(0xFFFF << (16 * (reg1 - 12)));
-                    self.x1[3] &= (data as u64) << ((16 * reg1 - 12) as u64);
-                } else {
-                    self.x1[0] &= !(0xFFFF << 0);
-                    self.x1[0] &= data as u64;
-                }
-            }
-            Register::X2 => {
-                if reg1 < 4 {
-                    self.x2[0] &= !(0xFFFF << (16 * reg1));
-                    self.x2[0] &= (data as u64) << (16 * reg1 as u64);
-                } else if reg1 < 8 {
-                    self.x2[1] &= !(0xFFFF << (16 * (reg1 - 4)));
-                    self.x2[1] &= (data as u64) << ((16 * reg1 - 4) as u64);
-                } else if reg1 < 12 {
-                    self.x2[2] &= !(0xFFFF << (16 * (reg1 - 8)));
-                    self.x2[2] &= (data as u64) << ((16 * reg1 - 8) as u64);
-                } else if reg1 < 16 {
-                    self.x2[3] &= !(0xFFFF << (16 * (reg1 - 12)));
-                    self.x2[3] &= (data as u64) << ((16 * reg1 - 12) as u64);
-                } else {
-                    self.x2[0] &= !(0xFFFF << 0);
-                    self.x2[0] &= data as u64;
-                }
-            }
-        };
+    //16-bit lanes, the index comes from the reg1 nibble
+    fn set(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.insert(reg0, 16, reg1 as usize, data as u128);
     }
-    fn get(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let val = match Register::from(reg0) {
-            //Get value
-            Register::ZR => 0u64,
-            Register::R0 => self.r0 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::R1 => self.r1 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::R2 => self.r2 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::F0 => self.f0 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::F1 => self.f1 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::F2 => self.f2 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::P0 => self.p0 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::P1 => self.p1 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::P2 => self.p2 & (0xFFFF << if reg1 < 4 { reg1 } else { 0 }),
-            Register::S0 => {
-                if reg1 < 4 {
-                    self.s0[0] & (0xFFFF << reg1)
-                } else if reg1 < 8 {
-                    self.s0[1] & (0xFFFF << (reg1 - 4))
-                } else {
-                    self.s0[0]
-                }
-            }
-            Register::S1 => {
-                if reg1 < 4 {
-                    self.s1[0] & (0xFFFF << reg1)
-                } else if reg1 < 8 {
-                    self.s1[1] & (0xFFFF << (reg1 - 4))
-                } else {
-                    self.s1[0]
-                }
-            }
-            Register::S2 => {
-                if reg1 < 4 {
-                    self.s2[0] & (0xFFFF << reg1)
-                } else if reg1 < 8 {
-                    self.s2[1] & (0xFFFF << (reg1 - 4))
-                } else {
-                    self.s2[0]
-                }
-            }
-            Register::X0 => {
-                if reg1 < 4 {
-                    self.x0[0] & (0xFFFF << reg1)
-                } else if reg1 < 8 {
-                    self.x0[1] & (0xFFFF << (reg1 - 4))
-                } else if reg1 < 12 {
-                    self.x0[2] & (0xFFFF << (reg1 - 8))
-                } else if reg1 < 16 {
-                    self.x0[3] & (0xFFFF << (reg1 - 12))
-                } else {
-                    self.x0[0]
-                }
-            }
-            Register::X1 => {
-                if reg1 < 4 {
-                    self.x1[0] & (0xFFFF << reg1)
-                } else if reg1 < 8 {
-                    self.x1[1] & (0xFFFF << (reg1 - 4))
-                } else if reg1 < 12 {
-                    self.x1[2] & (0xFFFF << (reg1 - 8))
-                } else if reg1 < 16 {
-                    self.x1[3] & (0xFFFF << (reg1 - 12))
-                } else {
-                    self.x1[0]
-                }
-            }
-            Register::X2 => {
-                if reg1 < 4 {
-                    self.x2[0] & (0xFFFF << reg1)
-                } else if reg1 < 8 {
-                    self.x2[1] & (0xFFFF << (reg1 - 4))
-                } else if reg1 < 12 {
-                    self.x2[2] & (0xFFFF << (reg1 - 8))
-                } else if reg1 < 16 {
-                    self.x2[3] & (0xFFFF << (reg1 - 12))
-                } else {
-                    self.x2[0]
-                }
-            }
-        };
-        self.r0 &= !(0xFFFF << (16 * (reg1 % 4)));
-        self.r0 &= val;
-        self.jump_op(data);
+    //r0 gets lane index of reg0 zero extended, use vext for any other destination
+    fn get(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if let Some(val) = self.extract(reg0, 16, reg1 as usize) {
+            self.r0 = val as u64;
+            self.jump_op(data);
+        }
     }
-    fn update(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn update(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
@@ -364,12 +545,12 @@ impl S64 {
         };
         self.jump_op(data)
     }
-    fn delete(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn delete(&mut self, reg0: u8, reg1: u8, data: u64) {
         self.update(reg0, reg1, 0);
         self.nop(Register::ZR.into(), reg1, 0);
         self.jump_op(data);
     }
-    fn and(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn and(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
@@ -415,7 +596,7 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn or(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn or(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
@@ -461,7 +642,7 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn xor(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn xor(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
@@ -507,7 +688,7 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn not(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn not(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         let val = !val;
         let val2 = !val2;
@@ -557,282 +738,282 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn shl(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn shl(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 <<= val,
-            Register::R1 => self.r1 <<= val,
-            Register::R2 => self.r2 <<= val,
-            Register::F0 => self.f0 <<= val,
-            Register::F1 => self.f1 <<= val,
-            Register::F2 => self.f2 <<= val,
-            Register::P0 => self.p0 <<= val,
-            Register::P1 => self.p1 <<= val,
-            Register::P2 => self.p2 <<= val,
+            Register::R0 => self.r0 = shift_left(self.r0, val),
+            Register::R1 => self.r1 = shift_left(self.r1, val),
+            Register::R2 => self.r2 = shift_left(self.r2, val),
+            Register::F0 => self.f0 = shift_left(self.f0, val),
+            Register::F1 => self.f1 = shift_left(self.f1, val),
+            Register::F2 => self.f2 = shift_left(self.f2, val),
+            Register::P0 => self.p0 = shift_left(self.p0, val),
+            Register::P1 => self.p1 = shift_left(self.p1, val),
+            Register::P2 => self.p2 = shift_left(self.p2, val),
             Register::S0 => {
                 if val >= 64 {
                     self.nop(Register::S0.into(), Register::ZR.into(), 0);
                 } else {
-                    self.s0[0] <<= val;
-                    self.s0[1] <<= val2;
+                    self.s0[0] = shift_left(self.s0[0], val);
+                    self.s0[1] = shift_left(self.s0[1], val2);
                 }
             }
             Register::S1 => {
                 if val >= 64 {
                     self.nop(Register::S1.into(), Register::ZR.into(), 0);
                 } else {
-                    self.s1[0] <<= val;
-                    self.s1[1] <<= val2;
+                    self.s1[0] = shift_left(self.s1[0], val);
+                    self.s1[1] = shift_left(self.s1[1], val2);
                 }
             }
             Register::S2 => {
                 if val >= 64 {
                     self.nop(Register::S2.into(), Register::ZR.into(), 0);
                 } else {
-                    self.s2[0] <<= val;
-                    self.s2[1] <<= val2;
+                    self.s2[0] = shift_left(self.s2[0], val);
+                    self.s2[1] = shift_left(self.s2[1], val2);
                 }
             }
             Register::X0 => {
                 if val >= 64 {
                     self.nop(Register::X0.into(), Register::ZR.into(), 0);
                 } else {
-                    self.x0[0] <<= val;
-                    self.x0[1] <<= val2;
-                    self.x0[2] <<= val3;
-                    self.x0[3] <<= val4;
+                    self.x0[0] = shift_left(self.x0[0], val);
+                    self.x0[1] = shift_left(self.x0[1], val2);
+                    self.x0[2] = shift_left(self.x0[2], val3);
+                    self.x0[3] = shift_left(self.x0[3], val4);
                 }
             }
             Register::X1 => {
                 if val >= 64 {
                     self.nop(Register::X1.into(), Register::ZR.into(), 0);
                 } else {
-                    self.x1[0] <<= val;
-                    self.x1[1] <<= val2;
-                    self.x1[2] <<= val3;
-                    self.x1[3] <<= val4;
+                    self.x1[0] = shift_left(self.x1[0], val);
+                    self.x1[1] = shift_left(self.x1[1], val2);
+                    self.x1[2] = shift_left(self.x1[2], val3);
+                    self.x1[3] = shift_left(self.x1[3], val4);
                 }
             }
             Register::X2 => {
                 if val >= 64 {
                     self.nop(Register::X2.into(), Register::ZR.into(), 0);
                 } else {
-                    self.x2[0] <<= val;
-                    self.x2[1] <<= val2;
-                    self.x2[2] <<= val3;
-                    self.x2[3] <<= val4;
+                    self.x2[0] = shift_left(self.x2[0], val);
+                    self.x2[1] = shift_left(self.x2[1], val2);
+                    self.x2[2] = shift_left(self.x2[2], val3);
+                    self.x2[3] = shift_left(self.x2[3], val4);
                 }
             }
         };
         self.jump_op(data);
     }
-    fn shr(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn shr(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 >>= val,
-            Register::R1 => self.r1 >>= val,
-            Register::R2 => self.r2 >>= val,
-            Register::F0 => self.f0 >>= val,
-            Register::F1 => self.f1 >>= val,
-            Register::F2 => self.f2 >>= val,
-            Register::P0 => self.p0 >>= val,
-            Register::P1 => self.p1 >>= val,
-            Register::P2 => self.p2 >>= val,
+            Register::R0 => self.r0 = shift_right(self.r0, val),
+            Register::R1 => self.r1 = shift_right(self.r1, val),
+            Register::R2 => self.r2 = shift_right(self.r2, val),
+            Register::F0 => self.f0 = shift_right(self.f0, val),
+            Register::F1 => self.f1 = shift_right(self.f1, val),
+            Register::F2 => self.f2 = shift_right(self.f2, val),
+            Register::P0 => self.p0 = shift_right(self.p0, val),
+            Register::P1 => self.p1 = shift_right(self.p1, val),
+            Register::P2 => self.p2 = shift_right(self.p2, val),
             Register::S0 => {
                 if val >= 64 {
                     self.nop(Register::S0.into(), Register::ZR.into(), 0);
                 } else {
-                    self.s0[0] >>= val;
-                    self.s0[1] >>= val2;
+                    self.s0[0] = shift_right(self.s0[0], val);
+                    self.s0[1] = shift_right(self.s0[1], val2);
                 }
             }
             Register::S1 => {
                 if val >= 64 {
                     self.nop(Register::S1.into(), Register::ZR.into(), 0);
                 } else {
-                    self.s1[0] >>= val;
-                    self.s1[1] >>= val2;
+                    self.s1[0] = shift_right(self.s1[0], val);
+                    self.s1[1] = shift_right(self.s1[1], val2);
                 }
             }
             Register::S2 => {
                 if val >= 64 {
                     self.nop(Register::S2.into(), Register::ZR.into(), 0);
                 } else {
-                    self.s2[0] >>= val;
-                    self.s2[1] >>= val2;
+                    self.s2[0] = shift_right(self.s2[0], val);
+                    self.s2[1] = shift_right(self.s2[1], val2);
                 }
             }
             Register::X0 => {
                 if val >= 64 {
                     self.nop(Register::X0.into(), Register::ZR.into(), 0);
                 } else {
-                    self.x0[0] >>= val;
-                    self.x0[1] >>= val2;
-                    self.x0[2] >>= val3;
-                    self.x0[3] >>= val4;
+                    self.x0[0] = shift_right(self.x0[0], val);
+                    self.x0[1] = shift_right(self.x0[1], val2);
+                    self.x0[2] = shift_right(self.x0[2], val3);
+                    self.x0[3] = shift_right(self.x0[3], val4);
                 }
             }
             Register::X1 => {
                 if val >= 64 {
                     self.nop(Register::X1.into(), Register::ZR.into(), 0);
                 } else {
-                    self.x1[0] >>= val;
-                    self.x1[1] >>= val2;
-                    self.x1[2] >>= val3;
-                    self.x1[3] >>= val4;
+                    self.x1[0] = shift_right(self.x1[0], val);
+                    self.x1[1] = shift_right(self.x1[1], val2);
+                    self.x1[2] = shift_right(self.x1[2], val3);
+                    self.x1[3] = shift_right(self.x1[3], val4);
                 }
             }
             Register::X2 => {
                 if val >= 64 {
                     self.nop(Register::X2.into(), Register::ZR.into(), 0);
                 } else {
-                    self.x2[0] >>= val;
-                    self.x2[1] >>= val2;
-                    self.x2[2] >>= val3;
-                    self.x2[3] >>= val4;
+                    self.x2[0] = shift_right(self.x2[0], val);
+                    self.x2[1] = shift_right(self.x2[1], val2);
+                    self.x2[2] = shift_right(self.x2[2], val3);
+                    self.x2[3] = shift_right(self.x2[3], val4);
                 }
             }
         };
         self.jump_op(data);
     }
-    fn dand(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dand(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val & (data as u64),
-            Register::R1 => self.r1 = val & (data as u64),
-            Register::R2 => self.r2 = val & (data as u64),
-            Register::F0 => self.f0 = val & (data as u64),
-            Register::F1 => self.f1 = val & (data as u64),
-            Register::F2 => self.f2 = val & (data as u64),
-            Register::P0 => self.p0 = val & (data as u64),
-            Register::P1 => self.p1 = val & (data as u64),
-            Register::P2 => self.p2 = val & (data as u64),
+            Register::R0 => self.r0 = val & data,
+            Register::R1 => self.r1 = val & data,
+            Register::R2 => self.r2 = val & data,
+            Register::F0 => self.f0 = val & data,
+            Register::F1 => self.f1 = val & data,
+            Register::F2 => self.f2 = val & data,
+            Register::P0 => self.p0 = val & data,
+            Register::P1 => self.p1 = val & data,
+            Register::P2 => self.p2 = val & data,
             Register::S0 => {
-                self.s0[0] = val & (data as u64);
-                self.s0[1] = val2 & (data as u64);
+                self.s0[0] = val & data;
+                self.s0[1] = val2 & data;
             }
             Register::S1 => {
-                self.s1[0] = val & (data as u64);
-                self.s1[1] = val2 & (data as u64);
+                self.s1[0] = val & data;
+                self.s1[1] = val2 & data;
             }
             Register::S2 => {
-                self.s2[0] = val & (data as u64);
-                self.s2[1] = val2 & (data as u64);
+                self.s2[0] = val & data;
+                self.s2[1] = val2 & data;
             }
             Register::X0 => {
-                self.x0[0] = val & (data as u64);
-                self.x0[1] = val2 & (data as u64);
-                self.x0[2] = val3 & (data as u64);
-                self.x0[3] = val4 & (data as u64);
+                self.x0[0] = val & data;
+                self.x0[1] = val2 & data;
+                self.x0[2] = val3 & data;
+                self.x0[3] = val4 & data;
             }
             Register::X1 => {
-                self.x1[0] = val & (data as u64);
-                self.x1[1] = val2 & (data as u64);
-                self.x1[2] = val3 & (data as u64);
-                self.x1[3] = val4 & (data as u64);
+                self.x1[0] = val & data;
+                self.x1[1] = val2 & data;
+                self.x1[2] = val3 & data;
+                self.x1[3] = val4 & data;
             }
             Register::X2 => {
-                self.x2[0] = val & (data as u64);
-                self.x2[1] = val2 & (data as u64);
-                self.x2[2] = val3 & (data as u64);
-                self.x2[3] = val4 & (data as u64);
+                self.x2[0] = val & data;
+                self.x2[1] = val2 & data;
+                self.x2[2] = val3 & data;
+                self.x2[3] = val4 & data;
             }
         };
     }
-    fn dor(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dor(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val | (data as u64),
-            Register::R1 => self.r1 = val | (data as u64),
-            Register::R2 => self.r2 = val | (data as u64),
-            Register::F0 => self.f0 = val | (data as u64),
-            Register::F1 => self.f1 = val | (data as u64),
-            Register::F2 => self.f2 = val | (data as u64),
-            Register::P0 => self.p0 = val | (data as u64),
-            Register::P1 => self.p1 = val | (data as u64),
-            Register::P2 => self.p2 = val | (data as u64),
+            Register::R0 => self.r0 = val | data,
+            Register::R1 => self.r1 = val | data,
+            Register::R2 => self.r2 = val | data,
+            Register::F0 => self.f0 = val | data,
+            Register::F1 => self.f1 = val | data,
+            Register::F2 => self.f2 = val | data,
+            Register::P0 => self.p0 = val | data,
+            Register::P1 => self.p1 = val | data,
+            Register::P2 => self.p2 = val | data,
             Register::S0 => {
-                self.s0[0] = val | (data as u64);
-                self.s0[1] = val2 | (data as u64);
+                self.s0[0] = val | data;
+                self.s0[1] = val2 | data;
             }
             Register::S1 => {
-                self.s1[0] = val | (data as u64);
-                self.s1[1] = val2 | (data as u64);
+                self.s1[0] = val | data;
+                self.s1[1] = val2 | data;
             }
             Register::S2 => {
-                self.s2[0] = val | (data as u64);
-                self.s2[1] = val2 | (data as u64);
+                self.s2[0] = val | data;
+                self.s2[1] = val2 | data;
             }
             Register::X0 => {
-                self.x0[0] = val | (data as u64);
-                self.x0[1] = val2 | (data as u64);
-                self.x0[2] = val3 | (data as u64);
-                self.x0[3] = val4 | (data as u64);
+                self.x0[0] = val | data;
+                self.x0[1] = val2 | data;
+                self.x0[2] = val3 | data;
+                self.x0[3] = val4 | data;
             }
             Register::X1 => {
-                self.x1[0] = val | (data as u64);
-                self.x1[1] = val2 | (data as u64);
-                self.x1[2] = val3 | (data as u64);
-                self.x1[3] = val4 | (data as u64);
+                self.x1[0] = val | data;
+                self.x1[1] = val2 | data;
+                self.x1[2] = val3 | data;
+                self.x1[3] = val4 | data;
             }
             Register::X2 => {
-                self.x2[0] = val | (data as u64);
-                self.x2[1] = val2 | (data as u64);
-                self.x2[2] = val3 | (data as u64);
-                self.x2[3] = val4 | (data as u64);
+                self.x2[0] = val | data;
+                self.x2[1] = val2 | data;
+                self.x2[2] = val3 | data;
+                self.x2[3] = val4 | data;
             }
         };
     }
-    fn dxor(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dxor(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val ^ (data as u64),
-            Register::R1 => self.r1 = val ^ (data as u64),
-            Register::R2 => self.r2 = val ^ (data as u64),
-            Register::F0 => self.f0 = val ^ (data as u64),
-            Register::F1 => self.f1 = val ^ (data as u64),
-            Register::F2 => self.f2 = val ^ (data as u64),
-            Register::P0 => self.p0 = val ^ (data as u64),
-            Register::P1 => self.p1 = val ^ (data as u64),
-            Register::P2 => self.p2 = val ^ (data as u64),
+            Register::R0 => self.r0 = val ^ data,
+            Register::R1 => self.r1 = val ^ data,
+            Register::R2 => self.r2 = val ^ data,
+            Register::F0 => self.f0 = val ^ data,
+            Register::F1 => self.f1 = val ^ data,
+            Register::F2 => self.f2 = val ^ data,
+            Register::P0 => self.p0 = val ^ data,
+            Register::P1 => self.p1 = val ^ data,
+            Register::P2 => self.p2 = val ^ data,
             Register::S0 => {
-                self.s0[0] = val ^ (data as u64);
-                self.s0[1] = val2 ^ (data as u64);
+                self.s0[0] = val ^ data;
+                self.s0[1] = val2 ^ data;
             }
             Register::S1 => {
-                self.s1[0] = val ^ (data as u64);
-                self.s1[1] = val2 ^ (data as u64);
+                self.s1[0] = val ^ data;
+                self.s1[1] = val2 ^ data;
             }
             Register::S2 => {
-                self.s2[0] = val ^ (data as u64);
-                self.s2[1] = val2 ^ (data as u64);
+                self.s2[0] = val ^ data;
+                self.s2[1] = val2 ^ data;
             }
             Register::X0 => {
-                self.x0[0] = val ^ (data as u64);
-                self.x0[1] = val2 ^ (data as u64);
-                self.x0[2] = val3 ^ (data as u64);
-                self.x0[3] = val4 ^ (data as u64);
+                self.x0[0] = val ^ data;
+                self.x0[1] = val2 ^ data;
+                self.x0[2] = val3 ^ data;
+                self.x0[3] = val4 ^ data;
             }
             Register::X1 => {
-                self.x1[0] = val ^ (data as u64);
-                self.x1[1] = val2 ^ (data as u64);
-                self.x1[2] = val3 ^ (data as u64);
-                self.x1[3] = val4 ^ (data as u64);
+                self.x1[0] = val ^ data;
+                self.x1[1] = val2 ^ data;
+                self.x1[2] = val3 ^ data;
+                self.x1[3] = val4 ^ data;
             }
             Register::X2 => {
-                self.x2[0] = val ^ (data as u64);
-                self.x2[1] = val2 ^ (data as u64);
-                self.x2[2] = val3 ^ (data as u64);
-                self.x2[3] = val4 ^ (data as u64);
+                self.x2[0] = val ^ data;
+                self.x2[1] = val2 ^ data;
+                self.x2[2] = val3 ^ data;
+                self.x2[3] = val4 ^ data;
             }
         };
     }
-    fn dnot(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dnot(&mut self, reg0: u8, reg1: u8, data: u64) {
         match Register::from(reg1) {
             Register::ZR => {}
             Register::R0 => self.r0 = !self.r0,
@@ -919,237 +1100,109 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn dshl(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dshl(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val << (data as u64),
-            Register::R1 => self.r1 = val << (data as u64),
-            Register::R2 => self.r2 = val << (data as u64),
-            Register::F0 => self.f0 = val << (data as u64),
-            Register::F1 => self.f1 = val << (data as u64),
-            Register::F2 => self.f2 = val << (data as u64),
-            Register::P0 => self.p0 = val << (data as u64),
-            Register::P1 => self.p1 = val << (data as u64),
-            Register::P2 => self.p2 = val << (data as u64),
+            Register::R0 => self.r0 = shift_left(val, data),
+            Register::R1 => self.r1 = shift_left(val, data),
+            Register::R2 => self.r2 = shift_left(val, data),
+            Register::F0 => self.f0 = shift_left(val, data),
+            Register::F1 => self.f1 = shift_left(val, data),
+            Register::F2 => self.f2 = shift_left(val, data),
+            Register::P0 => self.p0 = shift_left(val, data),
+            Register::P1 => self.p1 = shift_left(val, data),
+            Register::P2 => self.p2 = shift_left(val, data),
             Register::S0 => {
-                self.s0[0] = val << data;
-                self.s0[1] = val2 << data;
+                self.s0[0] = shift_left(val, data);
+                self.s0[1] = shift_left(val2, data);
             }
             Register::S1 => {
-                self.s1[0] = val << data;
-                self.s1[1] = val2 << data;
+                self.s1[0] = shift_left(val, data);
+                self.s1[1] = shift_left(val2, data);
             }
             Register::S2 => {
-                self.s2[0] = val << data;
-                self.s2[1] = val2 << data;
+                self.s2[0] = shift_left(val, data);
+                self.s2[1] = shift_left(val2, data);
             }
             Register::X0 => {
-                self.x0[0] = val << data;
-                self.x0[1] = val2 << data;
-                self.x0[2] = val3 << data;
-                self.x0[3] = val4 << data;
+                self.x0[0] = shift_left(val, data);
+                self.x0[1] = shift_left(val2, data);
+                self.x0[2] = shift_left(val3, data);
+                self.x0[3] = shift_left(val4, data);
             }
             Register::X1 => {
-                self.x1[0] = val << data;
-                self.x1[1] = val2 << data;
-                self.x1[2] = val3 << data;
-                self.x1[3] = val4 << data;
+                self.x1[0] = shift_left(val, data);
+                self.x1[1] = shift_left(val2, data);
+                self.x1[2] = shift_left(val3, data);
+                self.x1[3] = shift_left(val4, data);
             }
             Register::X2 => {
-                self.x0[0] = val << data;
-                self.x0[1] = val2 << data;
-                self.x0[2] = val3 << data;
-                self.x0[3] = val4 << data;
+                self.x2[0] = shift_left(val, data);
+                self.x2[1] = shift_left(val2, data);
+                self.x2[2] = shift_left(val3, data);
+                self.x2[3] = shift_left(val4, data);
             }
         };
     }
-    fn dshr(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dshr(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val >> (data as u64),
-            Register::R1 => self.r1 = val >> (data as u64),
-            Register::R2 => self.r2 = val >> (data as u64),
-            Register::F0 => self.f0 = val >> (data as u64),
-            Register::F1 => self.f1 = val >> (data as u64),
-            Register::F2 => self.f2 = val >> (data as u64),
-            Register::P0 => self.p0 = val >> (data as u64),
-            Register::P1 => self.p1 = val >> (data as u64),
-            Register::P2 => self.p2 = val >> (data as u64),
+            Register::R0 => self.r0 = shift_right(val, data),
+            Register::R1 => self.r1 = shift_right(val, data),
+            Register::R2 => self.r2 = shift_right(val, data),
+            Register::F0 => self.f0 = shift_right(val, data),
+            Register::F1 => self.f1 = shift_right(val, data),
+            Register::F2 => self.f2 = shift_right(val, data),
+            Register::P0 => self.p0 = shift_right(val, data),
+            Register::P1 => self.p1 = shift_right(val, data),
+            Register::P2 => self.p2 = shift_right(val, data),
             Register::S0 => {
-                self.s0[0] = val >> data;
-                self.s0[1] = val2 >> data;
+                self.s0[0] = shift_right(val, data);
+                self.s0[1] = shift_right(val2, data);
             }
             Register::S1 => {
-                self.s1[0] = val >> data;
-                self.s1[1] = val2 >> data;
+                self.s1[0] = shift_right(val, data);
+                self.s1[1] = shift_right(val2, data);
             }
             Register::S2 => {
-                self.s2[0] = val >> data;
-                self.s2[1] = val2 >> data;
+                self.s2[0] = shift_right(val, data);
+                self.s2[1] = shift_right(val2, data);
             }
             Register::X0 => {
-                self.x0[0] = val >> data;
-                self.x0[1] = val2 >> data;
-                self.x0[2] = val3 >> data;
-                self.x0[3] = val4 >> data;
+                self.x0[0] = shift_right(val, data);
+                self.x0[1] = shift_right(val2, data);
+                self.x0[2] = shift_right(val3, data);
+                self.x0[3] = shift_right(val4, data);
             }
             Register::X1 => {
-                self.x1[0] = val >> data;
-                self.x1[1] = val2 >> data;
-                self.x1[2] = val3 >> data;
-                self.x1[3] = val4 >> data;
+                self.x1[0] = shift_right(val, data);
+                self.x1[1] = shift_right(val2, data);
+                self.x1[2] = shift_right(val3, data);
+                self.x1[3] = shift_right(val4, data);
             }
             Register::X2 => {
-                self.x0[0] = val >> data;
-                self.x0[1] = val2 >> data;
-                self.x0[2] = val3 >> data;
-                self.x0[3] = val4 >> data;
+                self.x2[0] = shift_right(val, data);
+                self.x2[1] = shift_right(val2, data);
+                self.x2[2] = shift_right(val3, data);
+                self.x2[3] = shift_right(val4, data);
             }
         };
     }
-    fn add(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 += val,
-            Register::R1 => self.r1 += val,
-            Register::R2 => self.r2 += val,
-            Register::F0 => self.f0 = ((val as f64) + (self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) + (self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) + (self.f2 as f64)).to_bits(),
-            Register::P0 => self.p0 += val,
-            Register::P1 => self.p1 += val,
-            Register::P2 => self.p2 += val,
-            Register::S0 => {
-                self.s0[0] += val;
-                self.s0[1] += val2;
-            }
-            Register::S1 => {
-                self.s1[0] += val;
-                self.s1[1] += val2;
-            }
-            Register::S2 => {
-                self.s2[0] += val;
-                self.s2[1] += val2;
-            }
-            Register::X0 => {
-                self.x0[0] += val;
-                self.x0[1] += val2;
-                self.x0[2] += val3;
-                self.x0[3] += val4;
-            }
-            Register::X1 => {
-                self.x1[0] += val;
-                self.x1[1] += val2;
-                self.x1[2] += val3;
-                self.x1[3] += val4;
-            }
-            Register::X2 => {
-                self.x2[0] += val;
-                self.x2[1] += val2;
-                self.x2[2] += val3;
-                self.x2[3] += val4;
-            }
-        };
-        self.jump_op(data);
+    fn add(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.binary(self.overflow, IntOp::Add, reg0, reg1, data);
     }
-    fn sub(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 -= val,
-            Register::R1 => self.r1 -= val,
-            Register::R2 => self.r2 -= val,
-            Register::F0 => self.f0 = ((val as f64) - (self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) - (self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) - (self.f2 as f64)).to_bits(),
-            Register::P0 => self.p0 -= val,
-            Register::P1 => self.p1 -= val,
-            Register::P2 => self.p2 -= val,
-            Register::S0 => {
-                self.s0[0] -= val;
-                self.s0[1] -= val2;
-            }
-            Register::S1 => {
-                self.s1[0] -= val;
-                self.s1[1] -= val2;
-            }
-            Register::S2 => {
-                self.s2[0] -= val;
-                self.s2[1] -= val2;
-            }
-            Register::X0 => {
-                self.x0[0] -= val;
-                self.x0[1] -= val2;
-                self.x0[2] -= val3;
-                self.x0[3] -= val4;
-            }
-            Register::X1 => {
-                self.x1[0] -= val;
-                self.x1[1] -= val2;
-                self.x1[2] -= val3;
-                self.x1[3] -= val4;
-            }
-            Register::X2 => {
-                self.x2[0] -= val;
-                self.x2[1] -= val2;
-                self.x2[2] -= val3;
-                self.x2[3] -= val4;
-            }
-        };
-        self.jump_op(data);
+    fn sub(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.binary(self.overflow, IntOp::Sub, reg0, reg1, data);
     }
-    fn mul(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 *= val,
-            Register::R1 => self.r1 *= val,
-            Register::R2 => self.r2 *= val,
-            Register::F0 => self.f0 = ((val as f64) * (self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) * (self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) * (self.f2 as f64)).to_bits(),
-            Register::P0 => self.p0 *= val,
-            Register::P1 => self.p1 *= val,
-            Register::P2 => self.p2 *= val,
-            Register::S0 => {
-                self.s0[0] *= val;
-                self.s0[1] *= val2;
-            }
-            Register::S1 => {
-                self.s1[0] *= val;
-                self.s1[1] *= val2;
-            }
-            Register::S2 => {
-                self.s2[0] *= val;
-                self.s2[1] *= val2;
-            }
-            Register::X0 => {
-                self.x0[0] *= val;
-                self.x0[1] *= val2;
-                self.x0[2] *= val3;
-                self.x0[3] *= val4;
-            }
-            Register::X1 => {
-                self.x1[0] *= val;
-                self.x1[1] *= val2;
-                self.x1[2] *= val3;
-                self.x1[3] *= val4;
-            }
-            Register::X2 => {
-                self.x2[0] *= val;
-                self.x2[1] *= val2;
-                self.x2[2] *= val3;
-                self.x2[3] *= val4;
-            }
-        };
-        self.jump_op(data);
+    fn mul(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.binary(self.overflow, IntOp::Mul, reg0, reg1, data);
     }
-    fn div(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn div(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
-        if val == 0 || val2 == 0 || val3 == 0 || val4 == 0 {
+        //Float division by zero is defined (inf/NaN)
+        if !is_float(reg0) && self.divisor_zero(reg0, reg1) {
             return;
         }
         match Register::from(reg0) {
@@ -1157,9 +1210,9 @@ impl S64 {
             Register::R0 => self.r0 /= val,
             Register::R1 => self.r1 /= val,
             Register::R2 => self.r2 /= val,
-            Register::F0 => self.f0 = ((val as f64) / (self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) / (self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) / (self.f2 as f64)).to_bits(),
+            Register::F0 => self.f0 = self.fop(FloatOp::Div, self.f0, val),
+            Register::F1 => self.f1 = self.fop(FloatOp::Div, self.f1, val),
+            Register::F2 => self.f2 = self.fop(FloatOp::Div, self.f2, val),
             Register::P0 => self.p0 /= val,
             Register::P1 => self.p1 /= val,
             Register::P2 => self.p2 /= val,
@@ -1196,9 +1249,10 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn r#mod(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn r#mod(&mut self, reg0: u8, reg1: u8, data: u64) {
         let (val, val2, val3, val4) = self.get_vals(reg1);
-        if val == 0 || val2 == 0 || val3 == 0 || val4 == 0 {
+        //Float division by zero is defined (inf/NaN)
+        if !is_float(reg0) && self.divisor_zero(reg0, reg1) {
             return;
         }
         match Register::from(reg0) {
@@ -1206,9 +1260,9 @@ impl S64 {
             Register::R0 => self.r0 %= val,
             Register::R1 => self.r1 %= val,
             Register::R2 => self.r2 %= val,
-            Register::F0 => self.f0 = ((val as f64) % (self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) % (self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) % (self.f2 as f64)).to_bits(),
+            Register::F0 => self.f0 = self.fop(FloatOp::Rem, self.f0, val),
+            Register::F1 => self.f1 = self.fop(FloatOp::Rem, self.f1, val),
+            Register::F2 => self.f2 = self.fop(FloatOp::Rem, self.f2, val),
             Register::P0 => self.p0 %= val,
             Register::P1 => self.p1 %= val,
             Register::P2 => self.p2 %= val,
@@ -1245,849 +1299,1375 @@ impl S64 {
         };
         self.jump_op(data);
     }
-    fn inc(&mut self, reg0: u8, reg1: u8, data: u16) {
-        match Register::from(reg1) {
+    fn inc(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.step(self.overflow, IntOp::Add, reg0, reg1, data);
+    }
+    fn dec(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.step(self.overflow, IntOp::Sub, reg0, reg1, data);
+    }
+    fn neg(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.step(self.overflow, IntOp::Neg, reg0, reg1, data);
+    }
+    fn dadd(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let a = self.get_limbs(reg1);
+        self.immediate(self.overflow, IntOp::Add, reg0, a, data);
+    }
+    fn dsub(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let a = self.get_limbs(reg1);
+        self.immediate(self.overflow, IntOp::Sub, reg0, a, data);
+    }
+    fn dmul(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let a = self.get_limbs(reg1);
+        self.immediate(self.overflow, IntOp::Mul, reg0, a, data);
+    }
+    fn ddiv(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if data == 0 && !is_float(reg0) {
+            return self.fault(VmError::DivideByZero);
+        }
+        let (val, val2, val3, val4) = self.get_vals(reg1);
+        match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = self.r0 + 1,
-            Register::R1 => self.r1 = self.r1 + 1,
-            Register::R2 => self.r2 = self.r2 + 1,
-            Register::F0 => self.f0 = ((self.f0 as f64) + 1.0).to_bits(),
-            Register::F1 => self.f1 = ((self.f1 as f64) + 1.0).to_bits(),
-            Register::F2 => self.f2 = ((self.f0 as f64) + 1.0).to_bits(),
-            Register::P0 => self.p0 = self.p0 + 1,
-            Register::P1 => self.p1 = self.p1 + 1,
-            Register::P2 => self.p2 = self.p2 + 1,
+            Register::R0 => self.r0 = val / data,
+            Register::R1 => self.r1 = val / data,
+            Register::R2 => self.r2 = val / data,
+            Register::F0 => self.f0 = self.fop(FloatOp::Div, val, (data as f64).to_bits()),
+            Register::F1 => self.f1 = self.fop(FloatOp::Div, val, (data as f64).to_bits()),
+            Register::F2 => self.f2 = self.fop(FloatOp::Div, val, (data as f64).to_bits()),
+            Register::P0 => self.p0 = val / data,
+            Register::P1 => self.p1 = val / data,
+            Register::P2 => self.p2 = val / data,
             Register::S0 => {
-                self.s0[0] += 1;
-                self.s0[1] += 1;
+                self.s0[0] = val / data;
+                self.s0[1] = val2 / data;
             }
             Register::S1 => {
-                self.s1[0] += 1;
-                self.s1[1] += 1;
+                self.s1[0] = val / data;
+                self.s1[1] = val2 / data;
             }
             Register::S2 => {
-                self.s2[0] += 1;
-                self.s2[1] += 1;
+                self.s2[0] = val / data;
+                self.s2[1] = val2 / data;
             }
             Register::X0 => {
-                self.x0[0] += 1;
-                self.x0[1] += 1;
-                self.x0[2] += 1;
-                self.x0[3] += 1;
+                self.x0[0] = val / data;
+                self.x0[1] = val2 / data;
+                self.x0[2] = val3 / data;
+                self.x0[3] = val4 / data;
             }
             Register::X1 => {
-                self.x1[0] += 1;
-                self.x1[1] += 1;
-                self.x1[2] += 1;
-                self.x1[3] += 1;
+                self.x1[0] = val / data;
+                self.x1[1] = val2 / data;
+                self.x1[2] = val3 / data;
+                self.x1[3] = val4 / data;
             }
             Register::X2 => {
-                self.x2[0] += 1;
-                self.x2[1] += 1;
-                self.x2[2] += 1;
-                self.x2[3] += 1;
+                self.x2[0] = val / data;
+                self.x2[1] = val2 / data;
+                self.x2[2] = val3 / data;
+                self.x2[3] = val4 / data;
             }
         };
+    }
+    fn dmod(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if data == 0 && !is_float(reg0) {
+            return self.fault(VmError::DivideByZero);
+        }
+        let (val, val2, val3, val4) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = self.r0 + 1,
-            Register::R1 => self.r1 = self.r1 + 1,
-            Register::R2 => self.r2 = self.r2 + 1,
-            Register::F0 => self.f0 = ((self.f0 as f64) + 1.0).to_bits(),
-            Register::F1 => self.f1 = ((self.f1 as f64) + 1.0).to_bits(),
-            Register::F2 => self.f2 = ((self.f0 as f64) + 1.0).to_bits(),
-            Register::P0 => self.p0 = self.p0 + 1,
-            Register::P1 => self.p1 = self.p1 + 1,
-            Register::P2 => self.p2 = self.p2 + 1,
+            Register::R0 => self.r0 = val % data,
+            Register::R1 => self.r1 = val % data,
+            Register::R2 => self.r2 = val % data,
+            Register::F0 => self.f0 = self.fop(FloatOp::Rem, val, (data as f64).to_bits()),
+            Register::F1 => self.f1 = self.fop(FloatOp::Rem, val, (data as f64).to_bits()),
+            Register::F2 => self.f2 = self.fop(FloatOp::Rem, val, (data as f64).to_bits()),
+            Register::P0 => self.p0 = val % data,
+            Register::P1 => self.p1 = val % data,
+            Register::P2 => self.p2 = val % data,
             Register::S0 => {
-                self.s0[0] += 1;
-                self.s0[1] += 1;
+                self.s0[0] = val % data;
+                self.s0[1] = val2 % data;
             }
             Register::S1 => {
-                self.s1[0] += 1;
-                self.s1[1] += 1;
+                self.s1[0] = val % data;
+                self.s1[1] = val2 % data;
             }
             Register::S2 => {
-                self.s2[0] += 1;
-                self.s2[1] += 1;
+                self.s2[0] = val % data;
+                self.s2[1] = val2 % data;
             }
             Register::X0 => {
-                self.x0[0] += 1;
-                self.x0[1] += 1;
-                self.x0[2] += 1;
-                self.x0[3] += 1;
+                self.x0[0] = val % data;
+                self.x0[1] = val2 % data;
+                self.x0[2] = val3 % data;
+                self.x0[3] = val4 % data;
             }
             Register::X1 => {
-                self.x1[0] += 1;
-                self.x1[1] += 1;
-                self.x1[2] += 1;
-                self.x1[3] += 1;
+                self.x1[0] = val % data;
+                self.x1[1] = val2 % data;
+                self.x1[2] = val3 % data;
+                self.x1[3] = val4 % data;
             }
             Register::X2 => {
-                self.x2[0] += 1;
-                self.x2[1] += 1;
-                self.x2[2] += 1;
-                self.x2[3] += 1;
+                self.x2[0] = val % data;
+                self.x2[1] = val2 % data;
+                self.x2[2] = val3 % data;
+                self.x2[3] = val4 % data;
             }
         };
-        self.jump_op(data);
     }
-    fn dec(&mut self, reg0: u8, reg1: u8, data: u16) {
+    fn dinc(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (a, one) = if is_float(reg0) {
+            ((data as f64).to_bits(), 1f64.to_bits())
+        } else {
+            (data, 1)
+        };
+        if !self.arith(self.overflow, IntOp::Add, reg0, [a; 4], [one; 4]) {
+            return;
+        }
         match Register::from(reg1) {
             Register::ZR => {}
-            Register::R0 => self.r0 = self.r0 + 1,
-            Register::R1 => self.r1 = self.r1 + 1,
-            Register::R2 => self.r2 = self.r2 + 1,
-            Register::F0 => self.f0 = ((self.f0 as f64) + 1.0).to_bits(),
-            Register::F1 => self.f1 = ((self.f1 as f64) + 1.0).to_bits(),
-            Register::F2 => self.f2 = ((self.f0 as f64) + 1.0).to_bits(),
-            Register::P0 => self.p0 = self.p0 + 1,
-            Register::P1 => self.p1 = self.p1 + 1,
-            Register::P2 => self.p2 = self.p2 + 1,
+            Register::R0 => self.r0 = data,
+            Register::R1 => self.r1 = data,
+            Register::R2 => self.r2 = data,
+            Register::F0 => self.f0 = (data as f64).to_bits(),
+            Register::F1 => self.f1 = (data as f64).to_bits(),
+            Register::F2 => self.f2 = (data as f64).to_bits(),
+            Register::P0 => self.p0 = data,
+            Register::P1 => self.p1 = data,
+            Register::P2 => self.p2 = data,
             Register::S0 => {
-                self.s0[0] += 1;
-                self.s0[1] += 1;
+                self.s0[0] = data;
+                self.s0[1] = data;
             }
             Register::S1 => {
-                self.s1[0] += 1;
-                self.s1[1] += 1;
+                self.s1[0] = data;
+                self.s1[1] = data;
             }
             Register::S2 => {
-                self.s2[0] += 1;
-                self.s2[1] += 1;
+                self.s2[0] = data;
+                self.s2[1] = data;
             }
             Register::X0 => {
-                self.x0[0] += 1;
-                self.x0[1] += 1;
-                self.x0[2] += 1;
-                self.x0[3] += 1;
+                self.x0[0] = data;
+                self.x0[1] = data;
+                self.x0[2] = data;
+                self.x0[3] = data;
             }
             Register::X1 => {
-                self.x1[0] += 1;
-                self.x1[1] += 1;
-                self.x1[2] += 1;
-                self.x1[3] += 1;
+                self.x1[0] = data;
+                self.x1[1] = data;
+                self.x1[2] = data;
+                self.x1[3] = data;
             }
             Register::X2 => {
-                self.x2[0] += 1;
-                self.x2[1] += 1;
-                self.x2[2] += 1;
-                self.x2[3] += 1;
+                self.x2[0] = data;
+                self.x2[1] = data;
+                self.x2[2] = data;
+                self.x2[3] = data;
             }
         };
-        match Register::from(reg0) {
+    }
+    fn ddec(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (a, one) = if is_float(reg0) {
+            ((data as f64).to_bits(), 1f64.to_bits())
+        } else {
+            (data, 1)
+        };
+        if !self.arith(self.overflow, IntOp::Sub, reg0, [a; 4], [one; 4]) {
+            return;
+        }
+        match Register::from(reg1) {
             Register::ZR => {}
-            Register::R0 => self.r0 = self.r0 - 1,
-            Register::R1 => self.r1 = self.r1 - 1,
-            Register::R2 => self.r2 = self.r2 - 1,
-            Register::F0 => self.f0 = ((self.f0 as f64) - 1.0).to_bits(),
-            Register::F1 => self.f1 = ((self.f1 as f64) - 1.0).to_bits(),
-            Register::F2 => self.f2 = ((self.f0 as f64) - 1.0).to_bits(),
-            Register::P0 => self.p0 = self.p0 - 1,
-            Register::P1 => self.p1 = self.p1 - 1,
-            Register::P2 => self.p2 = self.p2 - 1,
+            Register::R0 => self.r0 = data,
+            Register::R1 => self.r1 = data,
+            Register::R2 => self.r2 = data,
+            Register::F0 => self.f0 = (data as f64).to_bits(),
+            Register::F1 => self.f1 = (data as f64).to_bits(),
+            Register::F2 => self.f2 = (data as f64).to_bits(),
+            Register::P0 => self.p0 = data,
+            Register::P1 => self.p1 = data,
+            Register::P2 => self.p2 = data,
             Register::S0 => {
-                self.s0[0] -= 1;
-                self.s0[1] -= 1;
+                self.s0[0] = data;
+                self.s0[1] = data;
             }
             Register::S1 => {
-                self.s1[0] -= 1;
-                self.s1[1] -= 1;
+                self.s1[0] = data;
+                self.s1[1] = data;
             }
             Register::S2 => {
-                self.s2[0] -= 1;
-                self.s2[1] -= 1;
+                self.s2[0] = data;
+                self.s2[1] = data;
             }
             Register::X0 => {
-                self.x0[0] -= 1;
-                self.x0[1] -= 1;
-                self.x0[2] -= 1;
-                self.x0[3] -= 1;
+                self.x0[0] = data;
+                self.x0[1] = data;
+                self.x0[2] = data;
+                self.x0[3] = data;
             }
             Register::X1 => {
-                self.x1[0] -= 1;
-                self.x1[1] -= 1;
-                self.x1[2] -= 1;
-                self.x1[3] -= 1;
+                self.x1[0] = data;
+                self.x1[1] = data;
+                self.x1[2] = data;
+                self.x1[3] = data;
             }
             Register::X2 => {
-                self.x2[0] -= 1;
-                self.x2[1] -= 1;
-                self.x2[2] -= 1;
-                self.x2[3] -= 1;
+                self.x2[0] = data;
+                self.x2[1] = data;
+                self.x2[2] = data;
+                self.x2[3] = data;
             }
         };
-        self.jump_op(data);
     }
-    fn neg(&mut self, reg0: u8, reg1: u8, data: u16) {
-        match Register::from(reg1) {
+    fn dneg(&mut self, reg0: u8, reg1: u8, data: u64) {
+        match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = self.r0.wrapping_neg(),
-            Register::R1 => self.r1 = self.r1.wrapping_neg(),
-            Register::R2 => self.r2 = self.r2.wrapping_neg(),
-            Register::F0 => self.f0 = (-(self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = (-(self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = (-(self.f0 as f64)).to_bits(),
-            Register::P0 => self.p0 = self.p0.wrapping_neg(),
-            Register::P1 => self.p1 = self.p1.wrapping_neg(),
-            Register::P2 => self.p2 = self.p2.wrapping_neg(),
+            Register::R0 => self.r0 = data.wrapping_neg(),
+            Register::R1 => self.r1 = data.wrapping_neg(),
+            Register::R2 => self.r2 = data.wrapping_neg(),
+            Register::F0 => self.f0 = (-(data as f64)).to_bits(),
+            Register::F1 => self.f1 = (-(data as f64)).to_bits(),
+            Register::F2 => self.f2 = (-(data as f64)).to_bits(),
+            Register::P0 => self.p0 = data.wrapping_neg(),
+            Register::P1 => self.p1 = data.wrapping_neg(),
+            Register::P2 => self.p2 = data.wrapping_neg(),
             Register::S0 => {
-                self.s0[0] = self.s0[0].wrapping_neg();
-                self.s0[1] = self.s0[1].wrapping_neg();
+                self.s0[0] = data.wrapping_neg();
+                self.s0[1] = data.wrapping_neg();
             }
             Register::S1 => {
-                self.s1[0] = self.s1[0].wrapping_neg();
-                self.s1[1] = self.s1[1].wrapping_neg();
+                self.s1[0] = data.wrapping_neg();
+                self.s1[1] = data.wrapping_neg();
             }
             Register::S2 => {
-                self.s2[0] = self.s2[0].wrapping_neg();
-                self.s2[1] = self.s2[1].wrapping_neg();
+                self.s2[0] = data.wrapping_neg();
+                self.s2[1] = data.wrapping_neg();
             }
             Register::X0 => {
-                self.x0[0] = self.x0[0].wrapping_neg();
-                self.x0[1] = self.x0[1].wrapping_neg();
-                self.x0[2] = self.x0[2].wrapping_neg();
-                self.x0[3] = self.x0[3].wrapping_neg();
+                self.x0[0] = data.wrapping_neg();
+                self.x0[1] = data.wrapping_neg();
+                self.x0[2] = data.wrapping_neg();
+                self.x0[3] = data.wrapping_neg();
             }
             Register::X1 => {
-                self.x1[0] = self.x1[0].wrapping_neg();
-                self.x1[1] = self.x1[1].wrapping_neg();
-                self.x1[2] = self.x1[2].wrapping_neg();
-                self.x1[3] = self.x1[3].wrapping_neg();
+                self.x1[0] = data.wrapping_neg();
+                self.x1[1] = data.wrapping_neg();
+                self.x1[2] = data.wrapping_neg();
+                self.x1[3] = data.wrapping_neg();
             }
             Register::X2 => {
-                self.x2[0] = self.x2[0].wrapping_neg();
-                self.x2[1] = self.x2[1].wrapping_neg();
-                self.x2[2] = self.x2[2].wrapping_neg();
-                self.x2[3] = self.x2[3].wrapping_neg();
+                self.x2[0] = data.wrapping_neg();
+                self.x2[1] = data.wrapping_neg();
+                self.x2[2] = data.wrapping_neg();
+                self.x2[3] = data.wrapping_neg();
             }
         };
-        match Register::from(reg0) {
+        match Register::from(reg1) {
             Register::ZR => {}
-            Register::R0 => self.r0 = self.r0.wrapping_neg(),
-            Register::R1 => self.r1 = self.r1.wrapping_neg(),
-            Register::R2 => self.r2 = self.r2.wrapping_neg(),
-            Register::F0 => self.f0 = (-(self.f0 as f64)).to_bits(),
-            Register::F1 => self.f1 = (-(self.f1 as f64)).to_bits(),
-            Register::F2 => self.f2 = (-(self.f0 as f64)).to_bits(),
-            Register::P0 => self.p0 = self.p0.wrapping_neg(),
-            Register::P1 => self.p1 = self.p1.wrapping_neg(),
-            Register::P2 => self.p2 = self.p2.wrapping_neg(),
+            Register::R0 => self.r0 = data,
+            Register::R1 => self.r1 = data,
+            Register::R2 => self.r2 = data,
+            Register::F0 => self.f0 = (data as f64).to_bits(),
+            Register::F1 => self.f1 = (data as f64).to_bits(),
+            Register::F2 => self.f2 = (data as f64).to_bits(),
+            Register::P0 => self.p0 = data,
+            Register::P1 => self.p1 = data,
+            Register::P2 => self.p2 = data,
             Register::S0 => {
-                self.s0[0] = self.s0[0].wrapping_neg();
-                self.s0[1] = self.s0[1].wrapping_neg();
+                self.s0[0] = data;
+                self.s0[1] = data;
             }
             Register::S1 => {
-                self.s1[0] = self.s1[0].wrapping_neg();
-                self.s1[1] = self.s1[1].wrapping_neg();
+                self.s1[0] = data;
+                self.s1[1] = data;
             }
             Register::S2 => {
-                self.s2[0] = self.s2[0].wrapping_neg();
-                self.s2[1] = self.s2[1].wrapping_neg();
+                self.s2[0] = data;
+                self.s2[1] = data;
             }
             Register::X0 => {
-                self.x0[0] = self.x0[0].wrapping_neg();
-                self.x0[1] = self.x0[1].wrapping_neg();
-                self.x0[2] = self.x0[2].wrapping_neg();
-                self.x0[3] = self.x0[3].wrapping_neg();
+                self.x0[0] = data;
+                self.x0[1] = data;
+                self.x0[2] = data;
+                self.x0[3] = data;
             }
             Register::X1 => {
-                self.x1[0] = self.x1[0].wrapping_neg();
-                self.x1[1] = self.x1[1].wrapping_neg();
-                self.x1[2] = self.x1[2].wrapping_neg();
-                self.x1[3] = self.x1[3].wrapping_neg();
+                self.x1[0] = data;
+                self.x1[1] = data;
+                self.x1[2] = data;
+                self.x1[3] = data;
             }
             Register::X2 => {
-                self.x2[0] = self.x2[0].wrapping_neg();
-                self.x2[1] = self.x2[1].wrapping_neg();
-                self.x2[2] = self.x2[2].wrapping_neg();
-                self.x2[3] = self.x2[3].wrapping_neg();
+                self.x2[0] = data;
+                self.x2[1] = data;
+                self.x2[2] = data;
+                self.x2[3] = data;
             }
         };
-        self.jump_op(data);
     }
-    fn dadd(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg1);
+    fn swap(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (val, val2, val3, val4) = self.get_vals(reg0);
+        let (val5, val6, val7, val8) = self.get_vals(reg1);
         match Register::from(reg0) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val + (data as u64),
-            Register::R1 => self.r1 = val + (data as u64),
-            Register::R2 => self.r2 = val + (data as u64),
-            Register::F0 => self.f0 = ((val as f64) + (data as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) + (data as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) + (data as f64)).to_bits(),
-            Register::P0 => self.p0 = val + (data as u64),
-            Register::P1 => self.p1 = val + (data as u64),
-            Register::P2 => self.p2 = val + (data as u64),
+            Register::R0 => self.r0 = val5,
+            Register::R1 => self.r1 = val5,
+            Register::R2 => self.r2 = val5,
+            Register::F0 => self.f0 = val5,
+            Register::F1 => self.f1 = val5,
+            Register::F2 => self.f2 = val5,
+            Register::P0 => self.p0 = val5,
+            Register::P1 => self.p1 = val5,
+            Register::P2 => self.p2 = val5,
             Register::S0 => {
-                self.s0[0] = val + (data as u64);
-                self.s0[1] = val2 + (data as u64);
+                self.s0[0] = val5;
+                self.s0[1] = val6;
             }
             Register::S1 => {
-                self.s1[0] = val + (data as u64);
-                self.s1[1] = val2 + (data as u64);
+                self.s1[0] = val5;
+                self.s1[1] = val6;
             }
             Register::S2 => {
-                self.s2[0] = val + (data as u64);
-                self.s2[1] = val2 + (data as u64);
+                self.s2[0] = val5;
+                self.s2[1] = val6;
             }
             Register::X0 => {
-                self.x0[0] = val + (data as u64);
-                self.x0[1] = val2 + (data as u64);
-                self.x0[2] = val3 + (data as u64);
-                self.x0[3] = val4 + (data as u64);
+                self.x0[0] = val5;
+                self.x0[1] = val6;
+                self.x0[2] = val7;
+                self.x0[3] = val8;
             }
             Register::X1 => {
-                self.x1[0] = val + (data as u64);
-                self.x1[1] = val2 + (data as u64);
-                self.x1[2] = val3 + (data as u64);
-                self.x1[3] = val4 + (data as u64);
+                self.x1[0] = val5;
+                self.x1[1] = val6;
+                self.x1[2] = val7;
+                self.x1[3] = val8;
             }
             Register::X2 => {
-                self.x2[0] = val + (data as u64);
-                self.x2[1] = val2 + (data as u64);
-                self.x2[2] = val3 + (data as u64);
-                self.x2[3] = val4 + (data as u64);
+                self.x2[0] = val5;
+                self.x2[1] = val6;
+                self.x2[2] = val7;
+                self.x2[3] = val8;
             }
         };
-    }
-    fn dsub(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
+        match Register::from(reg1) {
             Register::ZR => {}
-            Register::R0 => self.r0 = val - (data as u64),
-            Register::R1 => self.r1 = val - (data as u64),
-            Register::R2 => self.r2 = val - (data as u64),
-            Register::F0 => self.f0 = ((val as f64) - (data as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) - (data as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) - (data as f64)).to_bits(),
-            Register::P0 => self.p0 = val - (data as u64),
-            Register::P1 => self.p1 = val - (data as u64),
-            Register::P2 => self.p2 = val - (data as u64),
+            Register::R0 => self.r0 = val,
+            Register::R1 => self.r1 = val,
+            Register::R2 => self.r2 = val,
+            Register::F0 => self.f0 = val,
+            Register::F1 => self.f1 = val,
+            Register::F2 => self.f2 = val,
+            Register::P0 => self.p0 = val,
+            Register::P1 => self.p1 = val,
+            Register::P2 => self.p2 = val,
             Register::S0 => {
-                self.s0[0] = val - (data as u64);
-                self.s0[1] = val2 - (data as u64);
+                self.s0[0] = val;
+                self.s0[1] = val2;
             }
             Register::S1 => {
-                self.s1[0] = val - (data as u64);
-                self.s1[1] = val2 - (data as u64);
+                self.s1[0] = val;
+                self.s1[1] = val2;
             }
             Register::S2 => {
-                self.s2[0] = val - (data as u64);
-                self.s2[1] = val2 - (data as u64);
+                self.s2[0] = val;
+                self.s2[1] = val2;
             }
             Register::X0 => {
-                self.x0[0] = val - (data as u64);
-                self.x0[1] = val2 - (data as u64);
-                self.x0[2] = val3 - (data as u64);
-                self.x0[3] = val4 - (data as u64);
+                self.x0[0] = val;
+                self.x0[1] = val2;
+                self.x0[2] = val3;
+                self.x0[3] = val4;
             }
             Register::X1 => {
-                self.x1[0] = val - (data as u64);
-                self.x1[1] = val2 - (data as u64);
-                self.x1[2] = val3 - (data as u64);
-                self.x1[3] = val4 - (data as u64);
+                self.x1[0] = val;
+                self.x1[1] = val2;
+                self.x1[2] = val3;
+                self.x1[3] = val4;
             }
             Register::X2 => {
-                self.x2[0] = val - (data as u64);
-                self.x2[1] = val2 - (data as u64);
-                self.x2[2] = val3 - (data as u64);
-                self.x2[3] = val4 - (data as u64);
+                self.x2[0] = val;
+                self.x2[1] = val2;
+                self.x2[2] = val3;
+                self.x2[3] = val4;
             }
         };
+        self.jump_op(data);
     }
-    fn dmul(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = val * (data as u64),
-            Register::R1 => self.r1 = val * (data as u64),
-            Register::R2 => self.r2 = val * (data as u64),
-            Register::F0 => self.f0 = ((val as f64) * (data as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) * (data as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) * (data as f64)).to_bits(),
-            Register::P0 => self.p0 = val * (data as u64),
-            Register::P1 => self.p1 = val * (data as u64),
-            Register::P2 => self.p2 = val * (data as u64),
-            Register::S0 => {
-                self.s0[0] = val * (data as u64);
-                self.s0[1] = val2 * (data as u64);
-            }
-            Register::S1 => {
-                self.s1[0] = val * (data as u64);
-                self.s1[1] = val2 * (data as u64);
-            }
-            Register::S2 => {
-                self.s2[0] = val * (data as u64);
-                self.s2[1] = val2 * (data as u64);
-            }
-            Register::X0 => {
-                self.x0[0] = val * (data as u64);
-                self.x0[1] = val2 * (data as u64);
-                self.x0[2] = val3 * (data as u64);
-                self.x0[3] = val4 * (data as u64);
-            }
-            Register::X1 => {
-                self.x1[0] = val * (data as u64);
-                self.x1[1] = val2 * (data as u64);
-                self.x1[2] = val3 * (data as u64);
-                self.x1[3] = val4 * (data as u64);
-            }
-            Register::X2 => {
-                self.x2[0] = val * (data as u64);
-                self.x2[1] = val2 * (data as u64);
-                self.x2[2] = val3 * (data as u64);
-                self.x2[3] = val4 * (data as u64);
-            }
+    fn itof(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        self.convert(reg0, reg1, |v| {
+            let (val, flags) = float::from_int(v as i64 as i128, rm);
+            (val.to_bits(), flags)
+        });
+        self.jump_op(data);
+    }
+    fn utof(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        self.convert(reg0, reg1, |v| {
+            let (val, flags) = float::from_int(v as i128, rm);
+            (val.to_bits(), flags)
+        });
+        self.jump_op(data);
+    }
+    fn ftoi(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.convert(reg0, reg1, |v| {
+            float::to_int(f64::from_bits(v), RoundingMode::TowardZero, true)
+        });
+        self.jump_op(data);
+    }
+    fn ftou(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.convert(reg0, reg1, |v| {
+            float::to_int(f64::from_bits(v), RoundingMode::TowardZero, false)
+        });
+        self.jump_op(data);
+    }
+    fn rftoi(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        self.convert(reg0, reg1, |v| float::to_int(f64::from_bits(v), rm, true));
+        self.jump_op(data);
+    }
+    fn rftou(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        self.convert(reg0, reg1, |v| float::to_int(f64::from_bits(v), rm, false));
+        self.jump_op(data);
+    }
+    fn adds(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.single(FloatOp::Add, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn subs(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.single(FloatOp::Sub, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn muls(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.single(FloatOp::Mul, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn divs(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.single(FloatOp::Div, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fcvts(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        self.convert(reg0, reg1, |v| {
+            let (val, flags) = float::narrow64(f64::from_bits(v), rm);
+            (val.to_bits() as u64, flags)
+        });
+        self.jump_op(data);
+    }
+    fn fcvtd(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.convert(reg0, reg1, |v| {
+            let val = f32::from_bits(v as u32);
+            //Widening is exact, only signaling NaNs raise a flag
+            let flags = if val.is_nan() && val.to_bits() & (1 << 22) == 0 {
+                float::FLAG_INVALID
+            } else {
+                0
+            };
+            (float::canonical64(val as f64).to_bits(), flags)
+        });
+        self.jump_op(data);
+    }
+    fn rfcsr(&mut self, reg0: u8, _reg1: u8, data: u64) {
+        self.set_vals(reg0, (self.fcsr, 0, 0, 0));
+        self.jump_op(data);
+    }
+    fn wfcsr(&mut self, reg0: u8, _reg1: u8, data: u64) {
+        self.fcsr = self.get_vals(reg0).0 & (FLAGS | (0b11 << RM_SHIFT));
+        self.jump_op(data);
+    }
+    fn dfrm(&mut self, _reg0: u8, _reg1: u8, data: u64) {
+        self.fcsr &= !(0b11 << RM_SHIFT);
+        self.fcsr |= (data & 0b11) << RM_SHIFT;
+    }
+    fn dfclr(&mut self, reg0: u8, _reg1: u8, data: u64) {
+        self.set_vals(reg0, (self.fcsr & FLAGS, 0, 0, 0));
+        self.fcsr &= !(data & FLAGS);
+    }
+    fn sqrt(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Sqrt, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn sqrts(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Sqrt, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fabs(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Abs, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fabss(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Abs, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn floor(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Floor, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn floors(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Floor, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn ceil(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Ceil, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn ceils(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Ceil, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn round(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Round, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn rounds(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Round, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn sin(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Sin, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn sins(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Sin, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn cos(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Cos, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn coss(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Cos, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn exp(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Exp, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn exps(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Exp, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn log(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Log, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn logs(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Log, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fmin(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Min, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fmins(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Min, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fmax(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Max, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fmaxs(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Max, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn copysign(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Copysign, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn copysigns(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Copysign, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn pow(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.math(MathOp::Pow, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn pows(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.maths(MathOp::Pow, reg0, reg1);
+        self.jump_op(data);
+    }
+    fn fma(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        let (c, c2, c3, c4) = self.get_vals(data as u8);
+        let c = [c, c2, c3, c4];
+        let mut lane = 0;
+        self.lanes64(reg0, reg1, |a, b| {
+            let (val, flags) = float::fma64(
+                f64::from_bits(a),
+                f64::from_bits(b),
+                f64::from_bits(c[lane]),
+                rm,
+            );
+            lane += 1;
+            (val.to_bits(), flags)
+        });
+    }
+    fn fmas(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let rm = self.rounding_mode();
+        let c = split32(self.get_vals(data as u8));
+        let mut lane = 0;
+        self.lanes32(reg0, reg1, |a, b| {
+            let (val, flags) = float::fma32(
+                f32::from_bits(a),
+                f32::from_bits(b),
+                f32::from_bits(c[lane]),
+                rm,
+            );
+            lane += 1;
+            (val.to_bits(), flags)
+        });
+    }
+    //Lane type, half and mask come from data, S/X registers hold 16/32 byte vectors
+    fn vector(&mut self, op: VectorOp, reg0: u8, reg1: u8, data: u64) {
+        let (a, b) = (self.get_bytes(reg0), self.get_bytes(reg1));
+        let ty = LaneType::from(data as u16);
+        let (out, flags) = vector::binary(
+            op,
+            ty,
+            data as u16 & LANE_HIGH != 0,
+            &a,
+            &b,
+            width(reg0) * 8,
+            self.rounding_mode(),
+        );
+        self.fcsr |= flags;
+        //Widening ops are masked by their result lanes
+        let bits = if op.widening() && ty != LaneType::F64 {
+            ty.bits() * 2
+        } else {
+            ty.bits()
         };
+        self.set_masked(reg0, data, bits, &a, &out);
     }
-    fn ddiv(&mut self, reg0: u8, reg1: u8, data: u16) {
-        if data == 0 {
-            return;
+    fn compare(&mut self, op: CompareOp, reg0: u8, reg1: u8, data: u64) {
+        let (a, b) = (self.get_bytes(reg0), self.get_bytes(reg1));
+        let ty = LaneType::from(data as u16);
+        let (out, flags) = vector::compare(op, ty, &a, &b, width(reg0) * 8);
+        self.fcsr |= flags;
+        self.set_masked(reg0, data, ty.bits(), &a, &out);
+    }
+    //Masked move, inactive lanes keep reg0 or are zeroed
+    fn blend(&mut self, reg0: u8, reg1: u8, data: u64, zero: bool) {
+        let old = if zero {
+            [0u8; 32]
+        } else {
+            self.get_bytes(reg0)
+        };
+        let b = self.get_bytes(reg1);
+        self.set_masked(reg0, data, LaneType::from(data as u16).bits(), &old, &b);
+    }
+    //Writes the active lanes of new (all of them without a mask register) and old elsewhere
+    fn set_masked(&mut self, reg0: u8, data: u64, bits: usize, old: &Bytes, new: &Bytes) {
+        let out = match lane::mask_register(data as u16) {
+            0 => *new,
+            mask => vector::select(bits, &self.get_bytes(mask), old, new, width(reg0) * 8),
+        };
+        self.set_bytes(reg0, out);
+    }
+    fn vshuf(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let a = self.get_bytes(reg1);
+        let pattern = (data >> LANE_IMM_SHIFT) as u8;
+        let out = vector::shuffle(LaneType::from(data as u16), &a, width(reg0) * 8, pattern);
+        self.set_bytes(reg0, out);
+    }
+    fn vperm(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (index, table) = (self.get_bytes(reg0), self.get_bytes(reg1));
+        let out = vector::permute(LaneType::from(data as u16), &table, &index, width(reg0) * 8);
+        self.set_bytes(reg0, out);
+    }
+    //Out of range lane indices fault
+    fn vins(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (ty, index) = (
+            LaneType::from(data as u16),
+            (data >> LANE_IMM_SHIFT) as usize,
+        );
+        let val = vector::read(&self.get_bytes(reg1), ty.bits(), 0);
+        self.insert(reg0, ty.bits(), index, val);
+    }
+    fn vext(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (ty, index) = (
+            LaneType::from(data as u16),
+            (data >> LANE_IMM_SHIFT) as usize,
+        );
+        if let Some(val) = self.extract(reg1, ty.bits(), index) {
+            self.set_vals(reg0, (val as u64, 0, 0, 0));
         }
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = val / (data as u64),
-            Register::R1 => self.r1 = val / (data as u64),
-            Register::R2 => self.r2 = val / (data as u64),
-            Register::F0 => self.f0 = ((val as f64) / (data as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) / (data as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) / (data as f64)).to_bits(),
-            Register::P0 => self.p0 = val / (data as u64),
-            Register::P1 => self.p1 = val / (data as u64),
-            Register::P2 => self.p2 = val / (data as u64),
-            Register::S0 => {
-                self.s0[0] = val / (data as u64);
-                self.s0[1] = val2 / (data as u64);
-            }
-            Register::S1 => {
-                self.s1[0] = val / (data as u64);
-                self.s1[1] = val2 / (data as u64);
-            }
-            Register::S2 => {
-                self.s2[0] = val / (data as u64);
-                self.s2[1] = val2 / (data as u64);
-            }
-            Register::X0 => {
-                self.x0[0] = val / (data as u64);
-                self.x0[1] = val2 / (data as u64);
-                self.x0[2] = val3 / (data as u64);
-                self.x0[3] = val4 / (data as u64);
-            }
-            Register::X1 => {
-                self.x1[0] = val / (data as u64);
-                self.x1[1] = val2 / (data as u64);
-                self.x1[2] = val3 / (data as u64);
-                self.x1[3] = val4 / (data as u64);
-            }
-            Register::X2 => {
-                self.x2[0] = val / (data as u64);
-                self.x2[1] = val2 / (data as u64);
-                self.x2[2] = val3 / (data as u64);
-                self.x2[3] = val4 / (data as u64);
+    }
+    //Writes lane index of reg, an index past the last lane faults and leaves reg unchanged
+    fn insert(&mut self, reg: u8, bits: usize, index: usize, val: u128) {
+        if self.lane_in_range(reg, bits, index) {
+            let mut out = self.get_bytes(reg);
+            vector::write(&mut out, bits, index, val);
+            self.set_bytes(reg, out);
+        }
+    }
+    fn extract(&mut self, reg: u8, bits: usize, index: usize) -> Option<u128> {
+        self.lane_in_range(reg, bits, index)
+            .then(|| vector::read(&self.get_bytes(reg), bits, index))
+    }
+    //zr has the lanes of a scalar register
+    fn lane_in_range(&mut self, reg: u8, bits: usize, index: usize) -> bool {
+        let lanes = width(reg).max(1) * 64 / bits;
+        if index >= lanes {
+            self.fault(VmError::LaneIndex { index, lanes });
+        }
+        index < lanes
+    }
+    fn vbcast(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let ty = LaneType::from(data as u16);
+        let val = vector::read(&self.get_bytes(reg1), ty.bits(), 0);
+        self.set_bytes(reg0, vector::broadcast(ty, val, width(reg0) * 8));
+    }
+    fn zip(&mut self, reg0: u8, reg1: u8, data: u64, high: bool) {
+        let (a, b) = (self.get_bytes(reg0), self.get_bytes(reg1));
+        let out = vector::zip(LaneType::from(data as u16), &a, &b, width(reg0) * 8, high);
+        self.set_bytes(reg0, out);
+    }
+    fn unzip(&mut self, reg0: u8, reg1: u8, data: u64, odd: bool) {
+        let (a, b) = (self.get_bytes(reg0), self.get_bytes(reg1));
+        let out = vector::unzip(LaneType::from(data as u16), &a, &b, width(reg0) * 8, odd);
+        self.set_bytes(reg0, out);
+    }
+    //Horizontal reduction of reg1 into the low lane of reg0
+    fn reduce(&mut self, op: ReduceOp, reg0: u8, reg1: u8, data: u64) {
+        let a = self.get_bytes(reg1);
+        let (val, flags) = vector::reduce(
+            op,
+            LaneType::from(data as u16),
+            &a,
+            width(reg1) * 8,
+            self.rounding_mode(),
+        );
+        self.fcsr |= flags;
+        self.set_vals(reg0, (val, 0, 0, 0));
+    }
+    //A faulting load leaves reg0 unchanged
+    fn vload(&mut self, mode: Addressing, reg0: u8, reg1: u8, data: u64) {
+        let size = LaneType::from(data as u16).bits() / 8;
+        let mut out = self.get_bytes(reg0);
+        for (k, address) in self.addresses(mode, reg0, reg1, data) {
+            match self.mem.read(address, size) {
+                Ok(bytes) => out[k * size..(k + 1) * size].copy_from_slice(&bytes),
+                Err(_) => return self.fault(VmError::LaneFault { lane: k, address }),
             }
+        }
+        self.set_bytes(reg0, out);
+    }
+    //Every lane is checked before writing, so a faulting store leaves memory unchanged
+    fn vstore(&mut self, mode: Addressing, reg0: u8, reg1: u8, data: u64) {
+        let size = LaneType::from(data as u16).bits() / 8;
+        let lanes = self.addresses(mode, reg0, reg1, data);
+        if let Some(&(k, address)) = lanes
+            .iter()
+            .find(|(_, address)| self.mem.check(*address, size).is_err())
+        {
+            return self.fault(VmError::LaneFault { lane: k, address });
+        }
+        let a = self.get_bytes(reg0);
+        for (k, address) in lanes {
+            let _ = self.mem.write(address, &a[k * size..(k + 1) * size]);
+        }
+    }
+    //Active lanes of reg0 and their addresses, empty after an alignment fault
+    fn addresses(&mut self, mode: Addressing, reg0: u8, reg1: u8, data: u64) -> Vec<(usize, u64)> {
+        let ty = LaneType::from(data as u16);
+        let (bits, size) = (ty.bits(), ty.bits() as u64 / 8);
+        let base = self.get_vals(reg1).0;
+        if mode == Addressing::Aligned && !base.is_multiple_of((width(reg0) as u64 * 8).max(1)) {
+            self.fault(VmError::Misaligned(base));
+            return Vec::new();
+        }
+        let imm = (data >> LANE_IMM_SHIFT) as u8;
+        let index = match mode {
+            Addressing::Indexed => self.get_bytes(imm),
+            _ => [0u8; 32],
         };
+        let mask = lane::mask_register(data as u16);
+        let active = self.get_bytes(mask);
+        (0..width(reg0) * 64 / bits)
+            .filter(|&k| mask == 0 || vector::read(&active, bits, k) != 0)
+            .map(|k| {
+                let offset = match mode {
+                    Addressing::Contiguous | Addressing::Aligned => k as u64,
+                    Addressing::Strided => (k as i64).wrapping_mul(imm as i8 as i64) as u64,
+                    Addressing::Indexed => vector::read(&index, bits, k) as u64,
+                };
+                (k, base.wrapping_add(offset.wrapping_mul(size)))
+            })
+            .collect()
+    }
+    fn fault(&mut self, err: VmError) {
+        self.fault = Some(err);
+    }
+    //Enters the handler of the fault raised by the instruction at pc, see trap
+    fn trap(&mut self, pc: u64, err: VmError) -> Result<(), VmError> {
+        if self.in_trap {
+            return Err(VmError::DoubleFault(Box::new(err)));
+        }
+        let (cause, value) = trap::cause(&err);
+        match self.enter(pc, self.pc, cause, value) {
+            true => Ok(()),
+            false => Err(err),
+        }
+    }
+    //Enters the handler of the lowest line that has one, before the instruction at pc which the
+    //handler returns to. Lines without a handler are skipped for this delivery only so they
+    //can't hold back the lines above them, they stay pending and in the guest's mask
+    fn interrupt(&mut self) {
+        let mut skip = 0;
+        while let Some(line) = self.irq.next(skip) {
+            let pc = self.pc;
+            if self.enter(pc, pc, trap::INTERRUPTS + line as u64, line as u64) {
+                return self.irq.clear(1 << line);
+            }
+            skip |= 1 << line;
+        }
+    }
+    //Whether there was a handler for the cause to enter
+    fn enter(&mut self, pc: u64, next: u64, cause: u64, value: u64) -> bool {
+        let vector = trap::VECTORS.wrapping_add(cause.wrapping_mul(8));
+        let handler = match self.mem.read(self.tvec.wrapping_add(vector), 8) {
+            Ok(bytes) if self.tvec != 0 => u64::from_le_bytes(bytes.try_into().unwrap()),
+            _ => return false,
+        };
+        if handler == 0 || self.mem.check(self.tvec, trap::VECTORS as usize).is_err() {
+            return false;
+        }
+        for (offset, val) in [
+            (trap::FRAME_PC, pc),
+            (trap::FRAME_NEXT, next),
+            (trap::FRAME_CAUSE, cause),
+            (trap::FRAME_VALUE, value),
+        ] {
+            let _ = self.mem.write(self.tvec + offset, &val.to_le_bytes());
+        }
+        self.in_trap = true;
+        self.pc = handler;
+        true
     }
-    fn dmod(&mut self, reg0: u8, reg1: u8, data: u16) {
-        if data == 0 {
+    //The third register (data) gets the carry out, wadc also takes its low bit as carry in
+    fn wadd(&mut self, reg0: u8, reg1: u8, data: u64, carry: bool) {
+        let carry = carry && self.get_vals(data as u8).0 & 1 == 1;
+        let (a, b) = (self.get_limbs(reg0), self.get_limbs(reg1));
+        let (out, carry) = wide::add(&a, &b, carry, width(reg0));
+        self.set_limbs(reg0, out);
+        self.set_vals(data as u8, (carry as u64, 0, 0, 0));
+    }
+    fn wsub(&mut self, reg0: u8, reg1: u8, data: u64, borrow: bool) {
+        let borrow = borrow && self.get_vals(data as u8).0 & 1 == 1;
+        let (a, b) = (self.get_limbs(reg0), self.get_limbs(reg1));
+        let (out, borrow) = wide::sub(&a, &b, borrow, width(reg0));
+        self.set_limbs(reg0, out);
+        self.set_vals(data as u8, (borrow as u64, 0, 0, 0));
+    }
+    //Low half to reg0, high half to the third register (data)
+    fn wmul(&mut self, reg0: u8, reg1: u8, data: u64, signed: bool) {
+        let (a, b) = (self.get_limbs(reg0), self.get_limbs(reg1));
+        let (low, high) = wide::mul(&a, &b, width(reg0), signed);
+        self.set_limbs(reg0, low);
+        self.set_limbs(data as u8, high);
+    }
+    fn wshift(&mut self, reg0: u8, reg1: u8, data: u64, op: impl Fn(&Limbs, u64, usize) -> Limbs) {
+        let a = self.get_limbs(reg0);
+        let amount = self.get_vals(reg1).0;
+        self.set_limbs(reg0, op(&a, amount, width(reg0)));
+        self.jump_op(data);
+    }
+    fn wcmp(&mut self, reg0: u8, reg1: u8, data: u64, signed: bool) {
+        let (a, b) = (self.get_limbs(reg0), self.get_limbs(reg1));
+        let ordering = wide::cmp(&a, &b, width(reg0), signed);
+        self.set_vals(data as u8, (ordering as i64 as u64, 0, 0, 0));
+    }
+    fn sdiv(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if self.divisor_zero(reg0, reg1) {
             return;
         }
-        let (val, val2, val3, val4) = self.get_vals(reg1);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = val % (data as u64),
-            Register::R1 => self.r1 = val % (data as u64),
-            Register::R2 => self.r2 = val % (data as u64),
-            Register::F0 => self.f0 = ((val as f64) % (data as f64)).to_bits(),
-            Register::F1 => self.f1 = ((val as f64) % (data as f64)).to_bits(),
-            Register::F2 => self.f2 = ((val as f64) % (data as f64)).to_bits(),
-            Register::P0 => self.p0 = val % (data as u64),
-            Register::P1 => self.p1 = val % (data as u64),
-            Register::P2 => self.p2 = val % (data as u64),
-            Register::S0 => {
-                self.s0[0] = val % (data as u64);
-                self.s0[1] = val2 % (data as u64);
-            }
-            Register::S1 => {
-                self.s1[0] = val % (data as u64);
-                self.s1[1] = val2 % (data as u64);
-            }
-            Register::S2 => {
-                self.s2[0] = val % (data as u64);
-                self.s2[1] = val2 % (data as u64);
-            }
-            Register::X0 => {
-                self.x0[0] = val % (data as u64);
-                self.x0[1] = val2 % (data as u64);
-                self.x0[2] = val3 % (data as u64);
-                self.x0[3] = val4 % (data as u64);
-            }
-            Register::X1 => {
-                self.x1[0] = val % (data as u64);
-                self.x1[1] = val2 % (data as u64);
-                self.x1[2] = val3 % (data as u64);
-                self.x1[3] = val4 % (data as u64);
-            }
-            Register::X2 => {
-                self.x2[0] = val % (data as u64);
-                self.x2[1] = val2 % (data as u64);
-                self.x2[2] = val3 % (data as u64);
-                self.x2[3] = val4 % (data as u64);
-            }
+        self.integer(reg0, reg1, |a, b| (a as i64).wrapping_div(b as i64) as u64);
+        self.jump_op(data);
+    }
+    fn smod(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if self.divisor_zero(reg0, reg1) {
+            return;
+        }
+        self.integer(reg0, reg1, |a, b| (a as i64).wrapping_rem(b as i64) as u64);
+        self.jump_op(data);
+    }
+    fn sar(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |a, b| ((a as i64) >> b.min(63)) as u64);
+        self.jump_op(data);
+    }
+    fn mulh(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |a, b| {
+            ((a as i64 as i128 * b as i64 as i128) >> 64) as u64
+        });
+        self.jump_op(data);
+    }
+    fn mulhu(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |a, b| ((a as u128 * b as u128) >> 64) as u64);
+        self.jump_op(data);
+    }
+    //Quotient to reg0, remainder to reg1
+    fn divmod(&mut self, reg0: u8, reg1: u8, data: u64, signed: bool) {
+        if self.divisor_zero(reg0, reg1) {
+            return;
+        }
+        let (a, b) = (self.get_limbs(reg0), self.get_limbs(reg1));
+        let (mut quotient, mut remainder) = ([0u64; 4], [0u64; 4]);
+        for k in 0..width(reg0) {
+            (quotient[k], remainder[k]) = if signed {
+                let (a, b) = (a[k] as i64, b[k] as i64);
+                (a.wrapping_div(b) as u64, a.wrapping_rem(b) as u64)
+            } else {
+                (a[k] / b[k], a[k] % b[k])
+            };
+        }
+        self.set_limbs(reg0, quotient);
+        self.set_limbs(reg1, remainder);
+        self.jump_op(data);
+    }
+    //Source widths other than 8/16/32 copy the whole u64
+    fn sext(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let shift = 64 - ext_bits(data);
+        self.integer(reg0, reg1, |_, b| (((b << shift) as i64) >> shift) as u64);
+    }
+    fn zext(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let shift = 64 - ext_bits(data);
+        self.integer(reg0, reg1, |_, b| (b << shift) >> shift);
+    }
+    //Whole register move, S/X included
+    fn cmov_flag(&mut self, reg0: u8, reg1: u8, data: u64, flag: u64, set: bool) {
+        if (self.flags & flag != 0) == set {
+            self.set_vals(reg0, self.get_vals(reg1));
+        }
+        self.jump_op(data);
+    }
+    //The condition register (data) is tested on its raw bits, F registers by sign bit and zero
+    fn cmov(&mut self, reg0: u8, reg1: u8, data: u64, cond: fn(i64) -> bool) {
+        let c = self.get_limbs(data as u8);
+        let mut lane = 0;
+        self.lanes64(reg0, reg1, |a, b| {
+            let val = if cond(c[lane] as i64) { b } else { a };
+            lane += 1;
+            (val, 0)
+        });
+    }
+    //Per u64 integer op on every lane of reg0
+    fn integer(&mut self, reg0: u8, reg1: u8, op: impl Fn(u64, u64) -> u64) {
+        self.lanes64(reg0, reg1, |a, b| (op(a, b), 0));
+    }
+    //Checks the u64 lanes of reg1 that reg0 divides by
+    //Faults when a lane of the divisor is zero
+    fn divisor_zero(&mut self, reg0: u8, reg1: u8) -> bool {
+        let zero = self.get_limbs(reg1)[..width(reg0)].contains(&0);
+        if zero {
+            self.fault(VmError::DivideByZero);
+        }
+        zero
+    }
+    //reg0 op= reg1
+    fn binary(&mut self, policy: OverflowPolicy, op: IntOp, reg0: u8, reg1: u8, data: u64) {
+        let (a, b) = (self.get_limbs(reg0), self.get_limbs(reg1));
+        if self.arith(policy, op, reg0, a, b) {
+            self.jump_op(data);
+        }
+    }
+    //reg0 = a op data
+    fn immediate(&mut self, policy: OverflowPolicy, op: IntOp, reg0: u8, a: Limbs, data: u64) {
+        let b = if is_float(reg0) {
+            (data as f64).to_bits()
+        } else {
+            data
         };
+        self.arith(policy, op, reg0, a, [b; 4]);
     }
-    fn dinc(&mut self, reg0: u8, reg1: u8, data: u16) {
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = (data as u64) + 1,
-            Register::R1 => self.r1 = (data as u64) + 1,
-            Register::R2 => self.r2 = (data as u64) + 1,
-            Register::F0 => self.f0 = ((data as f64) + 1.0).to_bits(),
-            Register::F1 => self.f1 = ((data as f64) + 1.0).to_bits(),
-            Register::F2 => self.f2 = ((data as f64) + 1.0).to_bits(),
-            Register::P0 => self.p0 = (data as u64) + 1,
-            Register::P1 => self.p1 = (data as u64) + 1,
-            Register::P2 => self.p2 = (data as u64) + 1,
-            Register::S0 => {
-                self.s0[0] = (data as u64) + 1;
-                self.s0[1] = (data as u64) + 1;
-            }
-            Register::S1 => {
-                self.s1[0] = (data as u64) + 1;
-                self.s1[1] = (data as u64) + 1;
-            }
-            Register::S2 => {
-                self.s2[0] = (data as u64) + 1;
-                self.s2[1] = (data as u64) + 1;
-            }
-            Register::X0 => {
-                self.x0[0] = (data as u64) + 1;
-                self.x0[1] = (data as u64) + 1;
-                self.x0[2] = (data as u64) + 1;
-                self.x0[3] = (data as u64) + 1;
-            }
-            Register::X1 => {
-                self.x1[0] = (data as u64) + 1;
-                self.x1[1] = (data as u64) + 1;
-                self.x1[2] = (data as u64) + 1;
-                self.x1[3] = (data as u64) + 1;
-            }
-            Register::X2 => {
-                self.x2[0] = (data as u64) + 1;
-                self.x2[1] = (data as u64) + 1;
-                self.x2[2] = (data as u64) + 1;
-                self.x2[3] = (data as u64) + 1;
+    //Steps reg1 up (or negates it) and then reg0 by one, a trap leaves both unchanged
+    fn step(&mut self, policy: OverflowPolicy, op: IntOp, reg0: u8, reg1: u8, data: u64) {
+        let one = |reg: u8| {
+            if is_float(reg) {
+                [1f64.to_bits(); 4]
+            } else {
+                [1; 4]
             }
         };
-        match Register::from(reg1) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = data as u64,
-            Register::R1 => self.r1 = data as u64,
-            Register::R2 => self.r2 = data as u64,
-            Register::F0 => self.f0 = (data as f64).to_bits(),
-            Register::F1 => self.f1 = (data as f64).to_bits(),
-            Register::F2 => self.f2 = (data as f64).to_bits(),
-            Register::P0 => self.p0 = data as u64,
-            Register::P1 => self.p1 = data as u64,
-            Register::P2 => self.p2 = data as u64,
-            Register::S0 => {
-                self.s0[0] = data as u64;
-                self.s0[1] = data as u64;
-            }
-            Register::S1 => {
-                self.s1[0] = data as u64;
-                self.s1[1] = data as u64;
-            }
-            Register::S2 => {
-                self.s2[0] = data as u64;
-                self.s2[1] = data as u64;
-            }
-            Register::X0 => {
-                self.x0[0] = data as u64;
-                self.x0[1] = data as u64;
-                self.x0[2] = data as u64;
-                self.x0[3] = data as u64;
-            }
-            Register::X1 => {
-                self.x1[0] = data as u64;
-                self.x1[1] = data as u64;
-                self.x1[2] = data as u64;
-                self.x1[3] = data as u64;
-            }
-            Register::X2 => {
-                self.x2[0] = data as u64;
-                self.x2[1] = data as u64;
-                self.x2[2] = data as u64;
-                self.x2[3] = data as u64;
-            }
+        let first = if op == IntOp::Neg {
+            IntOp::Neg
+        } else {
+            IntOp::Add
         };
+        let saved = self.get_limbs(reg1);
+        if !self.arith(policy, first, reg1, saved, one(reg1)) {
+            return;
+        }
+        let a = self.get_limbs(reg0);
+        if !self.arith(policy, op, reg0, a, one(reg0)) {
+            self.set_limbs(reg1, saved);
+            return;
+        }
+        self.jump_op(data);
     }
-    fn ddec(&mut self, reg0: u8, reg1: u8, data: u16) {
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = (data as u64) - 1,
-            Register::R1 => self.r1 = (data as u64) - 1,
-            Register::R2 => self.r2 = (data as u64) - 1,
-            Register::F0 => self.f0 = ((data as f64) - 1.0).to_bits(),
-            Register::F1 => self.f1 = ((data as f64) - 1.0).to_bits(),
-            Register::F2 => self.f2 = ((data as f64) - 1.0).to_bits(),
-            Register::P0 => self.p0 = (data as u64) - 1,
-            Register::P1 => self.p1 = (data as u64) - 1,
-            Register::P2 => self.p2 = (data as u64) - 1,
-            Register::S0 => {
-                self.s0[0] = (data as u64) - 1;
-                self.s0[1] = (data as u64) - 1;
-            }
-            Register::S1 => {
-                self.s1[0] = (data as u64) - 1;
-                self.s1[1] = (data as u64) - 1;
-            }
-            Register::S2 => {
-                self.s2[0] = (data as u64) - 1;
-                self.s2[1] = (data as u64) - 1;
-            }
-            Register::X0 => {
-                self.x0[0] = (data as u64) - 1;
-                self.x0[1] = (data as u64) - 1;
-                self.x0[2] = (data as u64) - 1;
-                self.x0[3] = (data as u64) - 1;
-            }
-            Register::X1 => {
-                self.x1[0] = (data as u64) - 1;
-                self.x1[1] = (data as u64) - 1;
-                self.x1[2] = (data as u64) - 1;
-                self.x1[3] = (data as u64) - 1;
-            }
-            Register::X2 => {
-                self.x2[0] = (data as u64) - 1;
-                self.x2[1] = (data as u64) - 1;
-                self.x2[2] = (data as u64) - 1;
-                self.x2[3] = (data as u64) - 1;
+    //u64 lanes of a op b into reg0 and flags, false (reg0 unchanged) when a checked op traps
+    fn arith(&mut self, policy: OverflowPolicy, op: IntOp, reg0: u8, a: Limbs, b: Limbs) -> bool {
+        if is_float(reg0) {
+            let val = match op {
+                IntOp::Add => self.fop(FloatOp::Add, a[0], b[0]),
+                IntOp::Sub => self.fop(FloatOp::Sub, a[0], b[0]),
+                IntOp::Mul => self.fop(FloatOp::Mul, a[0], b[0]),
+                IntOp::Neg => (-f64::from_bits(a[0])).to_bits(),
+            };
+            self.set_vals(reg0, (val, 0, 0, 0));
+            return true;
+        }
+        let (mut out, mut flags) = ([0u64; 4], 0);
+        for k in 0..width(reg0) {
+            match overflow::apply(policy, op, a[k], b[k]) {
+                Some((val, lane_flags)) => {
+                    out[k] = val;
+                    flags |= lane_flags;
+                }
+                None => {
+                    self.fault(VmError::IntegerOverflow);
+                    return false;
+                }
             }
+        }
+        self.flags = flags;
+        self.set_limbs(reg0, out);
+        true
+    }
+    fn rflags(&mut self, reg0: u8, _reg1: u8, data: u64) {
+        self.set_vals(reg0, (self.flags, 0, 0, 0));
+        self.jump_op(data);
+    }
+    //reg0 = op(reg1) per u64 lane
+    fn bit_unary(&mut self, op: BitOp, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |_, b| vector::bit_op(op, b, 0, 64));
+        self.jump_op(data);
+    }
+    fn bit_rotate(&mut self, op: BitOp, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |a, b| vector::bit_op(op, a, b, 64));
+        self.jump_op(data);
+    }
+    //Fields past bit 63 are cut short, a width of 0 means up to bit 63
+    fn bfext(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (pos, mask) = field(data);
+        self.integer(reg0, reg1, |_, b| (b >> pos) & mask);
+    }
+    fn bfins(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (pos, mask) = field(data);
+        self.integer(reg0, reg1, |a, b| {
+            (a & !(mask << pos)) | ((b & mask) << pos)
+        });
+    }
+    fn bset(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |a, b| a | (1 << (b % 64)));
+        self.jump_op(data);
+    }
+    fn bclr(&mut self, reg0: u8, reg1: u8, data: u64) {
+        self.integer(reg0, reg1, |a, b| a & !(1 << (b % 64)));
+        self.jump_op(data);
+    }
+    //Tests the first u64 lane
+    fn btst(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (a, b) = (self.get_vals(reg0).0, self.get_vals(reg1).0);
+        if (a >> (b % 64)) & 1 == 1 {
+            self.jump_op(data);
+        }
+    }
+    fn vbits(&mut self, op: BitOp, reg0: u8, reg1: u8, data: u64) {
+        let (a, b) = (self.get_bytes(reg0), self.get_bytes(reg1));
+        //Unary ops read reg1, rotations rotate reg0 by reg1
+        let (a, b) = match op {
+            BitOp::Rotl | BitOp::Rotr => (a, b),
+            _ => (b, a),
         };
-        match Register::from(reg1) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = data as u64,
-            Register::R1 => self.r1 = data as u64,
-            Register::R2 => self.r2 = data as u64,
-            Register::F0 => self.f0 = (data as f64).to_bits(),
-            Register::F1 => self.f1 = (data as f64).to_bits(),
-            Register::F2 => self.f2 = (data as f64).to_bits(),
-            Register::P0 => self.p0 = data as u64,
-            Register::P1 => self.p1 = data as u64,
-            Register::P2 => self.p2 = data as u64,
-            Register::S0 => {
-                self.s0[0] = data as u64;
-                self.s0[1] = data as u64;
-            }
-            Register::S1 => {
-                self.s1[0] = data as u64;
-                self.s1[1] = data as u64;
-            }
-            Register::S2 => {
-                self.s2[0] = data as u64;
-                self.s2[1] = data as u64;
-            }
-            Register::X0 => {
-                self.x0[0] = data as u64;
-                self.x0[1] = data as u64;
-                self.x0[2] = data as u64;
-                self.x0[3] = data as u64;
-            }
-            Register::X1 => {
-                self.x1[0] = data as u64;
-                self.x1[1] = data as u64;
-                self.x1[2] = data as u64;
-                self.x1[3] = data as u64;
-            }
-            Register::X2 => {
-                self.x2[0] = data as u64;
-                self.x2[1] = data as u64;
-                self.x2[2] = data as u64;
-                self.x2[3] = data as u64;
-            }
+        let out = vector::bits(op, LaneType::from(data as u16), &a, &b, width(reg0) * 8);
+        self.set_bytes(reg0, out);
+    }
+    fn rounding_mode(&self) -> RoundingMode {
+        RoundingMode::from(self.fcsr >> RM_SHIFT)
+    }
+    fn fop(&mut self, op: FloatOp, a: u64, b: u64) -> u64 {
+        let (val, flags) = float::op64(
+            op,
+            f64::from_bits(a),
+            f64::from_bits(b),
+            self.rounding_mode(),
+        );
+        self.fcsr |= flags;
+        val.to_bits()
+    }
+    fn single(&mut self, op: FloatOp, reg0: u8, reg1: u8) {
+        let rm = self.rounding_mode();
+        self.lanes32(reg0, reg1, |a, b| {
+            let (val, flags) = float::op32(op, f32::from_bits(a), f32::from_bits(b), rm);
+            (val.to_bits(), flags)
+        });
+    }
+    fn math(&mut self, op: MathOp, reg0: u8, reg1: u8) {
+        let rm = self.rounding_mode();
+        self.lanes64(reg0, reg1, |a, b| {
+            let (val, flags) = float::math64(op, f64::from_bits(a), f64::from_bits(b), rm);
+            (val.to_bits(), flags)
+        });
+    }
+    fn maths(&mut self, op: MathOp, reg0: u8, reg1: u8) {
+        let rm = self.rounding_mode();
+        self.lanes32(reg0, reg1, |a, b| {
+            let (val, flags) = float::math32(op, f32::from_bits(a), f32::from_bits(b), rm);
+            (val.to_bits(), flags)
+        });
+    }
+    //Applies op to every u64 of reg1, writes the results to reg0 and raises the flags
+    fn convert(&mut self, reg0: u8, reg1: u8, op: impl Fn(u64) -> (u64, u64)) {
+        self.lanes64(reg0, reg1, |_, v| op(v));
+    }
+    //u64 lanes, op gets (reg0 lane, reg1 lane) and returns (result, fp flags)
+    fn lanes64(&mut self, reg0: u8, reg1: u8, mut op: impl FnMut(u64, u64) -> (u64, u64)) {
+        let (a, a2, a3, a4) = self.get_vals(reg0);
+        let (b, b2, b3, b4) = self.get_vals(reg1);
+        let (a, b) = ([a, a2, a3, a4], [b, b2, b3, b4]);
+        let mut out = [0u64; 4];
+        for k in 0..width(reg0) {
+            let (val, flags) = op(a[k], b[k]);
+            self.fcsr |= flags;
+            out[k] = val;
+        }
+        self.set_vals(reg0, (out[0], out[1], out[2], out[3]));
+    }
+    //f32 lanes, scalar registers use the low 32 bits, S/X registers every 32-bit lane
+    fn lanes32(&mut self, reg0: u8, reg1: u8, mut op: impl FnMut(u32, u32) -> (u32, u64)) {
+        let lanes = match width(reg0) {
+            0 | 1 => width(reg0),
+            v => v * 2,
         };
+        let a = split32(self.get_vals(reg0));
+        let b = split32(self.get_vals(reg1));
+        let mut out = [0u32; 8];
+        for k in 0..lanes {
+            let (val, flags) = op(a[k], b[k]);
+            self.fcsr |= flags;
+            out[k] = val;
+        }
+        self.set_vals(reg0, join32(out));
     }
-    fn dneg(&mut self, reg0: u8, reg1: u8, data: u16) {
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = data.wrapping_neg() as u64,
-            Register::R1 => self.r1 = data.wrapping_neg() as u64,
-            Register::R2 => self.r2 = data.wrapping_neg() as u64,
-            Register::F0 => self.f0 = (data.wrapping_neg() as f64).to_bits(),
-            Register::F1 => self.f1 = (data.wrapping_neg() as f64).to_bits(),
-            Register::F2 => self.f2 = (data.wrapping_neg() as f64).to_bits(),
-            Register::P0 => self.p0 = data.wrapping_neg() as u64,
-            Register::P1 => self.p1 = data.wrapping_neg() as u64,
-            Register::P2 => self.p2 = data.wrapping_neg() as u64,
-            Register::S0 => {
-                self.s0[0] = data.wrapping_neg() as u64;
-                self.s0[1] = data.wrapping_neg() as u64;
-            }
-            Register::S1 => {
-                self.s1[0] = data.wrapping_neg() as u64;
-                self.s1[1] = data.wrapping_neg() as u64;
-            }
-            Register::S2 => {
-                self.s2[0] = data.wrapping_neg() as u64;
-                self.s2[1] = data.wrapping_neg() as u64;
-            }
-            Register::X0 => {
-                self.x0[0] = data.wrapping_neg() as u64;
-                self.x0[1] = data.wrapping_neg() as u64;
-                self.x0[2] = data.wrapping_neg() as u64;
-                self.x0[3] = data.wrapping_neg() as u64;
-            }
-            Register::X1 => {
-                self.x1[0] = data.wrapping_neg() as u64;
-                self.x1[1] = data.wrapping_neg() as u64;
-                self.x1[2] = data.wrapping_neg() as u64;
-                self.x1[3] = data.wrapping_neg() as u64;
-            }
-            Register::X2 => {
-                self.x2[0] = data.wrapping_neg() as u64;
-                self.x2[1] = data.wrapping_neg() as u64;
-                self.x2[2] = data.wrapping_neg() as u64;
-                self.x2[3] = data.wrapping_neg() as u64;
+    //Absolute pc values are instruction indices, like base and entry
+    fn jmp(&mut self, reg0: u8, _reg1: u8, _data: u64) {
+        self.pc = self.get_vals(reg0).0;
+    }
+    fn call(&mut self, reg0: u8, reg1: u8, _data: u64) {
+        let target = self.get_vals(reg0).0;
+        self.set_vals(reg1, (self.pc, 0, 0, 0));
+        self.pc = target;
+    }
+    //Table entries are little-endian u64 pc values
+    fn jtab(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let index = self.get_vals(reg1).0;
+        if index >= data {
+            return;
+        }
+        let address = self.get_vals(reg0).0.wrapping_add(index.wrapping_mul(8));
+        match self.mem.read(address, 8) {
+            Ok(bytes) => self.pc = u64::from_le_bytes(bytes.try_into().unwrap()),
+            Err(address) => self.fault(VmError::BadAddress(address)),
+        }
+    }
+    fn adr(&mut self, reg0: u8, _reg1: u8, data: u64) {
+        let val = self.pc.wrapping_add_signed(data as i64);
+        self.set_vals(reg0, (val, 0, 0, 0));
+    }
+    /*
+    Block ops take pointers in reg0/reg1 and the length in the data register. Every execution
+    handles up to BLOCK_CHUNK bytes, moves the pointers past them, lowers the length and runs
+    the instruction again while bytes are left. Memmove copying back to front keeps the
+    pointers and takes its chunks from the end. A chunk outside data memory faults before
+    anything is written, with the registers at the start of that chunk.
+    */
+    fn block(&self, reg0: u8, reg1: u8, data: u64) -> (u64, u64, u64) {
+        let (a, b, len) = (
+            self.get_vals(reg0).0,
+            self.get_vals(reg1).0,
+            self.get_vals(data as u8).0,
+        );
+        (a, b, len.min(BLOCK_CHUNK))
+    }
+    //The length is written last so it counts down even when it shares a pointer register
+    fn advance(&mut self, pointers: &[u8], data: u64, step: u64, done: bool) {
+        for reg in pointers {
+            let val = self.get_vals(*reg).0.wrapping_add(step);
+            self.set_vals(*reg, (val, 0, 0, 0));
+        }
+        let len = self.get_vals(data as u8).0;
+        self.set_vals(data as u8, (len - step, 0, 0, 0));
+        self.resume = !done && len > step;
+    }
+    fn memcpy(&mut self, reg0: u8, reg1: u8, data: u64, overlap: bool) {
+        let (dst, src, chunk) = self.block(reg0, reg1, data);
+        let len = self.get_vals(data as u8).0;
+        if overlap && dst > src && dst - src < len {
+            let tail = len - chunk;
+            let copied = self.mem.copy(
+                dst.wrapping_add(tail),
+                src.wrapping_add(tail),
+                chunk as usize,
+            );
+            if let Err(address) = copied {
+                return self.fault(VmError::BadAddress(address));
+            }
+            return self.advance(&[], data, chunk, false);
+        }
+        match self.mem.copy(dst, src, chunk as usize) {
+            Ok(()) => self.advance(&[reg0, reg1], data, chunk, false),
+            Err(address) => self.fault(VmError::BadAddress(address)),
+        }
+    }
+    fn memset(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (dst, byte, chunk) = self.block(reg0, reg1, data);
+        if let Err(address) = self.mem.fill(dst, chunk as usize, byte as u8) {
+            return self.fault(VmError::BadAddress(address));
+        }
+        self.advance(&[reg0], data, chunk, false);
+    }
+    //Stops with the pointers at the first difference and the length counting it, so the
+    //length is zero only for equal blocks. Flags as for a sub of the differing bytes
+    fn memcmp(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (a, b, chunk) = self.block(reg0, reg1, data);
+        let found = match (
+            self.mem.read(a, chunk as usize),
+            self.mem.read(b, chunk as usize),
+        ) {
+            (Ok(a), Ok(b)) => a
+                .iter()
+                .zip(&b)
+                .position(|(x, y)| x != y)
+                .map(|k| (k, a[k] < b[k])),
+            (Err(address), _) | (_, Err(address)) => {
+                return self.fault(VmError::BadAddress(address))
             }
         };
-        match Register::from(reg1) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = data as u64,
-            Register::R1 => self.r1 = data as u64,
-            Register::R2 => self.r2 = data as u64,
-            Register::F0 => self.f0 = (data as f64).to_bits(),
-            Register::F1 => self.f1 = (data as f64).to_bits(),
-            Register::F2 => self.f2 = (data as f64).to_bits(),
-            Register::P0 => self.p0 = data as u64,
-            Register::P1 => self.p1 = data as u64,
-            Register::P2 => self.p2 = data as u64,
-            Register::S0 => {
-                self.s0[0] = data as u64;
-                self.s0[1] = data as u64;
-            }
-            Register::S1 => {
-                self.s1[0] = data as u64;
-                self.s1[1] = data as u64;
-            }
-            Register::S2 => {
-                self.s2[0] = data as u64;
-                self.s2[1] = data as u64;
-            }
-            Register::X0 => {
-                self.x0[0] = data as u64;
-                self.x0[1] = data as u64;
-                self.x0[2] = data as u64;
-                self.x0[3] = data as u64;
-            }
-            Register::X1 => {
-                self.x1[0] = data as u64;
-                self.x1[1] = data as u64;
-                self.x1[2] = data as u64;
-                self.x1[3] = data as u64;
-            }
-            Register::X2 => {
-                self.x2[0] = data as u64;
-                self.x2[1] = data as u64;
-                self.x2[2] = data as u64;
-                self.x2[3] = data as u64;
+        match found {
+            Some((k, below)) => {
+                self.advance(&[reg0, reg1], data, k as u64, true);
+                self.flags = if below { FLAG_CARRY } else { 0 };
+            }
+            None => {
+                self.advance(&[reg0, reg1], data, chunk, false);
+                if !self.resume {
+                    self.flags = 0;
+                }
             }
-        };
+        }
     }
-    fn swap(&mut self, reg0: u8, reg1: u8, data: u16) {
-        let (val, val2, val3, val4) = self.get_vals(reg0);
-        let (val5, val6, val7, val8) = self.get_vals(reg0);
-        match Register::from(reg0) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = val5,
-            Register::R1 => self.r1 = val5,
-            Register::R2 => self.r2 = val5,
-            Register::F0 => self.f0 = val5,
-            Register::F1 => self.f1 = val5,
-            Register::F2 => self.f2 = val5,
-            Register::P0 => self.p0 = val5,
-            Register::P1 => self.p1 = val5,
-            Register::P2 => self.p2 = val5,
-            Register::S0 => {
-                self.s0[0] = val5;
-                self.s0[1] = val6;
-            }
-            Register::S1 => {
-                self.s1[0] = val5;
-                self.s1[1] = val6;
-            }
-            Register::S2 => {
-                self.s2[0] = val5;
-                self.s2[1] = val6;
-            }
-            Register::X0 => {
-                self.x0[0] = val5;
-                self.x0[1] = val6;
-                self.x0[2] = val7;
-                self.x0[3] = val8;
-            }
-            Register::X1 => {
-                self.x1[0] = val5;
-                self.x1[1] = val6;
-                self.x1[2] = val7;
-                self.x1[3] = val8;
-            }
-            Register::X2 => {
-                self.x2[0] = val5;
-                self.x2[1] = val6;
-                self.x2[2] = val7;
-                self.x2[3] = val8;
-            }
+    //Stops with the pointer at the byte and the length counting it, zero if it isn't there
+    fn memchr(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (address, byte, chunk) = self.block(reg0, reg1, data);
+        let found = match self.mem.read(address, chunk as usize) {
+            Ok(bytes) => bytes.iter().position(|v| *v == byte as u8),
+            Err(address) => return self.fault(VmError::BadAddress(address)),
         };
-        match Register::from(reg1) {
-            Register::ZR => {}
-            Register::R0 => self.r0 = val,
-            Register::R1 => self.r1 = val,
-            Register::R2 => self.r2 = val,
-            Register::F0 => self.f0 = val,
-            Register::F1 => self.f1 = val,
-            Register::F2 => self.f2 = val,
-            Register::P0 => self.p0 = val,
-            Register::P1 => self.p1 = val,
-            Register::P2 => self.p2 = val,
-            Register::S0 => {
-                self.s0[0] = val;
-                self.s0[1] = val2;
-            }
-            Register::S1 => {
-                self.s1[0] = val;
-                self.s1[1] = val2;
-            }
-            Register::S2 => {
-                self.s2[0] = val;
-                self.s2[1] = val2;
-            }
-            Register::X0 => {
-                self.x0[0] = val;
-                self.x0[1] = val2;
-                self.x0[2] = val3;
-                self.x0[3] = val4;
-            }
-            Register::X1 => {
-                self.x1[0] = val;
-                self.x1[1] = val2;
-                self.x1[2] = val3;
-                self.x1[3] = val4;
-            }
-            Register::X2 => {
-                self.x2[0] = val;
-                self.x2[1] = val2;
-                self.x2[2] = val3;
-                self.x2[3] = val4;
+        let (len, step) = (
+            self.get_vals(data as u8).0,
+            found.map_or(chunk, |k| k as u64),
+        );
+        self.set_vals(reg0, (address.wrapping_add(step), 0, 0, 0));
+        self.set_vals(data as u8, (len - step, 0, 0, 0));
+        self.resume = found.is_none() && len > step;
+    }
+    //Atomics take the address in reg1, it has to be an aligned u64 inside data memory
+    fn atomic_address(&mut self, reg1: u8) -> Option<u64> {
+        let address = self.get_vals(reg1).0;
+        if !address.is_multiple_of(8) {
+            self.fault(VmError::Misaligned(address));
+            return None;
+        }
+        match self.mem.check(address, 8) {
+            Ok(()) => Some(address),
+            Err(address) => {
+                self.fault(VmError::BadAddress(address));
+                None
             }
-        };
+        }
     }
-    fn jump_op(&mut self, data: u16) {
-        if data == 0 {
-            return;
-        } else {
-            self.pc = self.pc.wrapping_add_signed((data as i16) as i64);
+    fn ald(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if let Some(address) = self.atomic_address(reg1) {
+            let val = atomic::load(self.mem.word(address).unwrap(), data);
+            self.set_vals(reg0, (val, 0, 0, 0));
+        }
+    }
+    fn ast(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if let Some(address) = self.atomic_address(reg1) {
+            atomic::store(self.mem.word(address).unwrap(), self.get_vals(reg0).0, data);
+        }
+    }
+    fn acas(&mut self, reg0: u8, reg1: u8, data: u64) {
+        if let Some(address) = self.atomic_address(reg1) {
+            let (expected, new) = (self.get_vals(reg0).0, self.get_vals(data as u8).0);
+            let (old, swapped) =
+                atomic::compare_swap(self.mem.word(address).unwrap(), expected, new);
+            self.set_vals(reg0, (old, 0, 0, 0));
+            self.flags = if swapped { FLAG_CARRY } else { 0 };
+        }
+    }
+    fn afetch(&mut self, reg0: u8, reg1: u8, data: u64, op: AtomicOp) {
+        if let Some(address) = self.atomic_address(reg1) {
+            let word = self.mem.word(address).unwrap();
+            let old = atomic::fetch(word, op, self.get_vals(reg0).0, data);
+            self.set_vals(reg0, (old, 0, 0, 0));
+        }
+    }
+    fn cid(&mut self, reg0: u8, reg1: u8, _data: u64) {
+        self.set_vals(reg0, (self.id, 0, 0, 0));
+        self.set_vals(reg1, (self.cores.max(1), 0, 0, 0));
+    }
+    fn spawn(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (core, pc, arg) = (
+            self.get_vals(reg0).0,
+            self.get_vals(reg1).0,
+            self.get_vals(data as u8).0,
+        );
+        self.event = Some(CoreEvent::Spawn { core, pc, arg });
+    }
+    fn tvec(&mut self, reg0: u8, reg1: u8, _data: u64) {
+        let table = self.get_vals(reg0).0;
+        self.set_vals(reg1, (self.tvec, 0, 0, 0));
+        self.tvec = table;
+    }
+    fn rtt(&mut self, _reg0: u8, _reg1: u8, _data: u64) {
+        if !self.in_trap {
+            return self.fault(VmError::IllegalOpcode(Opcode::Rtt.into()));
+        }
+        match self.mem.read(self.tvec.wrapping_add(trap::FRAME_PC), 8) {
+            Ok(bytes) => self.pc = u64::from_le_bytes(bytes.try_into().unwrap()),
+            Err(address) => return self.fault(VmError::BadAddress(address)),
+        }
+        self.in_trap = false;
+    }
+    fn ie(&mut self, reg0: u8, reg1: u8, _data: u64) {
+        let enabled = self.get_vals(reg0).0 != 0;
+        self.set_vals(reg1, (self.irq.enabled as u64, 0, 0, 0));
+        self.irq.enabled = enabled;
+    }
+    fn imask(&mut self, reg0: u8, reg1: u8, _data: u64) {
+        let mask = self.get_vals(reg0).0;
+        self.set_vals(reg1, (self.irq.mask, 0, 0, 0));
+        self.irq.mask = mask;
+    }
+    fn ipend(&mut self, reg0: u8, reg1: u8, _data: u64) {
+        let (pending, ack) = (self.irq.pending(), self.get_vals(reg1).0);
+        self.irq.clear(ack);
+        self.set_vals(reg0, (pending, 0, 0, 0));
+    }
+    fn timer(&mut self, reg0: u8, reg1: u8, data: u64) {
+        let (ticks, source) = (self.get_vals(reg0).0, TimerSource::from(data));
+        self.set_vals(reg1, (self.irq.clock(source), 0, 0, 0));
+        self.irq.arm(source, ticks);
+    }
+    fn jump_op(&mut self, data: u64) {
+        if data != 0 {
+            self.pc = self.pc.wrapping_add_signed(data as i64);
         }
     }
     fn get_vals(&self, reg0: u8) -> (u64, u64, u64, u64) {
@@ -2165,4 +2745,1095 @@ impl S64 {
         };
         (val, val2, val3, val4)
     }
+    fn get_limbs(&self, reg: u8) -> Limbs {
+        let (val, val2, val3, val4) = self.get_vals(reg);
+        [val, val2, val3, val4]
+    }
+    fn set_limbs(&mut self, reg: u8, limbs: Limbs) {
+        self.set_vals(reg, (limbs[0], limbs[1], limbs[2], limbs[3]));
+    }
+    fn get_bytes(&self, reg: u8) -> Bytes {
+        let (val, val2, val3, val4) = self.get_vals(reg);
+        let mut out = [0u8; 32];
+        for (k, v) in [val, val2, val3, val4].into_iter().enumerate() {
+            out[8 * k..8 * k + 8].copy_from_slice(&v.to_le_bytes());
+        }
+        out
+    }
+    fn set_bytes(&mut self, reg: u8, bytes: Bytes) {
+        let word = |k: usize| u64::from_le_bytes(bytes[8 * k..8 * k + 8].try_into().unwrap());
+        self.set_vals(reg, (word(0), word(1), word(2), word(3)));
+    }
+    fn set_vals(&mut self, reg0: u8, (val, val2, val3, val4): (u64, u64, u64, u64)) {
+        match Register::from(reg0) {
+            Register::ZR => {}
+            Register::R0 => self.r0 = val,
+            Register::R1 => self.r1 = val,
+            Register::R2 => self.r2 = val,
+            Register::F0 => self.f0 = val,
+            Register::F1 => self.f1 = val,
+            Register::F2 => self.f2 = val,
+            Register::P0 => self.p0 = val,
+            Register::P1 => self.p1 = val,
+            Register::P2 => self.p2 = val,
+            Register::S0 => self.s0 = [val, val2],
+            Register::S1 => self.s1 = [val, val2],
+            Register::S2 => self.s2 = [val, val2],
+            Register::X0 => self.x0 = [val, val2, val3, val4],
+            Register::X1 => self.x1 = [val, val2, val3, val4],
+            Register::X2 => self.x2 = [val, val2, val3, val4],
+        };
+    }
+}
+fn split32((val, val2, val3, val4): (u64, u64, u64, u64)) -> [u32; 8] {
+    let mut out = [0u32; 8];
+    for (k, v) in [val, val2, val3, val4].into_iter().enumerate() {
+        out[2 * k] = v as u32;
+        out[2 * k + 1] = (v >> 32) as u32;
+    }
+    out
+}
+fn join32(lanes: [u32; 8]) -> (u64, u64, u64, u64) {
+    let pair = |k: usize| (lanes[2 * k] as u64) | ((lanes[2 * k + 1] as u64) << 32);
+    (pair(0), pair(1), pair(2), pair(3))
+}
+//Register size in u64s
+fn width(reg: u8) -> usize {
+    match Register::from(reg) {
+        Register::ZR => 0,
+        Register::S0 | Register::S1 | Register::S2 => 2,
+        Register::X0 | Register::X1 | Register::X2 => 4,
+        _ => 1,
+    }
+}
+//Bit field operand, pos in the low byte and width in the high byte
+fn field(data: u64) -> (u32, u64) {
+    let pos = (data & 0xFF) as u32 % 64;
+    let width = match (data >> 8) as u32 {
+        0 => 64 - pos,
+        v => v.min(64 - pos),
+    };
+    (pos, u64::MAX >> (64 - width))
+}
+//Logical shifts by a guest-controlled amount, 64 or more shifts every bit out
+fn shift_left(val: u64, by: u64) -> u64 {
+    u32::try_from(by)
+        .ok()
+        .and_then(|v| val.checked_shl(v))
+        .unwrap_or(0)
+}
+fn shift_right(val: u64, by: u64) -> u64 {
+    u32::try_from(by)
+        .ok()
+        .and_then(|v| val.checked_shr(v))
+        .unwrap_or(0)
+}
+fn ext_bits(data: u64) -> u32 {
+    match data {
+        8 | 16 | 32 => data as u32,
+        _ => 64,
+    }
+}
+fn is_float(reg: u8) -> bool {
+    matches!(
+        Register::from(reg),
+        Register::F0 | Register::F1 | Register::F2
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compiler::assemble, vm::interrupt};
+
+    //A core on its own, outside a machine
+    impl S64 {
+        pub(crate) fn with_memory(size: usize) -> Self {
+            Self {
+                mem: Memory::new(size),
+                ..Default::default()
+            }
+        }
+        pub(crate) fn execute_code(&mut self, code: Vec<u32>) -> Result<(), VmError> {
+            self.load(Image {
+                base: 0,
+                entry: 0,
+                code,
+            });
+            self.run()
+        }
+        //Stops at the end of the code or at the first fault the guest doesn't handle, pc is then
+        //past the faulting instruction. Without a machine spawn fails and park returns at once
+        pub(crate) fn run(&mut self) -> Result<(), VmError> {
+            loop {
+                match self.run_for(u64::MAX)? {
+                    Exit::Done => return Ok(()),
+                    Exit::Event(CoreEvent::Spawn { .. }) => self.flags = FLAG_CARRY,
+                    Exit::Event(_) | Exit::Budget => {}
+                }
+            }
+        }
+    }
+
+    fn run(code: Vec<&str>) -> (S64, Result<(), VmError>) {
+        let mut vm = S64::with_memory(1 << 16);
+        let result = vm.execute_code(assemble(code).unwrap());
+        (vm, result)
+    }
+
+    #[test]
+    fn float_registers_hold_f64_bits() {
+        let (vm, result) = run(vec![
+            "dadd r0 zr 7",
+            "itof f0 r0",
+            "dadd r1 zr 2",
+            "itof f1 r1",
+            "div f0 f1",
+            "ftoi r2 f0",
+            "rftoi p0 f0",
+            "dsub r0 zr 7",
+            "itof f2 r0",
+            "ftou p2 f2",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((f64::from_bits(vm.f0), f64::from_bits(vm.f1)), (3.5, 2.0));
+        assert_eq!((vm.r2, vm.p0), (3, 4));
+        assert_eq!((f64::from_bits(vm.f2), vm.p2), (-7.0, 0));
+        let (vm, result) = run(vec![
+            "dsub r0 zr 1",
+            "utof f0 r0",
+            "itof f1 r0",
+            "sub f2 f2",
+            "div f2 f2",
+            "ftoi r1 f2",
+            "dadd r2 zr 1",
+            "itof f1 r2",
+            "sub p0 p0",
+            "itof f2 p0",
+            "div f1 f2",
+            "ftoi r2 f1",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!(f64::from_bits(vm.f0), u64::MAX as f64);
+        assert_eq!((vm.r1, vm.r2), (0, i64::MAX as u64));
+        assert_eq!(f64::from_bits(vm.f1), f64::INFINITY);
+        let flags = float::FLAG_INVALID | float::FLAG_DIVZERO;
+        assert_eq!(vm.fcsr & flags, flags);
+    }
+    #[test]
+    fn rounding_mode_and_flags_through_fcsr() {
+        let (vm, result) = run(vec![
+            "dadd r0 zr 1",
+            "dshl r0 r0 53",
+            "dadd r0 r0 1",
+            "dfrm zr zr 3",
+            "itof f0 r0",
+            "dfrm zr zr 2",
+            "itof f1 r0",
+            "dfclr r1 zr 31",
+            "rfcsr r2 zr",
+        ]);
+        assert_eq!(result, Ok(()));
+        let exact = 2f64.powi(53);
+        assert_eq!(
+            (f64::from_bits(vm.f0), f64::from_bits(vm.f1)),
+            (exact + 2.0, exact)
+        );
+        assert_eq!((vm.r1, vm.r2), (float::FLAG_INEXACT, 2 << RM_SHIFT));
+    }
+    #[test]
+    fn math_opcodes_on_registers_and_lanes() {
+        let (vm, result) = run(vec![
+            "dadd r0 zr 16",
+            "itof f0 r0",
+            "sqrt f1 f0",
+            "dadd r1 zr 3",
+            "itof f2 r1",
+            "fma f2 f1 f0",
+            "pow f1 f1",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!(f64::from_bits(vm.f2), 28.0);
+        assert_eq!(f64::from_bits(vm.f1), 256.0);
+        let mut vm = S64::with_memory(64);
+        vm.s0 = [
+            (9f32.to_bits() as u64) << 32 | 4f32.to_bits() as u64,
+            ((-1f32).to_bits() as u64) << 32 | 0.25f32.to_bits() as u64,
+        ];
+        assert_eq!(
+            vm.execute_code(assemble(vec!["sqrts s1 s0"]).unwrap()),
+            Ok(())
+        );
+        let lanes = [
+            vm.s1[0] as u32,
+            (vm.s1[0] >> 32) as u32,
+            vm.s1[1] as u32,
+            (vm.s1[1] >> 32) as u32,
+        ];
+        assert_eq!(&lanes[..3], [2f32, 3.0, 0.5].map(f32::to_bits));
+        assert!(f32::from_bits(lanes[3]).is_nan());
+        assert_eq!(vm.fcsr & float::FLAG_INVALID, float::FLAG_INVALID);
+    }
+    #[test]
+    fn unassigned_lane_types_are_illegal() {
+        let (_, result) = run(vec!["vadd x0 x1 f64", "vpopcnt x0 x1 i8"]);
+        assert_eq!(result, Ok(()));
+        for op in ["vadd x0 x1 i8", "vhsum r0 x1 i8", "vbitrev x0 x1 i8"] {
+            let mut code = assemble(vec![op]).unwrap();
+            for ty in [6, 7] {
+                code[0] = code[0] & !(0b111 << 16) | ty << 16;
+                let opcode = code[0] as u8;
+                let result = S64::with_memory(64).execute_code(code.clone());
+                assert_eq!(
+                    result,
+                    Err(VmError::IllegalOpcode(opcode)),
+                    "{} type {}",
+                    op,
+                    ty
+                );
+            }
+        }
+    }
+    #[test]
+    fn lane_insert_extract_and_broadcast() {
+        let (vm, result) = run(vec![
+            "dadd r0 zr 48879",
+            "vins x0 r0 i16:5",
+            "vext r1 x0 i16:5",
+            "vext r2 x0 i16:4",
+            "vbcast s1 r0 i16",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!(vm.x0, [0, 0xBEEF << 16, 0, 0]);
+        assert_eq!((vm.r1, vm.r2), (0xBEEF, 0));
+        assert_eq!(vm.s1, [0xBEEF_BEEF_BEEF_BEEF; 2]);
+        for op in ["vext r0 s0 i32:4", "vins x0 r0 i64:4", "vext r0 zr i8:8"] {
+            let (_, result) = run(vec![op]);
+            assert!(
+                matches!(result, Err(VmError::LaneIndex { .. })),
+                "{:?} {}",
+                result,
+                op
+            );
+        }
+    }
+    #[test]
+    fn masks_predicate_arithmetic_moves_and_stores() {
+        let mut vm = S64::with_memory(256);
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        (vm.x0, vm.x1, vm.x2) = ([1, 5, 3, 7], [2, 5, 1, 9], [1, 5, 3, 7]);
+        assert_eq!(exec(&mut vm, "vcmplt x2 x1 i64"), Ok(()));
+        assert_eq!(vm.x2, [u64::MAX, 0, 0, u64::MAX]);
+        assert_eq!(exec(&mut vm, "vadd x0 x1 i64/x2"), Ok(()));
+        assert_eq!(vm.x0, [3, 5, 3, 16]);
+        assert_eq!(exec(&mut vm, "vmovz x1 x0 i64/x2"), Ok(()));
+        assert_eq!(vm.x1, [3, 0, 0, 16]);
+        (vm.x0, vm.x1) = ([10, 11, 12, 13], [20, 21, 22, 23]);
+        assert_eq!(exec(&mut vm, "vblend x0 x1 i64/x2"), Ok(()));
+        assert_eq!(vm.x0, [20, 11, 12, 23]);
+        vm.mem.write(100, &[0xFF; 32]).unwrap();
+        vm.r0 = 100;
+        assert_eq!(exec(&mut vm, "vst x0 r0 i64/x2"), Ok(()));
+        let mut expected = [0xFF; 32];
+        expected[..8].copy_from_slice(&20u64.to_le_bytes());
+        expected[24..].copy_from_slice(&23u64.to_le_bytes());
+        assert_eq!(vm.mem.read(100, 32), Ok(expected.to_vec()));
+        //Mask lanes follow the lane type, NaN lanes compare unequal
+        vm.s0 = [f64::NAN.to_bits(), 1f64.to_bits()];
+        vm.s1 = vm.s0;
+        assert_eq!(exec(&mut vm, "vcmpeq s0 s1 f64"), Ok(()));
+        assert_eq!(vm.s0, [0, u64::MAX]);
+        vm.s1 = [0x0000_0001_0000_0002, 0x0000_0003_0000_0004];
+        vm.s2 = [0x0000_000A_0000_000A; 2];
+        assert_eq!(exec(&mut vm, "vsub s2 s1 i32/s0"), Ok(()));
+        assert_eq!(vm.s2, [0x0000_000A_0000_000A, 0x0000_0007_0000_0006]);
+    }
+    #[test]
+    fn vector_loads_stores_gathers_and_scatters() {
+        let mut vm = S64::with_memory(256);
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        let bytes = pattern(256);
+        vm.mem.write(0, &bytes).unwrap();
+        let word = |a: usize| u32::from_le_bytes(bytes[a..a + 4].try_into().unwrap()) as u64;
+        let pair = |a: usize, b: usize| word(a) | word(b) << 32;
+        vm.r0 = 4;
+        assert_eq!(exec(&mut vm, "vld x0 r0 i32"), Ok(()));
+        assert_eq!(
+            vm.x0,
+            [pair(4, 8), pair(12, 16), pair(20, 24), pair(28, 32)]
+        );
+        assert_eq!(exec(&mut vm, "vlda s0 r0 i64"), Err(VmError::Misaligned(4)));
+        vm.r0 = 32;
+        assert_eq!(exec(&mut vm, "vlda s0 r0 i64"), Ok(()));
+        assert_eq!(vm.s0, [pair(32, 36), pair(40, 44)]);
+        vm.r0 = 0;
+        assert_eq!(exec(&mut vm, "vlds s0 r0 i32:3"), Ok(()));
+        assert_eq!(vm.s0, [pair(0, 12), pair(24, 36)]);
+        vm.r0 = 60;
+        assert_eq!(exec(&mut vm, "vlds s0 r0 i32:255"), Ok(()));
+        assert_eq!(vm.s0, [pair(60, 56), pair(52, 48)]);
+        (vm.r0, vm.s1) = (0, [3, 15 | 1 << 32]);
+        assert_eq!(exec(&mut vm, "vgather s0 r0 i32:s1"), Ok(()));
+        assert_eq!(vm.s0, [pair(12, 0), pair(60, 4)]);
+        vm.r0 = 128;
+        assert_eq!(exec(&mut vm, "vscatter s0 r0 i32:s1"), Ok(()));
+        for (index, from) in [(3, 12), (0, 0), (15, 60), (1, 4)] {
+            let to = 128 + index * 4;
+            assert_eq!(
+                vm.mem.read(to as u64, 4),
+                Ok(bytes[from..from + 4].to_vec())
+            );
+        }
+        vm.r0 = 100;
+        assert_eq!(exec(&mut vm, "vsts s0 r0 i64:2"), Ok(()));
+        assert_eq!(vm.mem.read(116, 8), Ok(vm.s0[1].to_le_bytes().to_vec()));
+        //The first lane outside memory is reported, a faulting store writes nothing
+        vm.r0 = 240;
+        let fault = Err(VmError::LaneFault {
+            lane: 2,
+            address: 256,
+        });
+        assert_eq!(exec(&mut vm, "vld x0 r0 i64"), fault);
+        assert_eq!(exec(&mut vm, "vst x0 r0 i64"), fault);
+        assert_eq!(vm.mem.read(240, 16), Ok(bytes[240..].to_vec()));
+        (vm.r0, vm.s1) = (8, [0, u32::MAX as u64]);
+        let fault = Err(VmError::LaneFault {
+            lane: 2,
+            address: 8 + u32::MAX as u64 * 4,
+        });
+        assert_eq!(exec(&mut vm, "vgather s0 r0 i32:s1"), fault);
+    }
+    #[test]
+    fn wide_ops_treat_registers_as_one_integer() {
+        let mut vm = S64::with_memory(64);
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        (vm.x0, vm.x1) = ([u64::MAX, u64::MAX, 5, 0], [1, 0, 0, 0]);
+        assert_eq!(exec(&mut vm, "wadd x0 x1 r0"), Ok(()));
+        assert_eq!((vm.x0, vm.r0), ([0, 0, 6, 0], 0));
+        (vm.s0, vm.s1) = ([u64::MAX; 2], [1, 0]);
+        assert_eq!(exec(&mut vm, "wadd s0 s1 r0"), Ok(()));
+        assert_eq!((vm.s0, vm.r0), ([0, 0], 1));
+        assert_eq!(exec(&mut vm, "wsbb s0 s1 r0"), Ok(()));
+        assert_eq!((vm.s0, vm.r0), ([u64::MAX - 1, u64::MAX], 1));
+        (vm.s0, vm.s1) = ([u64::MAX; 2], [2, 0]);
+        assert_eq!(exec(&mut vm, "wmul s0 s1 s2"), Ok(()));
+        assert_eq!((vm.s0, vm.s2), ([u64::MAX - 1, u64::MAX], [1, 0]));
+        (vm.s0, vm.s1) = ([u64::MAX; 2], [2, 0]);
+        assert_eq!(exec(&mut vm, "wmuls s0 s1 s2"), Ok(()));
+        assert_eq!((vm.s0, vm.s2), ([u64::MAX - 1, u64::MAX], [u64::MAX; 2]));
+        (vm.x0, vm.r1) = ([1 << 63, 0, 0, 0], 1);
+        assert_eq!(exec(&mut vm, "wshl x0 r1"), Ok(()));
+        assert_eq!(vm.x0, [0, 1, 0, 0]);
+        (vm.x0, vm.r1) = ([0, 0, 0, 1 << 63], 130);
+        assert_eq!(exec(&mut vm, "wsar x0 r1"), Ok(()));
+        assert_eq!(vm.x0, [0, u64::MAX << 61, u64::MAX, u64::MAX]);
+        assert_eq!(exec(&mut vm, "wrotr x0 r1"), Ok(()));
+        assert_eq!(vm.x0, [u64::MAX, u64::MAX >> 2, 0, u64::MAX << 59]);
+        (vm.s0, vm.s1) = ([0, 1 << 63], [1, 0]);
+        assert_eq!(exec(&mut vm, "wcmp s0 s1 r2"), Ok(()));
+        assert_eq!(exec(&mut vm, "wcmps s0 s1 p2"), Ok(()));
+        assert_eq!((vm.r2, vm.p2 as i64), (1, -1));
+    }
+    #[test]
+    fn signed_division_shifts_and_high_products() {
+        let values = [0, 1, 7, -7, i64::MAX, i64::MIN, -1];
+        for a in values {
+            for b in values {
+                let mut vm = S64::with_memory(64);
+                let mut exec = |line: &str| {
+                    (vm.r0, vm.r1) = (a as u64, b as u64);
+                    let result = vm.execute_code(assemble(vec![line]).unwrap());
+                    (result, vm.r0 as i64, vm.r1 as i64)
+                };
+                if b == 0 {
+                    for op in ["sdiv r0 r1", "smod r0 r1", "sdivmod r0 r1", "divmod r0 r1"] {
+                        assert_eq!(exec(op), (Err(VmError::DivideByZero), a, 0), "{}", op);
+                    }
+                } else {
+                    assert_eq!(exec("sdiv r0 r1").1, a.wrapping_div(b), "{} / {}", a, b);
+                    assert_eq!(exec("smod r0 r1").1, a.wrapping_rem(b), "{} % {}", a, b);
+                    let expected = (Ok(()), a.wrapping_div(b), a.wrapping_rem(b));
+                    assert_eq!(exec("sdivmod r0 r1"), expected);
+                    let (a, b) = (a as u64, b as u64);
+                    let expected = (Ok(()), (a / b) as i64, (a % b) as i64);
+                    assert_eq!(exec("divmod r0 r1"), expected);
+                }
+                let product = a as i128 * b as i128;
+                assert_eq!(exec("mulh r0 r1").1, (product >> 64) as i64);
+                let product = a as u64 as u128 * b as u64 as u128;
+                assert_eq!(exec("mulhu r0 r1").1, (product >> 64) as i64);
+                assert_eq!(exec("sar r0 r1").1, a >> (b as u64).min(63));
+            }
+        }
+        let (vm, result) = run(vec![
+            "dadd r1 zr 65408",
+            "sext r0 r1 8",
+            "sext r2 r1 16",
+            "zext p0 r1 8",
+            "dadd p1 zr 255",
+            "sext p1 p1 8",
+            "sext p2 p1 64",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r0 as i64, vm.r2 as i64, vm.p0), (-128, -128, 0x80));
+        assert_eq!((vm.p1 as i64, vm.p2 as i64), (-1, -1));
+    }
+    #[test]
+    fn overflow_policies_wrap_trap_or_saturate() {
+        let mut vm = S64::with_memory(64);
+        let mut exec = |policy, line: &str, a: u64, b: u64| {
+            (vm.overflow, vm.r0, vm.r1, vm.r2) = (policy, a, b, 0);
+            let result = vm.execute_code(assemble(vec![line, "rflags r2"]).unwrap());
+            (result, vm.r0, vm.r1, vm.r2)
+        };
+        let (wrapping, checked) = (OverflowPolicy::Wrapping, OverflowPolicy::Checked);
+        let saturating = OverflowPolicy::Saturating;
+        let overflow = || Err(VmError::IntegerOverflow);
+        assert_eq!(
+            exec(wrapping, "add r0 r1", u64::MAX, 2),
+            (Ok(()), 1, 2, FLAG_CARRY)
+        );
+        assert_eq!(
+            exec(checked, "add r0 r1", u64::MAX, 2),
+            (overflow(), u64::MAX, 2, 0)
+        );
+        assert_eq!(exec(saturating, "add r0 r1", u64::MAX, 2).1, u64::MAX);
+        assert_eq!(exec(saturating, "sub r0 r1", 1, 2).1, 0);
+        assert_eq!(exec(checked, "dadd r0 r0 5", u64::MAX - 4, 0).0, overflow());
+        let flags = FLAG_CARRY | FLAG_OVERFLOW;
+        assert_eq!(exec(checked, "mul r0 r1", 1 << 63, 1).1, 1 << 63);
+        assert_eq!(
+            exec(wrapping, "mul r0 r1", 1 << 63, 2),
+            (Ok(()), 0, 2, flags)
+        );
+        //The policy variants ignore the VM policy
+        assert_eq!(exec(wrapping, "cadd r0 r1", u64::MAX, 2).0, overflow());
+        assert_eq!(exec(wrapping, "csub r0 r1", 0, 1).0, overflow());
+        assert_eq!(exec(wrapping, "cmul r0 r1", 1 << 32, 1 << 32).0, overflow());
+        assert_eq!(
+            exec(saturating, "cneg r0 r1", 0, i64::MIN as u64).0,
+            overflow()
+        );
+        assert_eq!(
+            exec(checked, "qadd r0 r1", u64::MAX, 2),
+            (Ok(()), u64::MAX, 2, FLAG_CARRY)
+        );
+        assert_eq!(exec(checked, "qsub r0 r1", 1, 2).1, 0);
+        assert_eq!(
+            exec(checked, "qneg r0 r1", 0, i64::MIN as u64).2,
+            i64::MAX as u64
+        );
+        //A trapping inc leaves both registers as they were
+        assert_eq!(
+            exec(wrapping, "cinc r0 r1", u64::MAX, 7),
+            (overflow(), u64::MAX, 7, 0)
+        );
+        assert_eq!(
+            exec(wrapping, "cinc r0 r1", 7, u64::MAX),
+            (overflow(), 7, u64::MAX, 0)
+        );
+        assert_eq!(
+            exec(wrapping, "qinc r0 r1", u64::MAX, 7),
+            (Ok(()), u64::MAX, 8, FLAG_CARRY)
+        );
+        //S/X registers take every u64 lane through the policy
+        let (vm, result) = run(vec![
+            "dadd r0 zr 1",
+            "vbcast s1 r0 i64",
+            "qadd s0 s1",
+            "qsub s2 s1",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.s0, vm.s2), ([1, 1], [0, 0]));
+        let mut vm = S64::with_memory(64);
+        (vm.s0, vm.s1) = ([u64::MAX, 5], [1, 1]);
+        let result = vm.execute_code(assemble(vec!["qadd s0 s1", "rflags r0"]).unwrap());
+        assert_eq!((result, vm.s0, vm.r0), (Ok(()), [u64::MAX, 6], FLAG_CARRY));
+    }
+    #[test]
+    fn bit_manipulation_on_registers_and_lanes() {
+        let mut vm = S64::with_memory(64);
+        let mut exec = |line: &str, a: u64, b: u64| {
+            (vm.r0, vm.r1) = (a, b);
+            assert_eq!(vm.execute_code(assemble(vec![line]).unwrap()), Ok(()));
+            vm.r0
+        };
+        let x = 0x0123_4567_89AB_CDEF;
+        assert_eq!(exec("popcnt r0 r1", 0, x), 32);
+        assert_eq!(exec("clz r0 r1", 0, 1 << 40), 23);
+        assert_eq!(exec("ctz r0 r1", 0, 0), 64);
+        assert_eq!(exec("rotl r0 r1", x, 68), x.rotate_left(4));
+        assert_eq!(exec("rotr r0 r1", x, 8), 0xEF01_2345_6789_ABCD);
+        assert_eq!(exec("bswap r0 r1", 0, x), 0xEFCD_AB89_6745_2301);
+        assert_eq!(exec("bitrev r0 r1", 0, 1), 1 << 63);
+        assert_eq!(exec("bfext r0 r1 2052", 0, x), 0xDE);
+        assert_eq!(exec("bfext r0 r1 60", 0, x), 0);
+        assert_eq!(exec("bfext r0 r1 56", 0, x), 0x01);
+        assert_eq!(exec("bfins r0 r1 2052", x, 0x1FF), 0x0123_4567_89AB_CFFF);
+        assert_eq!(exec("bset r0 r1", 0, 67), 8);
+        assert_eq!(exec("bclr r0 r1", u64::MAX, 63), u64::MAX >> 1);
+        let (vm, result) = run(vec![
+            "dadd r0 zr 5",
+            "dadd r1 zr 2",
+            "btst r0 r1 1",
+            "dadd r2 zr 1",
+            "dadd r1 zr 1",
+            "btst r0 r1 1",
+            "dadd p0 zr 1",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r2, vm.p0), (0, 1));
+        let mut vm = S64::with_memory(64);
+        (vm.s0, vm.s1) = (
+            [0x0000_00FF_0000_0001, 0x8000_0000_0000_0000],
+            [x, 0x0102_0304_0506_0708],
+        );
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        assert_eq!(exec(&mut vm, "vpopcnt s2 s0 i32"), Ok(()));
+        assert_eq!(vm.s2, [8 << 32 | 1, 1 << 32]);
+        assert_eq!(exec(&mut vm, "vclz s2 s0 i16"), Ok(()));
+        assert_eq!(
+            vm.s2,
+            [16 << 48 | 8 << 32 | 16 << 16 | 15, 16 << 32 | 16 << 16 | 16]
+        );
+        assert_eq!(exec(&mut vm, "vbswap s2 s1 i16"), Ok(()));
+        assert_eq!(vm.s2, [0x2301_6745_AB89_EFCD, 0x0201_0403_0605_0807]);
+        assert_eq!(exec(&mut vm, "vrotl s0 s1 i64"), Ok(()));
+        assert_eq!(
+            vm.s0,
+            [0x0000_00FF_0000_0001u64.rotate_left(x as u32 % 64), 1 << 7]
+        );
+    }
+    #[test]
+    fn set_and_get_address_any_lane() {
+        //The lane index is the raw reg1 field, which has no assembly syntax of its own
+        let lane = |op: Opcode, reg: Register, index: u8, data: u64| {
+            Instruction::encode(op.into(), reg.into(), index, None, data).words()
+        };
+        let mut vm = S64::with_memory(64);
+        let code = [
+            lane(Opcode::Set, Register::X0, 15, 0xABCD),
+            lane(Opcode::Set, Register::R1, 3, 7),
+            lane(Opcode::Set, Register::S2, 0, 0xFFFF),
+            lane(Opcode::Get, Register::X0, 15, 0),
+        ];
+        assert_eq!(vm.execute_code(code.concat()), Ok(()));
+        assert_eq!(
+            (vm.x0, vm.r1, vm.s2),
+            ([0, 0, 0, 0xABCD << 48], 7 << 48, [0xFFFF, 0])
+        );
+        assert_eq!(vm.r0, 0xABCD);
+        for (op, reg, index, lanes) in [
+            (Opcode::Set, Register::S0, 8, 8),
+            (Opcode::Set, Register::P0, 4, 4),
+            (Opcode::Get, Register::R1, 4, 4),
+        ] {
+            let result = vm.execute_code(lane(op, reg, index as u8, 1));
+            assert_eq!(result, Err(VmError::LaneIndex { index, lanes }));
+        }
+        //Faulting lanes leave every register as it was
+        assert_eq!((vm.s0, vm.p0, vm.r0), ([0, 0], 0, 0xABCD));
+        assert_eq!(
+            vm.execute_code(assemble(vec!["vext p2 x0 i8:31"]).unwrap()),
+            Ok(())
+        );
+        assert_eq!(vm.p2, 0xAB);
+        assert_eq!(
+            vm.execute_code(assemble(vec!["vext f1 r1 i16:3"]).unwrap()),
+            Ok(())
+        );
+        assert_eq!(vm.f1, 7);
+    }
+    #[test]
+    fn conditional_moves_select_per_register_and_lane() {
+        let mut vm = S64::with_memory(64);
+        let mut exec = |line: &str, cond: u64| {
+            (vm.r0, vm.r1, vm.r2) = (1, 2, cond);
+            assert_eq!(vm.execute_code(assemble(vec![line]).unwrap()), Ok(()));
+            vm.r0
+        };
+        let minus = -5i64 as u64;
+        assert_eq!([0, 3, minus].map(|c| exec("cmovz r0 r1 r2", c)), [2, 1, 1]);
+        assert_eq!([0, 3, minus].map(|c| exec("cmovnz r0 r1 r2", c)), [1, 2, 2]);
+        assert_eq!([0, 3, minus].map(|c| exec("cmovlz r0 r1 r2", c)), [1, 1, 2]);
+        assert_eq!(
+            [0, 3, minus].map(|c| exec("cmovgez r0 r1 r2", c)),
+            [2, 2, 1]
+        );
+        let mut vm = S64::with_memory(64);
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        (vm.f0, vm.f1, vm.f2) = (1.5f64.to_bits(), 2.5f64.to_bits(), (-1f64).to_bits());
+        assert_eq!(exec(&mut vm, "cmovlz f0 f1 f2"), Ok(()));
+        assert_eq!(f64::from_bits(vm.f0), 2.5);
+        (vm.x0, vm.x1, vm.x2) = ([1, 2, 3, 4], [10, 20, 30, 40], [0, 5, minus, 0]);
+        assert_eq!(exec(&mut vm, "cmovz x0 x1 x2"), Ok(()));
+        assert_eq!(vm.x0, [10, 2, 3, 40]);
+        vm.x0 = [1, 2, 3, 4];
+        assert_eq!(exec(&mut vm, "cmovlz x0 x1 x2"), Ok(()));
+        assert_eq!(vm.x0, [1, 2, 30, 4]);
+        for (flags, taken) in [
+            (0, [false, true, false, true]),
+            (FLAG_CARRY, [true, false, false, true]),
+            (FLAG_OVERFLOW, [false, true, true, false]),
+        ] {
+            for (op, taken) in ["cmovc", "cmovnc", "cmovo", "cmovno"]
+                .into_iter()
+                .zip(taken)
+            {
+                (vm.flags, vm.x0) = (flags, [1, 2, 3, 4]);
+                assert_eq!(exec(&mut vm, &format!("{} x0 x1", op)), Ok(()));
+                let expected = if taken { vm.x1 } else { [1, 2, 3, 4] };
+                assert_eq!(vm.x0, expected, "{} {}", op, flags);
+            }
+        }
+        //Flags come from the last arithmetic op
+        let (vm, result) = run(vec![
+            "dadd r0 zr 5",
+            "dadd r1 zr 9",
+            "dadd p0 zr 1",
+            "sub r0 r1",
+            "cmovc p0 r1",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!(vm.p0, 9);
+    }
+    #[test]
+    fn relaxed_jumps_reach_their_labels() {
+        let mut lines = vec![
+            "dadd r0 zr 1",
+            "nop zr zr fwd",
+            "back:",
+            "dadd r1 zr 9",
+            "nop zr zr end",
+        ];
+        lines.extend(std::iter::repeat_n("dadd r2 zr 7", 40000));
+        lines.extend(["fwd:", "inc r0 zr back", "end:"]);
+        let (vm, result) = run(lines);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r0, vm.r1, vm.r2), (2, 9, 0));
+    }
+    #[test]
+    fn shifts_of_64_or_more_clear_the_register() {
+        let (vm, result) = run(vec![
+            "dadd r1 zr 1",
+            "dshl r0 r1 3",
+            "dshl r2 r1 64",
+            "dadd p0 zr 70",
+            "dadd p1 zr 1",
+            "shl p1 p0",
+            "dadd p2 zr 1",
+            "dshl x2 p2 100000",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r0, vm.r2, vm.p1, vm.x2), (8, 0, 0, [0; 4]));
+        let (vm, _) = run(vec![
+            "dadd r0 zr 1",
+            "dshl x2 r0 4",
+            "dshr x1 x2 2",
+            "dshr r1 r0 65",
+        ]);
+        assert_eq!((vm.x2[0], vm.x1[0], vm.x0[0], vm.r1), (16, 4, 0, 0));
+    }
+    #[test]
+    fn swap_exchanges_and_jumps() {
+        let (vm, result) = run(vec![
+            "dadd r0 zr 1",
+            "dadd r1 zr 2",
+            "swap r0 r1 1",
+            "dadd r2 zr 3",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r0, vm.r1, vm.r2), (2, 1, 0));
+    }
+    #[test]
+    fn banked_registers_keep_their_own_values() {
+        let (vm, result) = run(vec![
+            "dadd r0 zr 1",
+            "dadd r1 zr 2",
+            "dadd r2 zr 3",
+            "dadd r3 zr 5",
+            "dadd r31 zr 7",
+            "add r30 r3 r31",
+            "dadd f3 zr 2",
+            "mul f31 f3",
+            "dadd p0 r30 0",
+            "dadd p1 r3 0",
+        ]);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r0, vm.r1, vm.r2), (1, 2, 3));
+        assert_eq!((vm.p0, vm.p1), (12, 5));
+        let banked = |name: &str| vm.banked[register::index(name).unwrap() as usize - 16];
+        assert_eq!(
+            (banked("r3")[0], banked("r31")[0], banked("r30")[0]),
+            (5, 7, 12)
+        );
+        assert_eq!(banked("f31")[0], 0f64.to_bits());
+        assert_eq!(banked("f3")[0], 2f64.to_bits());
+        let mut vm = S64::with_memory(64);
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        (vm.s0, vm.s1, vm.s2) = ([1, 1], [2, 2], [u64::MAX, 0]);
+        assert_eq!(exec(&mut vm, "update s3 s1"), Ok(()));
+        assert_eq!(exec(&mut vm, "update s7 s0"), Ok(()));
+        //A mask register can't be the slot a banked register stands in
+        assert_eq!(exec(&mut vm, "vadd s3 s7 i64/s2"), Ok(()));
+        assert_eq!(exec(&mut vm, "update x7 s3"), Ok(()));
+        assert_eq!(
+            vm.banked[register::index("x7").unwrap() as usize - 16],
+            [3, 2, 0, 0]
+        );
+        assert_eq!((vm.s0, vm.s1, vm.s2), ([1, 1], [2, 2], [u64::MAX, 0]));
+        //The index and mask registers leave one compact S register for two banked ones
+        let result = exec(&mut vm, "vgather s3 s4 i32:s0/s1");
+        assert_eq!(result, Err(VmError::BankConflict));
+        assert_eq!((vm.s0, vm.s1, vm.s2), ([1, 1], [2, 2], [u64::MAX, 0]));
+    }
+    fn block_vm(setup: &[(u64, Vec<u8>)], code: Vec<&str>) -> S64 {
+        let mut vm = S64::with_memory(1 << 16);
+        for (address, bytes) in setup {
+            vm.mem.write(*address, bytes).unwrap();
+        }
+        vm.load(Image {
+            base: 0,
+            entry: 0,
+            code: assemble(code).unwrap(),
+        });
+        vm
+    }
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|k| (k % 251) as u8).collect()
+    }
+    #[test]
+    fn block_ops_resume_in_chunks() {
+        let code = vec![
+            "dadd r0 zr 20000",
+            "dadd r1 zr 0",
+            "dadd r2 zr 10000",
+            "memcpy r0 r1 r2",
+        ];
+        let mut vm = block_vm(&[(0, pattern(10000))], code);
+        assert!(vm.run_for(4).is_ok());
+        assert_eq!((vm.r0, vm.r1, vm.r2), (20000 + 4096, 4096, 10000 - 4096));
+        assert_eq!(
+            vm.mem.read(20000 + 4095, 2),
+            Ok(vec![(4095 % 251) as u8, 0])
+        );
+        assert!(vm.run_for(1).is_ok());
+        assert_eq!(vm.r2, 10000 - 8192);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!((vm.r0, vm.r1, vm.r2), (30000, 10000, 0));
+        assert_eq!(vm.mem.read(20000, 10000), Ok(pattern(10000)));
+    }
+    #[test]
+    fn memmove_copies_overlap_back_to_front() {
+        for (dst, src) in [(100, 0), (0, 100), (4097, 1)] {
+            let (r0, r1) = (format!("dadd r0 zr {}", dst), format!("dadd r1 zr {}", src));
+            let code = vec![r0.as_str(), &r1, "dadd r2 zr 10000", "memmove r0 r1 r2"];
+            let mut vm = block_vm(&[(src, pattern(10000))], code);
+            assert_eq!(vm.run(), Ok(()));
+            assert_eq!(
+                vm.mem.read(dst, 10000),
+                Ok(pattern(10000)),
+                "{} {}",
+                dst,
+                src
+            );
+            assert_eq!(vm.r2, 0);
+        }
+    }
+    #[test]
+    fn memcmp_and_memchr_stop_at_the_byte() {
+        let mut b = pattern(9000);
+        b[5000] = 255;
+        let code = vec![
+            "dadd r0 zr 0",
+            "dadd r1 zr 10000",
+            "dadd r2 zr 9000",
+            "memcmp r0 r1 r2",
+        ];
+        let mut vm = block_vm(&[(0, pattern(9000)), (10000, b)], code);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!(
+            (vm.r0, vm.r1, vm.r2, vm.flags),
+            (5000, 15000, 4000, FLAG_CARRY)
+        );
+        let code = vec![
+            "dadd r0 zr 0",
+            "dadd r1 zr 10000",
+            "dadd r2 zr 9000",
+            "memcmp r0 r1 r2",
+        ];
+        let mut vm = block_vm(&[(0, pattern(9000)), (10000, pattern(9000))], code);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!((vm.r0, vm.r2, vm.flags), (9000, 0, 0));
+        let mut bytes = vec![0; 9000];
+        bytes[6000] = 7;
+        let code = vec![
+            "dadd r0 zr 100",
+            "dadd r1 zr 7",
+            "dadd r2 zr 9000",
+            "memchr r0 r1 r2",
+        ];
+        let mut vm = block_vm(&[(100, bytes)], code);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!((vm.r0, vm.r2), (6100, 3000));
+        let code = vec![
+            "dadd r0 zr 100",
+            "dadd r1 zr 9",
+            "dadd r2 zr 9000",
+            "memchr r0 r1 r2",
+        ];
+        let mut vm = block_vm(&[], code);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!((vm.r0, vm.r2), (9100, 0));
+    }
+    #[test]
+    fn empty_block_ops_do_nothing() {
+        for op in ["memcpy", "memmove", "memset", "memcmp", "memchr"] {
+            let line = format!("{} r0 r1 r2", op);
+            let code = vec!["dadd r0 zr 65536", "dadd r1 zr 65536", &line];
+            let mut vm = block_vm(&[], code);
+            assert_eq!(vm.run(), Ok(()), "{}", op);
+            assert_eq!((vm.r0, vm.r1, vm.r2), (65536, 65536, 0), "{}", op);
+        }
+        let code = vec!["dadd r0 zr 65536", "dadd r2 zr 1", "memset r0 r1 r2"];
+        let mut vm = block_vm(&[], code);
+        assert_eq!(vm.run(), Err(VmError::BadAddress(65536)));
+    }
+    #[test]
+    fn atomics_on_aligned_words() {
+        let mut vm = S64::with_memory(64);
+        let exec = |vm: &mut S64, line: &str| vm.execute_code(assemble(vec![line]).unwrap());
+        let word = |vm: &S64| u64::from_le_bytes(vm.mem.read(8, 8).unwrap().try_into().unwrap());
+        (vm.r0, vm.r1) = (0b1100, 8);
+        assert_eq!(exec(&mut vm, "ast r0 r1 2"), Ok(()));
+        assert_eq!(exec(&mut vm, "ald r2 r1 1"), Ok(()));
+        assert_eq!(vm.r2, 0b1100);
+        for (op, operand, old, new) in [
+            ("aadd", 3, 0b1100, 0b1111),
+            ("aand", 0b0110, 0b1111, 0b0110),
+            ("aor", 0b1001, 0b0110, 0b1111),
+            ("axor", 0b0101, 0b1111, 0b1010),
+            ("aswp", 42, 0b1010, 42),
+        ] {
+            vm.r0 = operand;
+            assert_eq!(exec(&mut vm, &format!("{} r0 r1 4", op)), Ok(()));
+            assert_eq!((vm.r0, word(&vm)), (old, new), "{}", op);
+        }
+        (vm.r0, vm.r2) = (41, 7);
+        assert_eq!(exec(&mut vm, "acas r0 r1 r2"), Ok(()));
+        assert_eq!((vm.r0, word(&vm), vm.flags), (42, 42, 0));
+        assert_eq!(exec(&mut vm, "acas r0 r1 r2"), Ok(()));
+        assert_eq!((vm.r0, word(&vm), vm.flags), (42, 7, FLAG_CARRY));
+        for order in 0..6 {
+            assert_eq!(exec(&mut vm, &format!("fence zr zr {}", order)), Ok(()));
+        }
+        //Faults leave the register and memory alone
+        for address in [12, 64, u64::MAX - 7] {
+            let err = match address {
+                12 => VmError::Misaligned(address),
+                _ => VmError::BadAddress(address),
+            };
+            for op in ["ald", "ast", "aadd", "acas"] {
+                (vm.r0, vm.r1) = (5, address);
+                let result = exec(&mut vm, &format!("{} r0 r1 r2", op));
+                assert_eq!(result.as_ref(), Err(&err), "{} {}", op, address);
+                assert_eq!((vm.r0, word(&vm)), (5, 7));
+            }
+        }
+    }
+    //Trap table at 1024 with a handler label on each cause
+    fn with_handlers(handlers: &[(u64, &str)], body: &[&str]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (cause, label) in handlers {
+            lines.push(format!("adr r1 zr {}", label));
+            lines.push(format!("dadd r2 zr {}", 1024 + trap::VECTORS + 8 * cause));
+            lines.push("ast r1 r2 0".to_string());
+        }
+        lines.extend(["dadd r0 zr 1024", "tvec r0 zr"].map(String::from));
+        lines.extend(body.iter().map(|v| v.to_string()));
+        lines
+    }
+    fn run_lines(lines: &[String]) -> (S64, Result<(), VmError>) {
+        run(lines.iter().map(String::as_str).collect())
+    }
+    #[test]
+    fn traps_enter_the_handler_and_skip() {
+        let lines = with_handlers(
+            &[(2, "handler")],
+            &[
+                "ddiv r0 r0 0",
+                "dadd p0 zr 77",
+                "nop zr zr end",
+                "handler:",
+                "dadd r2 zr 1040",
+                "ald p1 r2 0",
+                "dadd r2 zr 1032",
+                "ald p2 r2 0",
+                "dadd r2 zr 1024",
+                "ast p2 r2 0",
+                "rtt",
+                "end:",
+            ],
+        );
+        let (vm, result) = run_lines(&lines);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.r0, vm.p0, vm.p1, vm.p2), (1024, 77, 2, 6));
+        assert_eq!(vm.mem.read(1024, 8), Ok(6u64.to_le_bytes().to_vec()));
+        assert!(!vm.in_trap);
+    }
+    #[test]
+    fn rtt_retries_the_faulting_instruction() {
+        let lines = with_handlers(
+            &[(4, "handler")],
+            &[
+                "dadd r1 zr 3",
+                "ald p0 r1 0",
+                "nop zr zr end",
+                "handler:",
+                "dadd r2 zr 1048",
+                "ald p1 r2 0",
+                "dadd r1 zr 1024",
+                "rtt",
+                "end:",
+            ],
+        );
+        let (vm, result) = run_lines(&lines);
+        assert_eq!(result, Ok(()));
+        //The retried load reads the frame pc of the handled fault
+        assert_eq!((vm.p0, vm.p1), (6, 3));
+    }
+    #[test]
+    fn faults_in_handlers_and_stray_rtt_stop_the_run() {
+        let lines = with_handlers(
+            &[(2, "handler")],
+            &["ddiv r0 r0 0", "handler:", "dmod r0 r0 0"],
+        );
+        let (_, result) = run_lines(&lines);
+        assert_eq!(
+            result,
+            Err(VmError::DoubleFault(Box::new(VmError::DivideByZero)))
+        );
+        let lines = with_handlers(
+            &[(2, "handler")],
+            &["dadd r1 zr 1", "ald r0 r1 0", "handler:"],
+        );
+        assert_eq!(run_lines(&lines).1, Err(VmError::Misaligned(1)));
+        assert_eq!(
+            run(vec!["rtt"]).1,
+            Err(VmError::IllegalOpcode(Opcode::Rtt.into()))
+        );
+        let (vm, result) = run(vec!["ddiv r0 r0 0", "dadd r1 zr 1"]);
+        assert_eq!((result, vm.r1), (Err(VmError::DivideByZero), 0));
+    }
+    const TIMER_CAUSE: u64 = trap::INTERRUPTS + interrupt::TIMER as u64;
+    //Interrupt handler counting in p1 and keeping r0 of the interrupted code in p0
+    const COUNT: [&str; 4] = ["count:", "dadd p1 p1 1", "dadd p0 r0 0", "rtt"];
+
+    fn interrupt_lines(handlers: &[(u64, &str)], setup: &[&str], mask: u64) -> Vec<String> {
+        let mask = format!("dadd r1 zr {}", mask);
+        let mut body = vec![mask.as_str(), "imask r1 zr", "dadd r1 zr 1", "ie r1 zr"];
+        body.extend(setup);
+        body.extend(["dadd r0 zr 0"]);
+        body.extend(std::iter::repeat_n("dadd r0 r0 1", 10));
+        body.extend(["ipend p2 zr", "nop zr zr end"]);
+        body.extend(COUNT);
+        body.push("end:");
+        with_handlers(handlers, &body)
+    }
+    #[test]
+    fn timer_interrupts_between_instructions() {
+        let lines = interrupt_lines(
+            &[(TIMER_CAUSE, "count")],
+            &["dadd r1 zr 4", "timer r1 zr"],
+            1,
+        );
+        let (vm, result) = run_lines(&lines);
+        assert_eq!(result, Ok(()));
+        //Armed for 4 ticks, it fires after the increment setting r0 to 2
+        assert_eq!((vm.p1, vm.p0, vm.r0, vm.p2), (1, 2, 10, 0));
+        assert_eq!(
+            vm.mem.read(1040, 16),
+            Ok([TIMER_CAUSE, 0].map(u64::to_le_bytes).concat())
+        );
+        //Masked, the timer only shows up as pending
+        let lines = interrupt_lines(
+            &[(TIMER_CAUSE, "count")],
+            &["dadd r1 zr 4", "timer r1 zr"],
+            2,
+        );
+        let (vm, result) = run_lines(&lines);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.p1, vm.r0, vm.p2), (0, 10, 1));
+    }
+    #[test]
+    fn interrupts_wait_for_the_end_of_a_handler() {
+        //The timer fires during the fault handler and is taken after its rtt
+        let mut setup = vec![
+            "dadd r1 zr 2",
+            "timer r1 zr",
+            "ddiv r0 r0 0",
+            "nop zr zr done",
+        ];
+        setup.extend([
+            "fault:",
+            "dadd r0 zr 40",
+            "dadd r0 r0 1",
+            "dadd r2 zr 1032",
+            "ald p2 r2 0",
+            "dadd r2 zr 1024",
+            "ast p2 r2 0",
+            "rtt",
+            "done:",
+        ]);
+        let lines = interrupt_lines(&[(TIMER_CAUSE, "count"), (2, "fault")], &setup, 1);
+        let (vm, result) = run_lines(&lines);
+        assert_eq!(result, Ok(()));
+        assert_eq!((vm.p1, vm.p0), (1, 41));
+    }
+    #[test]
+    fn lines_without_a_handler_dont_starve_higher_ones() {
+        let lines = interrupt_lines(&[(trap::INTERRUPTS + 5, "count")], &[], 1 << 3 | 1 << 5);
+        let mut vm = S64::with_memory(1 << 16);
+        let irq = vm.irq.handle();
+        irq.raise(3);
+        irq.raise(5);
+        let code = assemble(lines.iter().map(String::as_str).collect()).unwrap();
+        assert_eq!(vm.execute_code(code), Ok(()));
+        //Line 3 is still pending and the guest's mask is left as it was
+        assert_eq!((vm.p1, vm.p2, vm.irq.mask), (1, 1 << 3, 1 << 3 | 1 << 5));
+        //and is taken once it has a handler
+        let handlers = [
+            (trap::INTERRUPTS + 3, "count"),
+            (trap::INTERRUPTS + 5, "count"),
+        ];
+        let lines = interrupt_lines(&handlers, &[], 1 << 3 | 1 << 5);
+        let code = assemble(lines.iter().map(String::as_str).collect()).unwrap();
+        assert_eq!(vm.execute_code(code), Ok(()));
+        assert_eq!((vm.p1, vm.p2), (2, 0));
+    }
+    #[test]
+    fn host_virtual_time_drives_the_timer() {
+        let lines = interrupt_lines(
+            &[(TIMER_CAUSE, "count")],
+            &["dadd r1 zr 100", "timer r1 zr 1"],
+            1,
+        );
+        let mut vm = S64::with_memory(1 << 16);
+        let code = assemble(lines.iter().map(String::as_str).collect()).unwrap();
+        vm.load(Image {
+            base: 0,
+            entry: 0,
+            code,
+        });
+        let irq = vm.irq.handle();
+        //Through the timer instruction
+        assert!(vm.run_for(11).is_ok());
+        assert_eq!(vm.p1, 0);
+        irq.advance(99);
+        assert!(vm.run_for(1).is_ok());
+        assert_eq!(vm.p1, 0);
+        irq.advance(1);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!(vm.p1, 1);
+    }
 }
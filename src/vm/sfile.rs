@@ -1,6 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+use crate::instruction::Instruction;
+
+#[derive(Serialize, Deserialize)]
 pub(crate) struct SFileHeader {
     magic: [u8; 2], //SF
     filetype: u8,
@@ -23,12 +25,362 @@ impl SFileHeader {
             version,
         }
     }
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, SFileError> {
+        Self::read(&mut Reader { bytes, pos: 0 })
+    }
+    fn read(reader: &mut Reader) -> Result<Self, SFileError> {
+        Ok(Self {
+            magic: [reader.u8()?, reader.u8()?],
+            filetype: reader.u8()?,
+            arch: reader.u8()?,
+            version: reader.u8()?,
+        })
+    }
+    pub(crate) fn magic(&self) -> [u8; 2] {
+        self.magic
+    }
+    pub(crate) fn filetype(&self) -> u8 {
+        self.filetype
+    }
+    pub(crate) fn arch(&self) -> u8 {
+        self.arch
+    }
+    pub(crate) fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+/*
+Layout (little-endian):
+header: magic[2] filetype arch version
+counts: sections u16, symbols u16, relocations u16, debug lines u16
+section: name, kind u8, address u64, size u32, offset u32 (file offset of data)
+symbol: name, section u16, offset u32, binding u8
+relocation: section u16, offset u32, symbol u16, kind u8
+debug line: section u16, offset u32, line u32, file
+strings are u16 length + utf8, offsets inside sections are bytes
+*/
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SFile {
+    pub header: SFileHeader,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+    pub debug: Vec<DebugLine>,
+}
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "SectionRepr", try_from = "SectionRepr")]
+pub(crate) struct Section {
+    pub name: String,
+    pub kind: u8,
+    pub address: u64, //Load address in bytes
+    pub data: Vec<u8>,
+}
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Symbol {
+    pub name: String,
+    pub section: u16,
+    pub offset: u32,
+    pub binding: u8,
+}
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Relocation {
+    pub section: u16,
+    pub offset: u32, //Instruction whose data field gets patched
+    pub symbol: u16,
+    pub kind: u8,
+}
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DebugLine {
+    pub section: u16,
+    pub offset: u32,
+    pub line: u32,
+    pub file: String,
+}
+
+//JSON form of a section, code sections are written as decoded instructions
+//unless some word wouldn't read back identically (undefined opcodes, padding, data)
+#[derive(Serialize, Deserialize)]
+struct SectionRepr {
+    name: String,
+    kind: u8,
+    address: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<Vec<Instruction>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<Vec<u8>>,
+}
+
+impl From<Section> for SectionRepr {
+    fn from(val: Section) -> Self {
+        let decode = val.kind == u8::from(SectionKind::Code) && val.data.len().is_multiple_of(4);
+        let code = decode
+            .then(|| {
+                let words = val.words();
+                let code: Vec<Instruction> = Instruction::decode_all(&words)
+                    .into_iter()
+                    .map(|v| v.1)
+                    .collect();
+                code.iter()
+                    .map(Instruction::json_words)
+                    .collect::<Option<Vec<_>>>()
+                    .is_some_and(|v| v.concat() == words)
+                    .then_some(code)
+            })
+            .flatten();
+        Self {
+            data: code.is_none().then_some(val.data),
+            code,
+            name: val.name,
+            kind: val.kind,
+            address: val.address,
+        }
+    }
+}
+impl TryFrom<SectionRepr> for Section {
+    type Error = String;
+    fn try_from(val: SectionRepr) -> Result<Self, Self::Error> {
+        let data = match (val.code, val.data) {
+            (Some(_), Some(_)) => {
+                return Err(format!(
+                    "section {}: expected either code or data, not both",
+                    val.name
+                ))
+            }
+            (Some(code), None) => code
+                .into_iter()
+                .flat_map(|v| v.words())
+                .flat_map(u32::to_le_bytes)
+                .collect(),
+            (None, data) => data.unwrap_or_default(),
+        };
+        Ok(Self {
+            name: val.name,
+            kind: val.kind,
+            address: val.address,
+            data,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SFileError {
+    Truncated(usize),          //Input ends inside a field starting at this offset
+    BadString(usize),          //String at this offset isn't utf8
+    SectionOutOfBounds(usize), //Section data lies outside the file (section index)
+    TooMany(usize),            //Table has more entries than a u16 count holds
+    TooLong(usize),            //String is longer than a u16 length holds
+    TooLarge(usize),           //File is larger than a u32 size/offset holds
+}
+
+impl SFile {
+    pub(crate) fn new(header: SFileHeader) -> Self {
+        Self {
+            header,
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+            debug: Vec::new(),
+        }
+    }
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, SFileError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        let header = SFileHeader::read(&mut reader)?;
+        let section_count = reader.u16()?;
+        let symbol_count = reader.u16()?;
+        let relocation_count = reader.u16()?;
+        let debug_count = reader.u16()?;
+        let mut file = SFile::new(header);
+        for index in 0..section_count as usize {
+            let name = reader.string()?;
+            let kind = reader.u8()?;
+            let address = reader.u64()?;
+            let size = reader.u32()? as usize;
+            let offset = reader.u32()? as usize;
+            let data = bytes
+                .get(offset..offset.saturating_add(size))
+                .ok_or(SFileError::SectionOutOfBounds(index))?
+                .to_vec();
+            file.sections.push(Section {
+                name,
+                kind,
+                address,
+                data,
+            });
+        }
+        for _ in 0..symbol_count {
+            file.symbols.push(Symbol {
+                name: reader.string()?,
+                section: reader.u16()?,
+                offset: reader.u32()?,
+                binding: reader.u8()?,
+            });
+        }
+        for _ in 0..relocation_count {
+            file.relocations.push(Relocation {
+                section: reader.u16()?,
+                offset: reader.u32()?,
+                symbol: reader.u16()?,
+                kind: reader.u8()?,
+            });
+        }
+        for _ in 0..debug_count {
+            file.debug.push(DebugLine {
+                section: reader.u16()?,
+                offset: reader.u32()?,
+                line: reader.u32()?,
+                file: reader.string()?,
+            });
+        }
+        Ok(file)
+    }
+    pub(crate) fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+    pub(crate) fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, SFileError> {
+        let mut table = Vec::new();
+        table.extend_from_slice(&self.header.magic);
+        table.extend_from_slice(&[self.header.filetype, self.header.arch, self.header.version]);
+        for count in [
+            self.sections.len(),
+            self.symbols.len(),
+            self.relocations.len(),
+            self.debug.len(),
+        ] {
+            let count = u16::try_from(count).map_err(|_| SFileError::TooMany(count))?;
+            table.extend_from_slice(&count.to_le_bytes());
+        }
+        //Section data goes after all tables, so the table size is needed up front
+        let mut size = table.len();
+        size += self
+            .sections
+            .iter()
+            .map(|v| 2 + v.name.len() + 17)
+            .sum::<usize>();
+        size += self
+            .symbols
+            .iter()
+            .map(|v| 2 + v.name.len() + 7)
+            .sum::<usize>();
+        size += self.relocations.len() * 9;
+        size += self
+            .debug
+            .iter()
+            .map(|v| 10 + 2 + v.file.len())
+            .sum::<usize>();
+        let mut offset = size;
+        for section in &self.sections {
+            write_string(&mut table, &section.name)?;
+            table.push(section.kind);
+            table.extend_from_slice(&section.address.to_le_bytes());
+            let end = offset + section.data.len();
+            u32::try_from(end).map_err(|_| SFileError::TooLarge(end))?;
+            table.extend_from_slice(&(section.data.len() as u32).to_le_bytes());
+            table.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset = end;
+        }
+        for symbol in &self.symbols {
+            write_string(&mut table, &symbol.name)?;
+            table.extend_from_slice(&symbol.section.to_le_bytes());
+            table.extend_from_slice(&symbol.offset.to_le_bytes());
+            table.push(symbol.binding);
+        }
+        for relocation in &self.relocations {
+            table.extend_from_slice(&relocation.section.to_le_bytes());
+            table.extend_from_slice(&relocation.offset.to_le_bytes());
+            table.extend_from_slice(&relocation.symbol.to_le_bytes());
+            table.push(relocation.kind);
+        }
+        for line in &self.debug {
+            table.extend_from_slice(&line.section.to_le_bytes());
+            table.extend_from_slice(&line.offset.to_le_bytes());
+            table.extend_from_slice(&line.line.to_le_bytes());
+            write_string(&mut table, &line.file)?;
+        }
+        for section in &self.sections {
+            table.extend_from_slice(&section.data);
+        }
+        Ok(table)
+    }
+}
+impl Section {
+    //Code sections hold little-endian u32 instructions
+    pub(crate) fn words(&self) -> Vec<u32> {
+        self.data
+            .chunks_exact(4)
+            .map(|v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+            .collect()
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], SFileError> {
+        let start = self.pos;
+        let slice = self
+            .bytes
+            .get(start..start + len)
+            .ok_or(SFileError::Truncated(start))?;
+        self.pos += len;
+        Ok(slice)
+    }
+    fn u8(&mut self) -> Result<u8, SFileError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, SFileError> {
+        let v = self.take(2)?;
+        Ok(u16::from_le_bytes([v[0], v[1]]))
+    }
+    fn u32(&mut self) -> Result<u32, SFileError> {
+        let v = self.take(4)?;
+        Ok(u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+    }
+    fn u64(&mut self) -> Result<u64, SFileError> {
+        let v = self.take(8)?;
+        Ok(u64::from_le_bytes([
+            v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7],
+        ]))
+    }
+    fn string(&mut self) -> Result<String, SFileError> {
+        let len = self.u16()? as usize;
+        let start = self.pos;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| SFileError::BadString(start))
+    }
+}
+fn write_string(out: &mut Vec<u8>, val: &str) -> Result<(), SFileError> {
+    let len = u16::try_from(val.len()).map_err(|_| SFileError::TooLong(val.len()))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(val.as_bytes());
+    Ok(())
 }
 pub(crate) enum FileType {
     Executable,
     Shared,
 }
 
+pub(crate) enum SectionKind {
+    Code,
+    Data,
+    Bss,
+}
+
+pub(crate) enum Binding {
+    Local,
+    Global,
+}
+
+pub(crate) enum RelocationKind {
+    Abs16, //data = symbol address
+    Rel16, //data = symbol address - (instruction address + 1)
+}
+
 pub(crate) enum Arch {
     SS64,
     IA32,
@@ -90,3 +442,175 @@ impl From<Arch> for u8 {
         }
     }
 }
+impl From<u8> for Arch {
+    fn from(val: u8) -> Self {
+        match val {
+            0 => Arch::SS64,
+            1 => Arch::IA32,
+            2 => Arch::AMD64,
+            3 => Arch::ARM32,
+            4 => Arch::ARM64,
+            5 => Arch::WASM32,
+            6 => Arch::WASM64,
+            _ => Arch::SS64,
+        }
+    }
+}
+impl From<Arch> for &str {
+    fn from(val: Arch) -> Self {
+        match val {
+            Arch::SS64 => "ss64",
+            Arch::IA32 => "ia32",
+            Arch::AMD64 => "amd64",
+            Arch::ARM32 => "arm32",
+            Arch::ARM64 => "arm64",
+            Arch::WASM32 => "wasm32",
+            Arch::WASM64 => "wasm64",
+        }
+    }
+}
+impl From<FileType> for &str {
+    fn from(val: FileType) -> Self {
+        match val {
+            FileType::Executable => "executable",
+            FileType::Shared => "shared",
+        }
+    }
+}
+
+impl From<u8> for SectionKind {
+    fn from(val: u8) -> Self {
+        match val {
+            0 => SectionKind::Code,
+            1 => SectionKind::Data,
+            2 => SectionKind::Bss,
+            _ => SectionKind::Data,
+        }
+    }
+}
+impl From<SectionKind> for u8 {
+    fn from(val: SectionKind) -> Self {
+        match val {
+            SectionKind::Code => 0,
+            SectionKind::Data => 1,
+            SectionKind::Bss => 2,
+        }
+    }
+}
+impl From<SectionKind> for &str {
+    fn from(val: SectionKind) -> Self {
+        match val {
+            SectionKind::Code => "code",
+            SectionKind::Data => "data",
+            SectionKind::Bss => "bss",
+        }
+    }
+}
+
+impl From<u8> for Binding {
+    fn from(val: u8) -> Self {
+        match val {
+            0 => Binding::Local,
+            1 => Binding::Global,
+            _ => Binding::Local,
+        }
+    }
+}
+impl From<Binding> for u8 {
+    fn from(val: Binding) -> Self {
+        match val {
+            Binding::Local => 0,
+            Binding::Global => 1,
+        }
+    }
+}
+impl From<Binding> for &str {
+    fn from(val: Binding) -> Self {
+        match val {
+            Binding::Local => "local",
+            Binding::Global => "global",
+        }
+    }
+}
+
+impl From<u8> for RelocationKind {
+    fn from(val: u8) -> Self {
+        match val {
+            0 => RelocationKind::Abs16,
+            1 => RelocationKind::Rel16,
+            _ => RelocationKind::Abs16,
+        }
+    }
+}
+impl From<RelocationKind> for u8 {
+    fn from(val: RelocationKind) -> Self {
+        match val {
+            RelocationKind::Abs16 => 0,
+            RelocationKind::Rel16 => 1,
+        }
+    }
+}
+impl From<RelocationKind> for &str {
+    fn from(val: RelocationKind) -> Self {
+        match val {
+            RelocationKind::Abs16 => "abs16",
+            RelocationKind::Rel16 => "rel16",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compiler::assemble, objdump::inspect, output::emit_sfile};
+    fn json_round_trip(file: &SFile) -> Vec<u8> {
+        let text = file.to_json().unwrap();
+        SFile::from_json(&text).unwrap().to_bytes().unwrap()
+    }
+    #[test]
+    fn oversized_tables_are_rejected() {
+        let mut file = emit_sfile(&[1, 2], 0, 0).unwrap();
+        assert!(SFile::from_bytes(&file.to_bytes().unwrap()).is_ok());
+        file.sections[0].name = "x".repeat(65536);
+        assert_eq!(file.to_bytes().err(), Some(SFileError::TooLong(65536)));
+        file.sections[0].name = ".text".to_string();
+        let count = 65536 - file.symbols.len();
+        file.symbols.extend((0..count).map(|_| Symbol {
+            name: String::new(),
+            section: 0,
+            offset: 0,
+            binding: 0,
+        }));
+        assert_eq!(file.to_bytes().err(), Some(SFileError::TooMany(65536)));
+    }
+    #[test]
+    fn objdump_wraps_addresses_near_the_top() {
+        let mut file = emit_sfile(&[1, 2], 0, 0).unwrap();
+        file.sections[0].address = u64::MAX - 3;
+        let text = inspect(&file.to_bytes().unwrap());
+        assert!(text.contains("fffffffffffffffc"), "{}", text);
+        assert!(text.contains("       0:"), "{}", text);
+    }
+    #[test]
+    fn json_keeps_code_sections_exact() {
+        let code = assemble(vec!["dadd r0 zr 5", "dadd r20 r1 100000", "jmp zr 0"]).unwrap();
+        let file = emit_sfile(&code, 0x40, 0x40).unwrap();
+        let bytes = file.to_bytes().unwrap();
+        assert!(file.to_json().unwrap().contains("\"code\""));
+        assert_eq!(json_round_trip(&file), bytes);
+        //Undefined opcodes, padding and a truncated extended form fall back to raw data
+        for extra in [
+            [0x0000_00D4, 0],
+            [0xDEAD_BEEF, 0xFFFF_FFFF],
+            [0, 0x0002_1AFF],
+        ] {
+            let mut file = emit_sfile(&code, 0x40, 0x40).unwrap();
+            file.sections[0]
+                .data
+                .extend(extra.iter().flat_map(|v: &u32| v.to_le_bytes()));
+            let bytes = file.to_bytes().unwrap();
+            assert!(file.to_json().unwrap().contains("\"data\""), "{:x?}", extra);
+            assert_eq!(json_round_trip(&file), bytes, "{:x?}", extra);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+/*
+Host SIMD (std::simd) versions of the integer vector operations.
+Registers are processed as full 32 byte vectors and lanes past len are zeroed afterwards.
+Float lanes stay on the soft-float path in vector.rs since host SIMD has no rounding
+modes or exception flags, so do i64 widening ops (no 128-bit lanes) and data movement.
+The scalar versions in vector.rs are the reference, the tests check both agree.
+*/
+
+use std::simd::{prelude::*, ToBytes};
+
+use super::vector::{Bytes, CompareOp, ReduceOp, VectorOp};
+use crate::lane::LaneType;
+
+macro_rules! lanes {
+    ($name:ident, $u:ty, $i:ty, $n:literal) => {
+        mod $name {
+            use super::*;
+
+            type U = Simd<$u, $n>;
+
+            fn load(bytes: &Bytes) -> U {
+                U::from_le_bytes(u8x32::from_array(*bytes))
+            }
+            fn store(val: U) -> Bytes {
+                val.to_le_bytes().to_array()
+            }
+            pub(super) fn binary(op: VectorOp, a: &Bytes, b: &Bytes) -> Option<Bytes> {
+                let (x, y) = (load(a), load(b));
+                let (sx, sy) = (x.cast::<$i>(), y.cast::<$i>());
+                Some(store(match op {
+                    VectorOp::Add => x + y,
+                    VectorOp::Sub => x - y,
+                    VectorOp::Mul => x * y,
+                    VectorOp::AddSat => sx.saturating_add(sy).cast(),
+                    VectorOp::SubSat => sx.saturating_sub(sy).cast(),
+                    VectorOp::AddSatU => x.saturating_add(y),
+                    VectorOp::SubSatU => x.saturating_sub(y),
+                    //Widening ops are handled by widen
+                    VectorOp::AddWide
+                    | VectorOp::AddWideU
+                    | VectorOp::MulWide
+                    | VectorOp::MulWideU => return None,
+                }))
+            }
+            pub(super) fn compare(op: CompareOp, a: &Bytes, b: &Bytes) -> Bytes {
+                let (x, y) = (load(a), load(b));
+                let (sx, sy) = (x.cast::<$i>(), y.cast::<$i>());
+                let holds = match op {
+                    CompareOp::Eq => x.simd_eq(y),
+                    CompareOp::Ne => x.simd_ne(y),
+                    CompareOp::Lt => sx.simd_lt(sy),
+                    CompareOp::Le => sx.simd_le(sy),
+                    CompareOp::LtU => x.simd_lt(y),
+                    CompareOp::LeU => x.simd_le(y),
+                };
+                store(holds.select(U::splat(<$u>::MAX), U::splat(0)))
+            }
+            pub(super) fn select(mask: &Bytes, a: &Bytes, b: &Bytes) -> Bytes {
+                store(load(mask).simd_ne(U::splat(0)).select(load(b), load(a)))
+            }
+            pub(super) fn reduce(op: ReduceOp, a: &Bytes, lanes: usize) -> u64 {
+                let x = load(a);
+                let index = U::from_array(std::array::from_fn(|k| k as $u));
+                let active = index.simd_lt(U::splat(lanes as $u));
+                let sx = x.cast::<$i>();
+                match op {
+                    ReduceOp::Sum => active.select(x, U::splat(0)).reduce_sum() as u64,
+                    ReduceOp::Min => {
+                        active.select(sx, Simd::splat(<$i>::MAX)).reduce_min() as $u as u64
+                    }
+                    ReduceOp::Max => {
+                        active.select(sx, Simd::splat(<$i>::MIN)).reduce_max() as $u as u64
+                    }
+                    ReduceOp::MinU => active.select(x, U::splat(<$u>::MAX)).reduce_min() as u64,
+                    ReduceOp::MaxU => active.select(x, U::splat(0)).reduce_max() as u64,
+                    ReduceOp::Any => (active & x.simd_ne(U::splat(0))).any() as u64,
+                    ReduceOp::All => (!active | x.simd_ne(U::splat(0))).all() as u64,
+                }
+            }
+        }
+    };
+}
+
+//Low half lanes of a and b widened to twice their size
+macro_rules! widen {
+    ($name:ident, $u:ty, $i:ty, $n:literal, $wu:ty, $wi:ty, $h:literal) => {
+        fn $name(op: VectorOp, a: &Bytes, b: &Bytes) -> Option<Bytes> {
+            let load =
+                |v: &Bytes| Simd::<$u, $n>::from_le_bytes(u8x32::from_array(*v)).resize::<$h>(0);
+            let (x, y) = (load(a), load(b));
+            let (sx, sy) = (x.cast::<$i>().cast::<$wi>(), y.cast::<$i>().cast::<$wi>());
+            let (ux, uy) = (x.cast::<$wu>(), y.cast::<$wu>());
+            let val: Simd<$wu, $h> = match op {
+                VectorOp::AddWide => (sx + sy).cast(),
+                VectorOp::MulWide => (sx * sy).cast(),
+                VectorOp::AddWideU => ux + uy,
+                VectorOp::MulWideU => ux * uy,
+                VectorOp::Add
+                | VectorOp::Sub
+                | VectorOp::Mul
+                | VectorOp::AddSat
+                | VectorOp::AddSatU
+                | VectorOp::SubSat
+                | VectorOp::SubSatU => return None,
+            };
+            Some(val.to_le_bytes().to_array())
+        }
+    };
+}
+
+lanes!(lanes8, u8, i8, 32);
+lanes!(lanes16, u16, i16, 16);
+lanes!(lanes32, u32, i32, 8);
+lanes!(lanes64, u64, i64, 4);
+widen!(widen8, u8, i8, 32, u16, i16, 16);
+widen!(widen16, u16, i16, 16, u32, i32, 8);
+widen!(widen32, u32, i32, 8, u64, i64, 4);
+
+//None for lane types and ops without a host SIMD version
+pub(crate) fn binary(
+    op: VectorOp,
+    ty: LaneType,
+    high: bool,
+    a: &Bytes,
+    b: &Bytes,
+    len: usize,
+) -> Option<Bytes> {
+    let out = if op.widening() {
+        let (a, b) = if high {
+            (upper(a, len), upper(b, len))
+        } else {
+            (*a, *b)
+        };
+        match ty {
+            LaneType::I8 => widen8(op, &a, &b)?,
+            LaneType::I16 => widen16(op, &a, &b)?,
+            LaneType::I32 => widen32(op, &a, &b)?,
+            _ => return None,
+        }
+    } else {
+        match ty {
+            LaneType::I8 => lanes8::binary(op, a, b)?,
+            LaneType::I16 => lanes16::binary(op, a, b)?,
+            LaneType::I32 => lanes32::binary(op, a, b)?,
+            LaneType::I64 => lanes64::binary(op, a, b)?,
+            _ => return None,
+        }
+    };
+    Some(truncate(out, len))
+}
+pub(crate) fn compare(
+    op: CompareOp,
+    ty: LaneType,
+    a: &Bytes,
+    b: &Bytes,
+    len: usize,
+) -> Option<Bytes> {
+    let out = match ty {
+        LaneType::I8 => lanes8::compare(op, a, b),
+        LaneType::I16 => lanes16::compare(op, a, b),
+        LaneType::I32 => lanes32::compare(op, a, b),
+        LaneType::I64 => lanes64::compare(op, a, b),
+        _ => return None,
+    };
+    Some(truncate(out, len))
+}
+//Unlike the other ops lanes past len keep a
+pub(crate) fn select(bits: usize, mask: &Bytes, a: &Bytes, b: &Bytes, len: usize) -> Option<Bytes> {
+    let mut out = match bits {
+        8 => lanes8::select(mask, a, b),
+        16 => lanes16::select(mask, a, b),
+        32 => lanes32::select(mask, a, b),
+        64 => lanes64::select(mask, a, b),
+        _ => return None,
+    };
+    out[len..].copy_from_slice(&a[len..]);
+    Some(out)
+}
+pub(crate) fn reduce(op: ReduceOp, ty: LaneType, a: &Bytes, len: usize) -> Option<u64> {
+    let lanes = len * 8 / ty.bits();
+    match ty {
+        LaneType::I8 => Some(lanes8::reduce(op, a, lanes)),
+        LaneType::I16 => Some(lanes16::reduce(op, a, lanes)),
+        LaneType::I32 => Some(lanes32::reduce(op, a, lanes)),
+        LaneType::I64 => Some(lanes64::reduce(op, a, lanes)),
+        _ => None,
+    }
+}
+//Upper half of the first len bytes moved to the bottom
+fn upper(bytes: &Bytes, len: usize) -> Bytes {
+    let mut out = [0u8; 32];
+    out[..len / 2].copy_from_slice(&bytes[len / 2..len]);
+    out
+}
+fn truncate(mut bytes: Bytes, len: usize) -> Bytes {
+    bytes[len..].fill(0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{float::RoundingMode, vector};
+
+    const TYPES: [LaneType; 4] = [LaneType::I8, LaneType::I16, LaneType::I32, LaneType::I64];
+    const OPS: [VectorOp; 11] = [
+        VectorOp::Add,
+        VectorOp::Sub,
+        VectorOp::Mul,
+        VectorOp::AddSat,
+        VectorOp::AddSatU,
+        VectorOp::SubSat,
+        VectorOp::SubSatU,
+        VectorOp::AddWide,
+        VectorOp::AddWideU,
+        VectorOp::MulWide,
+        VectorOp::MulWideU,
+    ];
+    const COMPARES: [CompareOp; 6] = [
+        CompareOp::Eq,
+        CompareOp::Ne,
+        CompareOp::Lt,
+        CompareOp::Le,
+        CompareOp::LtU,
+        CompareOp::LeU,
+    ];
+    const REDUCES: [ReduceOp; 7] = [
+        ReduceOp::Sum,
+        ReduceOp::Min,
+        ReduceOp::Max,
+        ReduceOp::MinU,
+        ReduceOp::MaxU,
+        ReduceOp::Any,
+        ReduceOp::All,
+    ];
+
+    //Register pairs with random bytes and bytes near the lane limits (0x00, 0x7f, 0x80, 0xff)
+    pub(super) fn inputs() -> Vec<(Bytes, Bytes)> {
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..200)
+            .map(|k| {
+                let mut pair = [[0u8; 32]; 2];
+                for v in pair.iter_mut().flatten() {
+                    let r = next();
+                    *v = match k % 2 {
+                        0 => r as u8,
+                        _ => [0x00, 0x7f, 0x80, 0xff][(r % 4) as usize],
+                    };
+                }
+                (pair[0], pair[1])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn binary_matches_scalar() {
+        for (a, b) in inputs() {
+            for ty in TYPES {
+                for op in OPS {
+                    for high in [false, true] {
+                        for len in [8, 16, 32] {
+                            let Some(out) = binary(op, ty, high, &a, &b, len) else {
+                                continue;
+                            };
+                            let rm = RoundingMode::NearestEven;
+                            let (expected, _) =
+                                vector::binary_scalar(op, ty, high, &a, &b, len, rm);
+                            assert_eq!(
+                                out, expected,
+                                "{:?} {:?} high={} len={}",
+                                ty, op, high, len
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    #[test]
+    fn compare_matches_scalar() {
+        for (a, b) in inputs() {
+            for ty in TYPES {
+                for op in COMPARES {
+                    for len in [8, 16, 32] {
+                        let out = compare(op, ty, &a, &b, len).unwrap();
+                        let (expected, _) = vector::compare_scalar(op, ty, &a, &b, len);
+                        assert_eq!(out, expected, "{:?} {:?} len={}", ty, op, len);
+                    }
+                }
+            }
+        }
+    }
+    #[test]
+    fn select_matches_scalar() {
+        let inputs = inputs();
+        for (mask, (a, b)) in inputs.iter().map(|v| v.0).zip(inputs.iter().rev()) {
+            for bits in [8, 16, 32, 64] {
+                for len in [8, 16, 32] {
+                    let out = select(bits, &mask, a, b, len).unwrap();
+                    let expected = vector::select_scalar(bits, &mask, a, b, len);
+                    assert_eq!(out, expected, "bits={} len={}", bits, len);
+                }
+            }
+        }
+    }
+    #[test]
+    fn reduce_matches_scalar() {
+        for (a, _) in inputs() {
+            for ty in TYPES {
+                for op in REDUCES {
+                    for len in [8, 16, 32] {
+                        let out = reduce(op, ty, &a, len).unwrap();
+                        let rm = RoundingMode::NearestEven;
+                        let (expected, _) = vector::reduce_scalar(op, ty, &a, len, rm);
+                        assert_eq!(out, expected, "{:?} {:?} len={}", ty, op, len);
+                    }
+                }
+            }
+        }
+    }
+}
+
+//cargo +nightly bench --features bench: i16 saturating adds and i8 compares on X registers,
+//host SIMD against the scalar path
+#[cfg(all(test, feature = "bench"))]
+mod benches {
+    extern crate test;
+
+    use super::{tests::inputs, *};
+    use crate::vm::{float::RoundingMode, vector};
+
+    #[bench]
+    fn bench_binary_simd(bench: &mut test::Bencher) {
+        let (a, b) = inputs()[0];
+        bench.iter(|| {
+            let a = test::black_box(a);
+            binary(VectorOp::AddSat, LaneType::I16, false, &a, &b, 32)
+        });
+    }
+    #[bench]
+    fn bench_binary_scalar(bench: &mut test::Bencher) {
+        let (a, b) = inputs()[0];
+        let rm = RoundingMode::NearestEven;
+        bench.iter(|| {
+            let a = test::black_box(a);
+            vector::binary_scalar(VectorOp::AddSat, LaneType::I16, false, &a, &b, 32, rm)
+        });
+    }
+    #[bench]
+    fn bench_compare_simd(bench: &mut test::Bencher) {
+        let (a, b) = inputs()[0];
+        bench.iter(|| {
+            let a = test::black_box(a);
+            compare(CompareOp::Lt, LaneType::I8, &a, &b, 32)
+        });
+    }
+    #[bench]
+    fn bench_compare_scalar(bench: &mut test::Bencher) {
+        let (a, b) = inputs()[0];
+        bench.iter(|| {
+            let a = test::black_box(a);
+            vector::compare_scalar(CompareOp::Lt, LaneType::I8, &a, &b, 32)
+        });
+    }
+}
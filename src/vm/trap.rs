@@ -0,0 +1,35 @@
+use super::s64::VmError;
+
+/*
+Guest trap table, set with tvec (0 turns traps off). It starts with the frame of the trap
+being handled, followed by the handler pc of every cause:
+    +0  pc of the faulting instruction, rtt continues there
+    +8  pc after it, copy it to +0 to skip the instruction
+    +16 cause
+    +24 value: the bad address, the opcode, the lane index, the interrupt line
+    +32 + 8*cause handler pc, 0 leaves the fault to the host
+Faults are causes 1-8, interrupt line n is cause INTERRUPTS + n.
+A fault inside a handler, or one whose frame or vector isn't in data memory, stops the run.
+*/
+
+pub(crate) const FRAME_PC: u64 = 0;
+pub(crate) const FRAME_NEXT: u64 = 8;
+pub(crate) const FRAME_CAUSE: u64 = 16;
+pub(crate) const FRAME_VALUE: u64 = 24;
+pub(crate) const VECTORS: u64 = 32;
+pub(crate) const INTERRUPTS: u64 = 16;
+
+//Cause and value stored in the frame
+pub(crate) fn cause(err: &VmError) -> (u64, u64) {
+    match *err {
+        VmError::IllegalOpcode(opcode) => (1, opcode as u64),
+        VmError::DivideByZero => (2, 0),
+        VmError::BadAddress(address) => (3, address),
+        VmError::Misaligned(address) => (4, address),
+        VmError::LaneFault { address, .. } => (5, address),
+        VmError::LaneIndex { index, .. } => (6, index as u64),
+        VmError::IntegerOverflow => (7, 0),
+        VmError::BankConflict => (8, 0),
+        VmError::DoubleFault(_) => (0, 0),
+    }
+}
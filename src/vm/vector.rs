@@ -0,0 +1,559 @@
+/*
+Lane-typed vector arithmetic on register bytes.
+Registers are viewed as little-endian byte arrays (lane 0 in the low bytes of the
+first u64), R/F/P registers as 8-byte vectors, S as 16 and X as 32.
+*/
+
+use super::{
+    float::{self, FloatOp, MathOp, RoundingMode},
+    simd,
+};
+use crate::lane::LaneType;
+
+pub(crate) type Bytes = [u8; 32];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum VectorOp {
+    Add,
+    Sub,
+    Mul,
+    AddSat,  //Signed saturating
+    AddSatU, //Unsigned saturating
+    SubSat,
+    SubSatU,
+    AddWide, //Signed widening, sources are the low (or high) half lanes
+    AddWideU,
+    MulWide,
+    MulWideU,
+}
+
+impl VectorOp {
+    pub(crate) fn widening(self) -> bool {
+        matches!(
+            self,
+            VectorOp::AddWide | VectorOp::AddWideU | VectorOp::MulWide | VectorOp::MulWideU
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ReduceOp {
+    Sum,
+    Min, //Signed for integer lanes
+    Max,
+    MinU,
+    MaxU,
+    Any, //1 if any lane is non-zero
+    All, //1 if every lane is non-zero
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CompareOp {
+    Eq,
+    Ne,
+    Lt, //Signed for integer lanes
+    Le,
+    LtU,
+    LeU,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum BitOp {
+    Popcnt,
+    Clz,
+    Ctz,
+    Bswap,
+    Bitrev,
+    Rotl, //By the matching lane of b
+    Rotr,
+}
+
+pub(crate) fn read(bytes: &Bytes, bits: usize, k: usize) -> u128 {
+    let size = bits / 8;
+    let mut val = 0u128;
+    for i in 0..size {
+        val |= (bytes[k * size + i] as u128) << (8 * i);
+    }
+    val
+}
+pub(crate) fn write(bytes: &mut Bytes, bits: usize, k: usize, val: u128) {
+    let size = bits / 8;
+    for i in 0..size {
+        bytes[k * size + i] = (val >> (8 * i)) as u8;
+    }
+}
+pub(crate) fn mask(bits: usize) -> u128 {
+    if bits >= 128 {
+        u128::MAX
+    } else {
+        (1u128 << bits) - 1
+    }
+}
+pub(crate) fn sext(val: u128, bits: usize) -> i128 {
+    let shift = 128 - bits;
+    ((val << shift) as i128) >> shift
+}
+//Lane-wise a op b over the first len bytes, returns the result and fp flags
+pub(crate) fn binary(
+    op: VectorOp,
+    ty: LaneType,
+    high: bool,
+    a: &Bytes,
+    b: &Bytes,
+    len: usize,
+    rm: RoundingMode,
+) -> (Bytes, u64) {
+    match simd::binary(op, ty, high, a, b, len) {
+        Some(out) => (out, 0),
+        None => binary_scalar(op, ty, high, a, b, len, rm),
+    }
+}
+pub(crate) fn binary_scalar(
+    op: VectorOp,
+    ty: LaneType,
+    high: bool,
+    a: &Bytes,
+    b: &Bytes,
+    len: usize,
+    rm: RoundingMode,
+) -> (Bytes, u64) {
+    let bits = ty.bits();
+    let lanes = len * 8 / bits;
+    let mut out = [0u8; 32];
+    let mut flags = 0;
+    //f64 lanes have nothing to widen into and use the plain operation
+    if op.widening() && ty != LaneType::F64 {
+        let offset = if high { lanes / 2 } else { 0 };
+        for k in 0..lanes / 2 {
+            let (x, y) = (read(a, bits, offset + k), read(b, bits, offset + k));
+            let (val, lane_flags) = widen(op, ty, x, y, rm);
+            flags |= lane_flags;
+            write(&mut out, bits * 2, k, val);
+        }
+        return (out, flags);
+    }
+    for k in 0..lanes {
+        let (x, y) = (read(a, bits, k), read(b, bits, k));
+        let (val, lane_flags) = match ty {
+            LaneType::F32 => {
+                let (val, flags) = float::op32(
+                    float_op(op),
+                    f32::from_bits(x as u32),
+                    f32::from_bits(y as u32),
+                    rm,
+                );
+                (val.to_bits() as u128, flags)
+            }
+            LaneType::F64 => {
+                let (val, flags) = float::op64(
+                    float_op(op),
+                    f64::from_bits(x as u64),
+                    f64::from_bits(y as u64),
+                    rm,
+                );
+                (val.to_bits() as u128, flags)
+            }
+            _ => (integer(op, bits, x, y), 0),
+        };
+        flags |= lane_flags;
+        write(&mut out, bits, k, val);
+    }
+    (out, flags)
+}
+fn float_op(op: VectorOp) -> FloatOp {
+    match op {
+        VectorOp::Sub | VectorOp::SubSat | VectorOp::SubSatU => FloatOp::Sub,
+        VectorOp::Mul | VectorOp::MulWide | VectorOp::MulWideU => FloatOp::Mul,
+        _ => FloatOp::Add,
+    }
+}
+fn integer(op: VectorOp, bits: usize, x: u128, y: u128) -> u128 {
+    let (min, max) = (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1);
+    let val = match op {
+        VectorOp::Add => x.wrapping_add(y),
+        VectorOp::Sub => x.wrapping_sub(y),
+        VectorOp::Mul => x.wrapping_mul(y),
+        VectorOp::AddSat => (sext(x, bits) + sext(y, bits)).clamp(min, max) as u128,
+        VectorOp::SubSat => (sext(x, bits) - sext(y, bits)).clamp(min, max) as u128,
+        VectorOp::AddSatU => (x + y).min(mask(bits)),
+        VectorOp::SubSatU => x.saturating_sub(y),
+        //Widening ops are handled by widen
+        _ => x.wrapping_add(y),
+    };
+    val & mask(bits)
+}
+fn widen(op: VectorOp, ty: LaneType, x: u128, y: u128, rm: RoundingMode) -> (u128, u64) {
+    let bits = ty.bits();
+    if ty == LaneType::F32 {
+        //f32 -> f64 is exact, so only the f64 operation rounds
+        let (x, y) = (
+            f32::from_bits(x as u32) as f64,
+            f32::from_bits(y as u32) as f64,
+        );
+        let (val, flags) = float::op64(float_op(op), x, y, rm);
+        return (val.to_bits() as u128, flags);
+    }
+    let val = match op {
+        VectorOp::AddWide => (sext(x, bits) + sext(y, bits)) as u128,
+        VectorOp::MulWide => (sext(x, bits) * sext(y, bits)) as u128,
+        VectorOp::AddWideU => x + y,
+        _ => x * y,
+    };
+    (val & mask(bits * 2), 0)
+}
+//Lane k takes lane pattern[k % 4] of its group of 4 lanes (2 bits per selector)
+pub(crate) fn shuffle(ty: LaneType, a: &Bytes, len: usize, pattern: u8) -> Bytes {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let mut out = [0u8; 32];
+    for k in 0..lanes {
+        let select = (pattern >> (2 * (k % 4))) as usize & 3;
+        write(
+            &mut out,
+            bits,
+            k,
+            read(a, bits, (k / 4 * 4 + select) % lanes),
+        );
+    }
+    out
+}
+//Lane k takes table[index[k]], out of range indices give 0
+pub(crate) fn permute(ty: LaneType, table: &Bytes, index: &Bytes, len: usize) -> Bytes {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let mut out = [0u8; 32];
+    for k in 0..lanes {
+        let i = read(index, bits, k);
+        if i < lanes as u128 {
+            write(&mut out, bits, k, read(table, bits, i as usize));
+        }
+    }
+    out
+}
+pub(crate) fn broadcast(ty: LaneType, val: u128, len: usize) -> Bytes {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let mut out = [0u8; 32];
+    for k in 0..lanes {
+        write(&mut out, bits, k, val);
+    }
+    out
+}
+//a0 b0 a1 b1 ... from the low (or high) half lanes of a and b
+pub(crate) fn zip(ty: LaneType, a: &Bytes, b: &Bytes, len: usize, high: bool) -> Bytes {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let offset = if high { lanes / 2 } else { 0 };
+    let mut out = [0u8; 32];
+    for k in 0..lanes / 2 {
+        write(&mut out, bits, 2 * k, read(a, bits, offset + k));
+        write(&mut out, bits, 2 * k + 1, read(b, bits, offset + k));
+    }
+    out
+}
+//Even (or odd) lanes of a followed by those of b
+pub(crate) fn unzip(ty: LaneType, a: &Bytes, b: &Bytes, len: usize, odd: bool) -> Bytes {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let mut out = [0u8; 32];
+    for k in 0..lanes / 2 {
+        let i = 2 * k + odd as usize;
+        write(&mut out, bits, k, read(a, bits, i));
+        write(&mut out, bits, lanes / 2 + k, read(b, bits, i));
+    }
+    out
+}
+//Folds the lanes of a in order, returns the result and fp flags
+pub(crate) fn reduce(
+    op: ReduceOp,
+    ty: LaneType,
+    a: &Bytes,
+    len: usize,
+    rm: RoundingMode,
+) -> (u64, u64) {
+    if len == 0 {
+        return (0, 0);
+    }
+    match simd::reduce(op, ty, a, len) {
+        Some(val) => (val, 0),
+        None => reduce_scalar(op, ty, a, len, rm),
+    }
+}
+pub(crate) fn reduce_scalar(
+    op: ReduceOp,
+    ty: LaneType,
+    a: &Bytes,
+    len: usize,
+    rm: RoundingMode,
+) -> (u64, u64) {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    if lanes == 0 {
+        return (0, 0);
+    }
+    match op {
+        ReduceOp::Any => return ((0..lanes).any(|k| read(a, bits, k) != 0) as u64, 0),
+        ReduceOp::All => return ((0..lanes).all(|k| read(a, bits, k) != 0) as u64, 0),
+        _ => {}
+    }
+    let mut acc = read(a, bits, 0);
+    let mut flags = 0;
+    for k in 1..lanes {
+        let x = read(a, bits, k);
+        let (val, lane_flags) = match ty {
+            LaneType::F32 => {
+                let (x, y) = (f32::from_bits(acc as u32), f32::from_bits(x as u32));
+                let (val, flags) = match reduce_math(op) {
+                    Some(math) => float::math32(math, x, y, rm),
+                    None => float::op32(FloatOp::Add, x, y, rm),
+                };
+                (val.to_bits() as u128, flags)
+            }
+            LaneType::F64 => {
+                let (x, y) = (f64::from_bits(acc as u64), f64::from_bits(x as u64));
+                let (val, flags) = match reduce_math(op) {
+                    Some(math) => float::math64(math, x, y, rm),
+                    None => float::op64(FloatOp::Add, x, y, rm),
+                };
+                (val.to_bits() as u128, flags)
+            }
+            _ => {
+                let val = match op {
+                    ReduceOp::Min => sext(acc, bits).min(sext(x, bits)) as u128,
+                    ReduceOp::Max => sext(acc, bits).max(sext(x, bits)) as u128,
+                    ReduceOp::MinU => acc.min(x),
+                    ReduceOp::MaxU => acc.max(x),
+                    _ => acc.wrapping_add(x),
+                };
+                (val & mask(bits), 0)
+            }
+        };
+        acc = val;
+        flags |= lane_flags;
+    }
+    (acc as u64, flags)
+}
+//Float lanes have no unsigned order, MinU/MaxU behave like Min/Max
+fn reduce_math(op: ReduceOp) -> Option<MathOp> {
+    match op {
+        ReduceOp::Min | ReduceOp::MinU => Some(MathOp::Min),
+        ReduceOp::Max | ReduceOp::MaxU => Some(MathOp::Max),
+        _ => None,
+    }
+}
+//Lanes of all ones where a op b holds, zeros elsewhere, returns the mask and fp flags
+pub(crate) fn compare(
+    op: CompareOp,
+    ty: LaneType,
+    a: &Bytes,
+    b: &Bytes,
+    len: usize,
+) -> (Bytes, u64) {
+    match simd::compare(op, ty, a, b, len) {
+        Some(out) => (out, 0),
+        None => compare_scalar(op, ty, a, b, len),
+    }
+}
+pub(crate) fn compare_scalar(
+    op: CompareOp,
+    ty: LaneType,
+    a: &Bytes,
+    b: &Bytes,
+    len: usize,
+) -> (Bytes, u64) {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let mut out = [0u8; 32];
+    let mut flags = 0;
+    for k in 0..lanes {
+        let (x, y) = (read(a, bits, k), read(b, bits, k));
+        let (ordering, lane_flags) = match ty {
+            LaneType::F32 => {
+                let (x, y) = (f32::from_bits(x as u32), f32::from_bits(y as u32));
+                let signaling = float::is_snan32(x) || float::is_snan32(y);
+                compare_float(op, x.partial_cmp(&y), signaling)
+            }
+            LaneType::F64 => {
+                let (x, y) = (f64::from_bits(x as u64), f64::from_bits(y as u64));
+                let signaling = float::is_snan64(x) || float::is_snan64(y);
+                compare_float(op, x.partial_cmp(&y), signaling)
+            }
+            _ => match op {
+                CompareOp::Lt | CompareOp::Le => (Some(sext(x, bits).cmp(&sext(y, bits))), 0),
+                _ => (Some(x.cmp(&y)), 0),
+            },
+        };
+        flags |= lane_flags;
+        let holds = match (op, ordering) {
+            (CompareOp::Eq, Some(v)) => v.is_eq(),
+            (CompareOp::Ne, v) => v.is_none_or(|v| v.is_ne()),
+            (CompareOp::Lt | CompareOp::LtU, Some(v)) => v.is_lt(),
+            (CompareOp::Le | CompareOp::LeU, Some(v)) => v.is_le(),
+            _ => false,
+        };
+        if holds {
+            write(&mut out, bits, k, mask(bits));
+        }
+    }
+    (out, flags)
+}
+//Eq/Ne are quiet and only signal on sNaN, ordered comparisons signal on any NaN
+fn compare_float(
+    op: CompareOp,
+    ordering: Option<std::cmp::Ordering>,
+    signaling: bool,
+) -> (Option<std::cmp::Ordering>, u64) {
+    let quiet = matches!(op, CompareOp::Eq | CompareOp::Ne);
+    if signaling || (ordering.is_none() && !quiet) {
+        (ordering, float::FLAG_INVALID)
+    } else {
+        (ordering, 0)
+    }
+}
+//Lane k of b where lane k of mask is non-zero, otherwise lane k of a
+pub(crate) fn select(bits: usize, mask: &Bytes, a: &Bytes, b: &Bytes, len: usize) -> Bytes {
+    simd::select(bits, mask, a, b, len).unwrap_or_else(|| select_scalar(bits, mask, a, b, len))
+}
+pub(crate) fn select_scalar(bits: usize, mask: &Bytes, a: &Bytes, b: &Bytes, len: usize) -> Bytes {
+    let mut out = *a;
+    for k in 0..len * 8 / bits {
+        if read(mask, bits, k) != 0 {
+            write(&mut out, bits, k, read(b, bits, k));
+        }
+    }
+    out
+}
+//Lane-wise bit manipulation, float lanes are treated as integers of the same size
+pub(crate) fn bits(op: BitOp, ty: LaneType, a: &Bytes, b: &Bytes, len: usize) -> Bytes {
+    let (bits, lanes) = (ty.bits(), len * 8 / ty.bits());
+    let mut out = [0u8; 32];
+    for k in 0..lanes {
+        let (x, y) = (read(a, bits, k) as u64, read(b, bits, k) as u64);
+        write(&mut out, bits, k, bit_op(op, x, y, bits as u32) as u128);
+    }
+    out
+}
+//op on the low bits of x
+pub(crate) fn bit_op(op: BitOp, x: u64, y: u64, bits: u32) -> u64 {
+    let shift = 64 - bits;
+    let val = match op {
+        BitOp::Popcnt => x.count_ones() as u64,
+        BitOp::Clz => (x << shift).leading_zeros().min(bits) as u64,
+        BitOp::Ctz => x.trailing_zeros().min(bits) as u64,
+        BitOp::Bswap => (x << shift).swap_bytes(),
+        BitOp::Bitrev => (x << shift).reverse_bits(),
+        BitOp::Rotl | BitOp::Rotr => {
+            let amount = (y % bits as u64) as u32;
+            let amount = if op == BitOp::Rotl {
+                amount
+            } else {
+                (bits - amount) % bits
+            };
+            match amount {
+                0 => x,
+                _ => (x << amount) | (x >> (bits - amount)),
+            }
+        }
+    };
+    val & (u64::MAX >> shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lanes32(vals: &[u32]) -> Bytes {
+        let mut out = [0u8; 32];
+        for (k, v) in vals.iter().enumerate() {
+            write(&mut out, 32, k, *v as u128);
+        }
+        out
+    }
+    fn values32(bytes: &Bytes, len: usize) -> Vec<u32> {
+        (0..len * 8 / 32)
+            .map(|k| read(bytes, 32, k) as u32)
+            .collect()
+    }
+
+    #[test]
+    fn shuffles_and_permutes_move_lanes() {
+        let a = lanes32(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let reverse = 0b00_01_10_11;
+        assert_eq!(
+            values32(&shuffle(LaneType::I32, &a, 32, reverse), 32),
+            [3, 2, 1, 0, 7, 6, 5, 4]
+        );
+        assert_eq!(values32(&shuffle(LaneType::I32, &a, 16, 0), 16), [0; 4]);
+        let table = lanes32(&[10, 11, 12, 13, 14, 15, 16, 17]);
+        let index = lanes32(&[7, 0, 8, 1, 3, 3, u32::MAX, 2]);
+        let out = permute(LaneType::I32, &table, &index, 32);
+        assert_eq!(values32(&out, 32), [17, 10, 0, 11, 13, 13, 0, 12]);
+        let out = broadcast(LaneType::I16, 0xABCD, 16);
+        assert_eq!(out[..16], [0xCD, 0xAB].repeat(8));
+        assert_eq!(out[16..], [0; 16]);
+    }
+    #[test]
+    fn zip_and_unzip_interleave() {
+        let (a, b) = (
+            lanes32(&[0, 1, 2, 3, 4, 5, 6, 7]),
+            lanes32(&[10, 11, 12, 13, 14, 15, 16, 17]),
+        );
+        let low = zip(LaneType::I32, &a, &b, 32, false);
+        let high = zip(LaneType::I32, &a, &b, 32, true);
+        assert_eq!(values32(&low, 32), [0, 10, 1, 11, 2, 12, 3, 13]);
+        assert_eq!(values32(&high, 32), [4, 14, 5, 15, 6, 16, 7, 17]);
+        assert_eq!(
+            values32(&unzip(LaneType::I32, &low, &high, 32, false), 32),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        assert_eq!(
+            values32(&unzip(LaneType::I32, &low, &high, 32, true), 32),
+            [10, 11, 12, 13, 14, 15, 16, 17]
+        );
+    }
+    #[test]
+    fn reductions_fold_every_lane() {
+        let rm = RoundingMode::NearestEven;
+        let a = lanes32(&[5, u32::MAX, 7, 0]);
+        assert_eq!(reduce(ReduceOp::Sum, LaneType::I32, &a, 16, rm), (11, 0));
+        assert_eq!(
+            reduce(ReduceOp::Min, LaneType::I32, &a, 16, rm),
+            (u32::MAX as u64, 0)
+        );
+        assert_eq!(reduce(ReduceOp::Max, LaneType::I32, &a, 16, rm), (7, 0));
+        assert_eq!(reduce(ReduceOp::MinU, LaneType::I32, &a, 16, rm), (0, 0));
+        assert_eq!(
+            reduce(ReduceOp::MaxU, LaneType::I32, &a, 16, rm),
+            (u32::MAX as u64, 0)
+        );
+        assert_eq!(reduce(ReduceOp::Any, LaneType::I32, &a, 16, rm), (1, 0));
+        assert_eq!(reduce(ReduceOp::All, LaneType::I32, &a, 16, rm), (0, 0));
+        assert_eq!(reduce(ReduceOp::All, LaneType::I32, &a, 8, rm), (1, 0));
+        let f = lanes32(&[1.5f32, -2.0, 4.0, 0.25].map(f32::to_bits));
+        assert_eq!(
+            reduce(ReduceOp::Sum, LaneType::F32, &f, 16, rm),
+            (3.75f32.to_bits() as u64, 0)
+        );
+        assert_eq!(
+            reduce(ReduceOp::Min, LaneType::F32, &f, 16, rm),
+            ((-2f32).to_bits() as u64, 0)
+        );
+    }
+    #[test]
+    fn bit_ops_match_the_primitive_types() {
+        macro_rules! check {
+            ($t:ty, $x:expr, $y:expr) => {{
+                let (x, y, bits) = ($x as $t, $y as u32, <$t>::BITS);
+                let op = |op| bit_op(op, x as u64, y as u64, bits);
+                assert_eq!(op(BitOp::Popcnt), x.count_ones() as u64);
+                assert_eq!(op(BitOp::Clz), x.leading_zeros() as u64);
+                assert_eq!(op(BitOp::Ctz), x.trailing_zeros() as u64);
+                assert_eq!(op(BitOp::Bswap), x.swap_bytes() as u64);
+                assert_eq!(op(BitOp::Bitrev), x.reverse_bits() as u64);
+                assert_eq!(op(BitOp::Rotl), x.rotate_left(y) as u64, "{:?} {}", x, y);
+                assert_eq!(op(BitOp::Rotr), x.rotate_right(y) as u64, "{:?} {}", x, y);
+            }};
+        }
+        for x in [0u64, 1, 0x80, 0x1234_5678_9ABC_DEF0, u64::MAX] {
+            for y in [0, 1, 7, 8, 15, 31, 33, 64, 100] {
+                check!(u8, x, y);
+                check!(u16, x, y);
+                check!(u32, x, y);
+                check!(u64, x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+/*
+Whole-register integer arithmetic, S registers as u128/i128 and X registers as u256/i256.
+Values are little-endian u64 limbs, only the first n limbs take part.
+*/
+
+use std::cmp::Ordering;
+
+pub(crate) type Limbs = [u64; 4];
+
+//a + b + carry, returns the sum and the carry out
+pub(crate) fn add(a: &Limbs, b: &Limbs, carry: bool, n: usize) -> (Limbs, bool) {
+    let mut out = [0u64; 4];
+    let mut carry = carry;
+    for k in 0..n {
+        let (val, c1) = a[k].overflowing_add(b[k]);
+        let (val, c2) = val.overflowing_add(carry as u64);
+        out[k] = val;
+        carry = c1 || c2;
+    }
+    (out, carry)
+}
+//a - b - borrow, returns the difference and the borrow out
+pub(crate) fn sub(a: &Limbs, b: &Limbs, borrow: bool, n: usize) -> (Limbs, bool) {
+    let mut out = [0u64; 4];
+    let mut borrow = borrow;
+    for k in 0..n {
+        let (val, b1) = a[k].overflowing_sub(b[k]);
+        let (val, b2) = val.overflowing_sub(borrow as u64);
+        out[k] = val;
+        borrow = b1 || b2;
+    }
+    (out, borrow)
+}
+//Full double-width product, returns the low and high halves
+pub(crate) fn mul(a: &Limbs, b: &Limbs, n: usize, signed: bool) -> (Limbs, Limbs) {
+    let mut product = [0u64; 8];
+    for i in 0..n {
+        let mut carry = 0u128;
+        for j in 0..n {
+            let val = a[i] as u128 * b[j] as u128 + product[i + j] as u128 + carry;
+            product[i + j] = val as u64;
+            carry = val >> 64;
+        }
+        product[i + n] = carry as u64;
+    }
+    let (mut low, mut high) = ([0u64; 4], [0u64; 4]);
+    low[..n].copy_from_slice(&product[..n]);
+    high[..n].copy_from_slice(&product[n..2 * n]);
+    //Two's complement correction of the unsigned product's high half
+    if signed {
+        if negative(a, n) {
+            high = sub(&high, b, false, n).0;
+        }
+        if negative(b, n) {
+            high = sub(&high, a, false, n).0;
+        }
+    }
+    (low, high)
+}
+//Shift amounts of n * 64 bits or more clear the register (or fill it with the sign)
+pub(crate) fn shl(a: &Limbs, amount: u64, n: usize) -> Limbs {
+    let mut out = [0u64; 4];
+    for bit in 0..n * 64 {
+        if let Some(src) = (bit as u64).checked_sub(amount) {
+            set_bit(&mut out, bit, get_bit(a, src as usize));
+        }
+    }
+    out
+}
+pub(crate) fn shr(a: &Limbs, amount: u64, n: usize, arithmetic: bool) -> Limbs {
+    let fill = arithmetic && negative(a, n);
+    let mut out = [0u64; 4];
+    for bit in 0..n * 64 {
+        let src = (bit as u64).saturating_add(amount);
+        let val = if src < (n * 64) as u64 {
+            get_bit(a, src as usize)
+        } else {
+            fill
+        };
+        set_bit(&mut out, bit, val);
+    }
+    out
+}
+//Rotate amounts wrap around the register width
+pub(crate) fn rotl(a: &Limbs, amount: u64, n: usize) -> Limbs {
+    let bits = (n * 64) as u64;
+    if bits == 0 {
+        return [0u64; 4];
+    }
+    let mut out = [0u64; 4];
+    for bit in 0..bits {
+        let src = (bit + bits - amount % bits) % bits;
+        set_bit(&mut out, bit as usize, get_bit(a, src as usize));
+    }
+    out
+}
+pub(crate) fn rotr(a: &Limbs, amount: u64, n: usize) -> Limbs {
+    let bits = (n * 64) as u64;
+    if bits == 0 {
+        return [0u64; 4];
+    }
+    rotl(a, bits - amount % bits, n)
+}
+pub(crate) fn cmp(a: &Limbs, b: &Limbs, n: usize, signed: bool) -> Ordering {
+    //Differing signs order the other way round than the raw limbs
+    if signed && negative(a, n) != negative(b, n) {
+        return negative(b, n).cmp(&negative(a, n));
+    }
+    (0..n)
+        .rev()
+        .map(|k| a[k].cmp(&b[k]))
+        .find(|v| v.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+fn negative(a: &Limbs, n: usize) -> bool {
+    n > 0 && a[n - 1] >> 63 == 1
+}
+fn get_bit(a: &Limbs, bit: usize) -> bool {
+    (a[bit / 64] >> (bit % 64)) & 1 == 1
+}
+fn set_bit(a: &mut Limbs, bit: usize, val: bool) {
+    a[bit / 64] |= (val as u64) << (bit % 64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [u128; 6] = [
+        0,
+        1,
+        u64::MAX as u128,
+        1 << 64,
+        u128::MAX,
+        0x8000_0000_0000_0000_1234_5678_9ABC_DEF0,
+    ];
+
+    fn limbs(v: u128) -> Limbs {
+        [v as u64, (v >> 64) as u64, 0, 0]
+    }
+    #[test]
+    fn two_limbs_match_u128() {
+        for a in SAMPLES {
+            for b in SAMPLES {
+                let (sum, carry) = add(&limbs(a), &limbs(b), false, 2);
+                assert_eq!(
+                    (sum, carry),
+                    (limbs(a.wrapping_add(b)), a.checked_add(b).is_none())
+                );
+                let (diff, borrow) = sub(&limbs(a), &limbs(b), true, 2);
+                let expected = a.wrapping_sub(b).wrapping_sub(1);
+                assert_eq!((diff, borrow), (limbs(expected), a <= b));
+                assert_eq!(
+                    mul(&limbs(a), &limbs(b), 2, false).0,
+                    limbs(a.wrapping_mul(b))
+                );
+                assert_eq!(
+                    cmp(&limbs(a), &limbs(b), 2, false),
+                    a.cmp(&b),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    cmp(&limbs(a), &limbs(b), 2, true),
+                    (a as i128).cmp(&(b as i128))
+                );
+            }
+            for amount in [0, 1, 63, 64, 65, 127, 128, 1000] {
+                let shifted = a.checked_shl(amount as u32).unwrap_or(0);
+                assert_eq!(
+                    shl(&limbs(a), amount, 2),
+                    limbs(shifted),
+                    "{:?} {}",
+                    a,
+                    amount
+                );
+                let shifted = a.checked_shr(amount as u32).unwrap_or(0);
+                assert_eq!(shr(&limbs(a), amount, 2, false), limbs(shifted));
+                let shifted = (a as i128) >> amount.min(127);
+                assert_eq!(shr(&limbs(a), amount, 2, true), limbs(shifted as u128));
+                let rotated = a.rotate_left((amount % 128) as u32);
+                assert_eq!(rotl(&limbs(a), amount, 2), limbs(rotated));
+                assert_eq!(rotr(&limbs(rotated), amount, 2), limbs(a));
+            }
+        }
+    }
+    #[test]
+    fn products_keep_the_high_half() {
+        for a in [0, 1, 7, u64::MAX, 1 << 63] {
+            for b in [0, 1, 3, u64::MAX, 1 << 63] {
+                let (low, high) = mul(&[a, 0, 0, 0], &[b, 0, 0, 0], 1, false);
+                let product = a as u128 * b as u128;
+                assert_eq!((low[0], high[0]), (product as u64, (product >> 64) as u64));
+                let (low, high) = mul(&[a, 0, 0, 0], &[b, 0, 0, 0], 1, true);
+                let product = a as i64 as i128 * b as i64 as i128;
+                assert_eq!(
+                    (low[0], high[0]),
+                    (product as u64, (product >> 64) as u64),
+                    "{} {}",
+                    a,
+                    b
+                );
+            }
+        }
+        let max = [u64::MAX; 4];
+        assert_eq!(
+            mul(&max, &max, 4, false),
+            ([1, 0, 0, 0], [u64::MAX - 1, u64::MAX, u64::MAX, u64::MAX])
+        );
+        assert_eq!(mul(&max, &max, 4, true), ([1, 0, 0, 0], [0; 4]));
+    }
+    #[test]
+    fn carries_cross_all_four_limbs() {
+        let max = [u64::MAX; 4];
+        assert_eq!(add(&max, &[1, 0, 0, 0], false, 4), ([0; 4], true));
+        assert_eq!(add(&max, &[0; 4], true, 3), ([0, 0, 0, 0], true));
+        assert_eq!(sub(&[0; 4], &[1, 0, 0, 0], false, 4), (max, true));
+        assert_eq!(shl(&[1, 0, 0, 0], 200, 4), [0, 0, 0, 1 << 8]);
+        assert_eq!(shr(&[0, 0, 0, 1 << 63], 255, 4, false), [1, 0, 0, 0]);
+        assert_eq!(shr(&[0, 0, 0, 1 << 63], 300, 4, true), max);
+        assert_eq!(rotl(&[0, 0, 0, 1 << 63], 1, 4), [1, 0, 0, 0]);
+        assert_eq!(
+            cmp(&[0, 0, 0, 1 << 63], &[1, 0, 0, 0], 4, true),
+            Ordering::Less
+        );
+        assert_eq!(
+            cmp(&[0, 0, 0, 1 << 63], &[1, 0, 0, 0], 4, false),
+            Ordering::Greater
+        );
+    }
+}